@@ -0,0 +1,251 @@
+//! Compiled test binaries kept between runs for `--binary-cache`.
+//!
+//! A package's binary is built once with `go test -c` and stored under
+//! `$XDG_CACHE_HOME/gotestfinder/bin` (`~/.cache/gotestfinder/bin` by
+//! default), named after a hash of the sources it is built from and the
+//! build settings. An edit anywhere in the module, including `go.mod` and
+//! `go.sum`, in a module a local `replace` directive points to, or in the
+//! `go.work` workspace and its modules, changes the hash and leads to a
+//! rebuild. The sizes and modification times of those files are recorded
+//! next to the binaries, so the sources are only read again once one of
+//! them changed.
+
+use crate::gomod;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Source files that go into a test binary.
+const SOURCE_EXTENSIONS: &[&str] = &["go", "s", "c", "h", "syso"];
+
+pub struct BinaryCache {
+    dir: PathBuf,
+}
+
+impl BinaryCache {
+    pub fn open() -> Option<BinaryCache> {
+        let dir = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(BinaryCache {
+            dir: dir.join("gotestfinder").join("bin"),
+        })
+    }
+
+    /// The path of the binary for the package in `package_dir` built with
+    /// `settings`, which exists if it was built before. `workspace_off`
+    /// tells that the build ignores `go.work`.
+    pub fn binary(
+        &self,
+        package_dir: &Path,
+        settings: &[String],
+        workspace_off: bool,
+    ) -> Option<PathBuf> {
+        let package_dir = package_dir.canonicalize().ok()?;
+        let package = package_id(&package_dir);
+        let files = source_files(&package_dir, workspace_off);
+
+        let mut stat = Fnv::new();
+        write_settings(&mut stat, settings);
+        for file in &files {
+            stat.write(file.to_string_lossy().as_bytes());
+            stat.write(&[0]);
+            if let Ok(metadata) = std::fs::metadata(file) {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .unwrap_or_default();
+                stat.write(&metadata.len().to_le_bytes());
+                stat.write(&modified.as_nanos().to_le_bytes());
+            }
+        }
+        let stat = format!("{:016x}", stat.finish());
+
+        // The binary named when the files last looked the same.
+        let stamp = self.dir.join(format!("{:016x}.stamp", package));
+        if let Ok(content) = std::fs::read_to_string(&stamp)
+            && let Some((recorded, name)) = content.trim().split_once(' ')
+            && recorded == stat
+        {
+            return Some(self.dir.join(name));
+        }
+
+        let mut hash = Fnv::new();
+        write_settings(&mut hash, settings);
+        for file in &files {
+            if let Ok(content) = std::fs::read(file) {
+                hash.write(file.to_string_lossy().as_bytes());
+                hash.write(&[0]);
+                hash.write(&content);
+            }
+        }
+        let name = format!("{:016x}-{:016x}.test", package, hash.finish());
+
+        if self.prepare().is_ok() {
+            let _ = std::fs::write(&stamp, format!("{} {}\n", stat, name));
+        }
+        Some(self.dir.join(name))
+    }
+
+    pub fn prepare(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)
+    }
+
+    /// Removes the other binaries of the same package, which are outdated
+    /// once `binary` is built.
+    pub fn prune(&self, binary: &Path) {
+        let Some(name) = binary.file_name().and_then(|name| name.to_str()) else {
+            return;
+        };
+        let Some((package, _)) = name.split_once('-') else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let other = entry.file_name();
+            let other = other.to_string_lossy();
+            if other != name && other.starts_with(&format!("{}-", package)) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+fn package_id(package_dir: &Path) -> u64 {
+    let mut hash = Fnv::new();
+    hash.write(package_dir.to_string_lossy().as_bytes());
+    hash.finish()
+}
+
+fn write_settings(hash: &mut Fnv, settings: &[String]) {
+    for setting in settings {
+        hash.write(setting.as_bytes());
+        hash.write(&[0]);
+    }
+}
+
+/// The files a test binary of `package_dir` can be built from: those of its
+/// module, of the directories its local `replace` directives name and,
+/// unless `workspace_off`, the `go.work` file and the files of the
+/// workspace's other modules and local replacements.
+fn source_files(package_dir: &Path, workspace_off: bool) -> Vec<PathBuf> {
+    let module = gomod::find_module(package_dir);
+    let root = module
+        .as_ref()
+        .map_or(package_dir.to_path_buf(), |module| module.root.clone());
+    let mut modules = vec![root.clone()];
+    let mut files = Vec::new();
+
+    if !workspace_off
+        && let Some(module) = &module
+        && let Some(workspace) = gomod::find_workspace(&module.root)
+        && workspace.contains(module)
+    {
+        files.push(workspace.file.clone());
+        files.push(workspace.file.with_extension("work.sum"));
+        modules.extend(workspace.modules.iter().cloned());
+        if let (Some(dir), Ok(content)) = (
+            workspace.file.parent(),
+            std::fs::read_to_string(&workspace.file),
+        ) {
+            modules.extend(replaced_dirs(dir, &content));
+        }
+    }
+
+    // Replacements can name further modules with replacements of their own.
+    let mut index = 0;
+    while index < modules.len() {
+        let dir = modules[index].clone();
+        if let Ok(content) = std::fs::read_to_string(dir.join("go.mod")) {
+            for replaced in replaced_dirs(&dir, &content) {
+                if !modules.contains(&replaced) {
+                    modules.push(replaced);
+                }
+            }
+        }
+        index += 1;
+    }
+
+    let mut seen = Vec::new();
+    for dir in modules {
+        if seen.contains(&dir) {
+            continue;
+        }
+        module_files(&dir, package_dir, &mut files);
+        seen.push(dir);
+    }
+    files.retain(|file| file.is_file());
+    files
+}
+
+/// The directories of the local `replace` directives in a `go.mod` or
+/// `go.work` file of `dir`.
+fn replaced_dirs(dir: &Path, content: &str) -> Vec<PathBuf> {
+    gomod::local_replacements(content)
+        .into_iter()
+        .filter_map(|path| dir.join(path).canonicalize().ok())
+        .collect()
+}
+
+/// Collects the files of the module in `root` a test binary of `package_dir`
+/// can depend on: the non-test sources of every package in the module, the
+/// package's own test files, and `go.mod`/`go.sum`. Nested modules and
+/// directories `go` ignores are left out.
+fn module_files(root: &Path, package_dir: &Path, files: &mut Vec<PathBuf>) {
+    files.push(root.join("go.mod"));
+    files.push(root.join("go.sum"));
+
+    let walker = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            if entry.depth() == 0 || !entry.file_type().is_dir() {
+                return true;
+            }
+            let name = entry.file_name().to_string_lossy();
+            !(name.starts_with('.')
+                || name.starts_with('_')
+                || name == "testdata"
+                || entry.path().join("go.mod").exists())
+        });
+
+    for entry in walker.flatten() {
+        let path = entry.path();
+        if !entry.file_type().is_file()
+            || !path
+                .extension()
+                .is_some_and(|ext| SOURCE_EXTENSIONS.iter().any(|e| ext == *e))
+        {
+            continue;
+        }
+        let is_test = path.to_string_lossy().ends_with("_test.go");
+        if is_test && path.parent() != Some(package_dir) {
+            continue;
+        }
+        files.push(path.to_path_buf());
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the std hasher is stable across Rust
+/// releases and so fit for names kept on disk.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -0,0 +1,75 @@
+//! Copying text to the system clipboard.
+//!
+//! The platform's clipboard tool is used when one is installed: `pbcopy` on
+//! macOS, `wl-copy` under Wayland, `xclip` or `xsel` under X11 and
+//! `clip.exe` on Windows and WSL. Without one, the text is sent to the
+//! terminal as an OSC 52 sequence, which most terminal emulators (and tmux
+//! with `set-clipboard on`) put on the clipboard, also over SSH.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Clipboard tools with their arguments, in order of preference.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Puts `text` on the clipboard and returns the name of the tool that did.
+pub fn copy(text: &str) -> Result<&'static str> {
+    for (tool, args) in TOOLS {
+        if *tool == "wl-copy" && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            continue;
+        }
+        if matches!(*tool, "xclip" | "xsel") && std::env::var_os("DISPLAY").is_none() {
+            continue;
+        }
+        if which::which(tool).is_err() {
+            continue;
+        }
+
+        let mut child = Command::new(tool)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", tool))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(tool);
+        }
+    }
+
+    let mut tty = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .context("No clipboard tool found and no terminal to copy through")?;
+    write!(tty, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    Ok("the terminal")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
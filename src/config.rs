@@ -0,0 +1,159 @@
+//! Configuration files.
+//!
+//! Settings are read from the user's `$XDG_CONFIG_HOME/gotestfinder/config.toml`
+//! and then from the nearest `.gotestfinder.toml` in the scanned directory or
+//! one of its ancestors, so a project file overrides the user's. Both use a
+//! small subset of TOML: `[section]` headers followed by `key = "value"` lines.
+//!
+//! ```toml
+//! [env]
+//! DATABASE_URL = "postgres://localhost/test"
+//! TZ = "UTC"
+//!
+//! [keys]
+//! ctrl-r = "race"
+//!
+//! [ui]
+//! theme = "dark"
+//! ```
+
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+
+/// Name of the per-project configuration file.
+const PROJECT_FILE: &str = ".gotestfinder.toml";
+
+#[derive(Debug, Default)]
+pub struct Config {
+    /// Environment variables set for `go test` runs, from `[env]`.
+    pub env: Vec<(String, String)>,
+    /// Selector key bindings to actions, from `[keys]`.
+    pub keys: Vec<(String, String)>,
+    /// Selector colors, from `theme` in `[ui]`.
+    pub theme: Option<String>,
+}
+
+impl Config {
+    /// Loads the user configuration and the project configuration for `dir`.
+    pub fn load(dir: &Path) -> Result<Config> {
+        let mut config = Config::default();
+        for file in [user_file(), project_file(dir)].into_iter().flatten() {
+            config.read(&file)?;
+        }
+        Ok(config)
+    }
+
+    fn read(&mut self, file: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        self.parse(&content)
+            .with_context(|| format!("Invalid configuration in {}", file.display()))
+    }
+
+    fn parse(&mut self, content: &str) -> Result<()> {
+        let mut section = String::new();
+
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                bail!("line {}: expected `key = value`", number + 1);
+            };
+            let key = unquote(key.trim()).with_context(|| format!("line {}", number + 1))?;
+            let value = unquote(value.trim()).with_context(|| format!("line {}", number + 1))?;
+
+            // Unknown sections are left alone so that newer files still load.
+            match section.as_str() {
+                "env" => set(&mut self.env, key, value),
+                "keys" => set(&mut self.keys, key, value),
+                "ui" if key == "theme" => self.theme = Some(value),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sets `key` in an ordered list of settings, replacing an earlier value.
+fn set(settings: &mut Vec<(String, String)>, key: String, value: String) {
+    match settings.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, v)) => *v = value,
+        None => settings.push((key, value)),
+    }
+}
+
+/// Reads a bare, basic (`"..."`, with escapes) or literal (`'...'`) string,
+/// dropping a trailing comment.
+fn unquote(text: &str) -> Result<String> {
+    if let Some(rest) = text.strip_prefix('\'') {
+        let Some((value, tail)) = rest.split_once('\'') else {
+            bail!("unterminated string");
+        };
+        check_tail(tail)?;
+        return Ok(value.to_string());
+    }
+
+    let Some(rest) = text.strip_prefix('"') else {
+        let value = text.split('#').next().unwrap_or_default().trim();
+        if value.is_empty() {
+            bail!("missing value");
+        }
+        return Ok(value.to_string());
+    };
+
+    let mut value = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                check_tail(&rest[i + 1..])?;
+                return Ok(value);
+            }
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some('\\') => value.push('\\'),
+                Some('"') => value.push('"'),
+                Some(c) => bail!("unknown escape `\\{}`", c),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    bail!("unterminated string")
+}
+
+/// Only a comment may follow a quoted string.
+fn check_tail(tail: &str) -> Result<()> {
+    let tail = tail.trim();
+    if !tail.is_empty() && !tail.starts_with('#') {
+        bail!("unexpected `{}` after string", tail);
+    }
+    Ok(())
+}
+
+fn user_file() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    let file = dir.join("gotestfinder").join("config.toml");
+    file.is_file().then_some(file)
+}
+
+fn project_file(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|file| file.is_file())
+}
@@ -0,0 +1,533 @@
+//! Build constraint parsing and evaluation.
+//!
+//! Files guarded by `//go:build` (or the older `// +build`) lines, or named
+//! with a `_GOOS`/`_GOARCH` suffix, are only compiled when the constraint
+//! holds, so their tests can only be run with a matching set of build tags
+//! on a matching target.
+
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Tag(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn eval(&self, tags: &BuildTags) -> bool {
+        self.eval_with(&|tag| tags.is_satisfied(tag))
+    }
+
+    fn eval_with(&self, holds: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            Expr::Tag(tag) => holds(tag),
+            Expr::Not(expr) => !expr.eval_with(holds),
+            Expr::And(lhs, rhs) => lhs.eval_with(holds) && rhs.eval_with(holds),
+            Expr::Or(lhs, rhs) => lhs.eval_with(holds) || rhs.eval_with(holds),
+        }
+    }
+
+    /// The tags in the expression that only `-tags` sets, as opposed to the
+    /// platform and toolchain tags `go` sets by itself.
+    pub fn custom_tags(&self) -> Vec<&str> {
+        let mut custom = Vec::new();
+        self.collect_custom_tags(&mut custom);
+        custom
+    }
+
+    fn collect_custom_tags<'a>(&'a self, custom: &mut Vec<&'a str>) {
+        match self {
+            Expr::Tag(tag) => {
+                if !is_implicit(tag) && !custom.contains(&tag.as_str()) {
+                    custom.push(tag);
+                }
+            }
+            Expr::Not(expr) => expr.collect_custom_tags(custom),
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.collect_custom_tags(custom);
+                rhs.collect_custom_tags(custom);
+            }
+        }
+    }
+
+    /// Whether some set of `-tags` makes the expression hold on the target
+    /// platform of `tags`, whatever tags were actually given.
+    pub fn can_hold(&self, tags: &BuildTags) -> bool {
+        let custom = self.custom_tags();
+        // Trying every combination is only affordable for a few tags, which
+        // is all constraints have in practice.
+        if custom.len() > MAX_CUSTOM_TAGS {
+            return true;
+        }
+        (0..1u32 << custom.len()).any(|set| {
+            self.eval_with(
+                &|tag| match custom.iter().position(|custom| *custom == tag) {
+                    Some(bit) => set & (1 << bit) != 0,
+                    None => tags.is_satisfied(tag),
+                },
+            )
+        })
+    }
+}
+
+/// Custom tags up to which [`Expr::can_hold`] tries every combination.
+const MAX_CUSTOM_TAGS: usize = 10;
+
+/// Go's syntax, with parentheses only where needed.
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let grouped = |expr: &Expr, f: &mut std::fmt::Formatter, group: bool| {
+            if group {
+                write!(f, "({})", expr)
+            } else {
+                write!(f, "{}", expr)
+            }
+        };
+        match self {
+            Expr::Tag(tag) => write!(f, "{}", tag),
+            Expr::Not(expr) => {
+                write!(f, "!")?;
+                grouped(expr, f, !matches!(**expr, Expr::Tag(_) | Expr::Not(_)))
+            }
+            Expr::And(lhs, rhs) => {
+                grouped(lhs, f, matches!(**lhs, Expr::Or(..)))?;
+                write!(f, " && ")?;
+                grouped(rhs, f, matches!(**rhs, Expr::Or(..)))
+            }
+            Expr::Or(lhs, rhs) => write!(f, "{} || {}", lhs, rhs),
+        }
+    }
+}
+
+/// The tags a build is evaluated against: the ones passed with `--tags` plus
+/// the target platform and toolchain tags `go` sets implicitly.
+pub struct BuildTags {
+    tags: Vec<String>,
+}
+
+const UNIX_OSES: &[&str] = &[
+    "aix",
+    "android",
+    "darwin",
+    "dragonfly",
+    "freebsd",
+    "hurd",
+    "illumos",
+    "ios",
+    "linux",
+    "netbsd",
+    "openbsd",
+    "solaris",
+];
+
+/// Operating systems recognized in file name suffixes.
+const KNOWN_OSES: &[&str] = &[
+    "aix",
+    "android",
+    "darwin",
+    "dragonfly",
+    "freebsd",
+    "hurd",
+    "illumos",
+    "ios",
+    "js",
+    "linux",
+    "nacl",
+    "netbsd",
+    "openbsd",
+    "plan9",
+    "solaris",
+    "wasip1",
+    "windows",
+    "zos",
+];
+
+/// Architectures recognized in file name suffixes.
+const KNOWN_ARCHES: &[&str] = &[
+    "386",
+    "amd64",
+    "amd64p32",
+    "arm",
+    "armbe",
+    "arm64",
+    "arm64be",
+    "loong64",
+    "mips",
+    "mipsle",
+    "mips64",
+    "mips64le",
+    "mips64p32",
+    "mips64p32le",
+    "ppc",
+    "ppc64",
+    "ppc64le",
+    "riscv",
+    "riscv64",
+    "s390",
+    "s390x",
+    "sparc",
+    "sparc64",
+    "wasm",
+];
+
+impl BuildTags {
+    /// Collects the tags for a build targeting `goos`/`goarch`, which
+    /// default to `$GOOS`/`$GOARCH` and then to the host platform.
+    pub fn new(user_tags: Option<&str>, goos: Option<&str>, goarch: Option<&str>) -> Self {
+        let goos = goos
+            .map(str::to_string)
+            .or_else(|| std::env::var("GOOS").ok())
+            .unwrap_or_else(|| host_goos().to_string());
+        let goarch = goarch
+            .map(str::to_string)
+            .or_else(|| std::env::var("GOARCH").ok())
+            .unwrap_or_else(|| host_goarch().to_string());
+
+        let mut tags: Vec<String> = user_tags
+            .unwrap_or_default()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if UNIX_OSES.contains(&goos.as_str()) {
+            tags.push("unix".to_string());
+        }
+        if std::env::var("CGO_ENABLED").as_deref() != Ok("0") {
+            tags.push("cgo".to_string());
+        }
+        tags.push("gc".to_string());
+        // These targets also satisfy the tag of the system they derive from.
+        match goos.as_str() {
+            "android" => tags.push("linux".to_string()),
+            "illumos" => tags.push("solaris".to_string()),
+            "ios" => tags.push("darwin".to_string()),
+            _ => {}
+        }
+        tags.push(goos);
+        tags.push(goarch);
+
+        BuildTags { tags }
+    }
+
+    pub fn is_satisfied(&self, tag: &str) -> bool {
+        // Release tags (go1.21, ...) are assumed to be met by the toolchain.
+        let release = tag
+            .strip_prefix("go1.")
+            .is_some_and(|minor| minor.chars().all(|c| c.is_ascii_digit()));
+        release || self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// Whether `go` sets the tag by itself for some target or toolchain.
+fn is_implicit(tag: &str) -> bool {
+    KNOWN_OSES.contains(&tag)
+        || KNOWN_ARCHES.contains(&tag)
+        || matches!(tag, "unix" | "cgo" | "gc" | "gccgo")
+        || tag
+            .strip_prefix("go1.")
+            .is_some_and(|minor| minor.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn host_goos() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    }
+}
+
+fn host_goarch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        "powerpc64" => "ppc64",
+        "wasm32" => "wasm",
+        arch => arch,
+    }
+}
+
+/// Returns the constraint a Go file is built under: its header constraint
+/// combined with the one implied by its name.
+pub fn file_constraint(path: &Path, source: &str) -> Option<Expr> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    match (parse_file_constraint(source), parse_file_name(&name)) {
+        (Some(header), Some(name)) => Some(Expr::And(Box::new(header), Box::new(name))),
+        (header, name) => header.or(name),
+    }
+}
+
+/// Reads the constraint implied by a `_GOOS`, `_GOARCH` or `_GOOS_GOARCH`
+/// suffix of a file name, as in `foo_linux_test.go` or
+/// `foo_windows_amd64_test.go`. The part before the first underscore never
+/// counts, so `linux_test.go` is not constrained.
+fn parse_file_name(name: &str) -> Option<Expr> {
+    let stem = name.split('.').next().unwrap_or_default();
+    let (_, suffix) = stem.split_once('_')?;
+    let mut parts: Vec<&str> = suffix.split('_').collect();
+    if parts.last() == Some(&"test") {
+        parts.pop();
+    }
+
+    let tag = |name: &str| Expr::Tag(name.to_string());
+    match parts.as_slice() {
+        [.., os, arch] if KNOWN_OSES.contains(os) && KNOWN_ARCHES.contains(arch) => {
+            Some(Expr::And(Box::new(tag(os)), Box::new(tag(arch))))
+        }
+        [.., os] if KNOWN_OSES.contains(os) => Some(tag(os)),
+        [.., arch] if KNOWN_ARCHES.contains(arch) => Some(tag(arch)),
+        _ => None,
+    }
+}
+
+/// Reads the build constraint from the header of a Go source file, the
+/// comment lines before the package clause. A `//go:build` line takes
+/// precedence over `// +build` lines, which are ANDed together.
+fn parse_file_constraint(source: &str) -> Option<Expr> {
+    let mut plus_build: Option<Expr> = None;
+
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(comment) = line.strip_prefix("//") else {
+            break;
+        };
+
+        if let Some(expr) = comment.strip_prefix("go:build") {
+            return parse_expr(expr);
+        }
+        if let Some(expr) = comment.trim_start().strip_prefix("+build")
+            && let Some(expr) = parse_plus_build(expr)
+        {
+            plus_build = Some(match plus_build {
+                Some(prev) => Expr::And(Box::new(prev), Box::new(expr)),
+                None => expr,
+            });
+        }
+    }
+
+    plus_build
+}
+
+/// `// +build a,b !c` means `(a && b) || !c`.
+fn parse_plus_build(line: &str) -> Option<Expr> {
+    line.split_whitespace()
+        .filter_map(|option| {
+            option
+                .split(',')
+                .map(|term| match term.strip_prefix('!') {
+                    Some(tag) => Expr::Not(Box::new(Expr::Tag(tag.to_string()))),
+                    None => Expr::Tag(term.to_string()),
+                })
+                .reduce(|lhs, rhs| Expr::And(Box::new(lhs), Box::new(rhs)))
+        })
+        .reduce(|lhs, rhs| Expr::Or(Box::new(lhs), Box::new(rhs)))
+}
+
+fn parse_expr(source: &str) -> Option<Expr> {
+    let tokens = tokenize(source)?;
+    let mut parser = ExprParser { tokens, pos: 0 };
+    let expr = parser.or()?;
+    (parser.pos == parser.tokens.len()).then_some(expr)
+}
+
+fn tokenize(source: &str) -> Option<Vec<String>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' || c == ')' || c == '!' {
+            tokens.push(c.to_string());
+            i += 1;
+        } else if (c == '&' || c == '|') && chars.get(i + 1) == Some(&c) {
+            tokens.push(format!("{}{}", c, c));
+            i += 2;
+        } else if c.is_alphanumeric() || c == '_' || c == '.' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            return None;
+        }
+    }
+
+    Some(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn or(&mut self) -> Option<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some("||") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Some(expr)
+    }
+
+    fn and(&mut self) -> Option<Expr> {
+        let mut expr = self.not()?;
+        while self.peek() == Some("&&") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Some(expr)
+    }
+
+    fn not(&mut self) -> Option<Expr> {
+        match self.peek()? {
+            "!" => {
+                self.pos += 1;
+                Some(Expr::Not(Box::new(self.not()?)))
+            }
+            "(" => {
+                self.pos += 1;
+                let expr = self.or()?;
+                if self.peek() != Some(")") {
+                    return None;
+                }
+                self.pos += 1;
+                Some(expr)
+            }
+            ")" | "&&" | "||" => None,
+            tag => {
+                let tag = tag.to_string();
+                self.pos += 1;
+                Some(Expr::Tag(tag))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(user_tags: &str, goos: &str) -> BuildTags {
+        BuildTags::new(Some(user_tags), Some(goos), Some("amd64"))
+    }
+
+    fn header(expr: &str) -> Expr {
+        parse_file_constraint(&format!("//go:build {}\n\npackage foo\n", expr)).unwrap()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let expr = header("a || b && c");
+        assert_eq!(expr.to_string(), "a || b && c");
+        assert!(expr.eval(&tags("a", "linux")));
+        assert!(!expr.eval(&tags("b", "linux")));
+        assert!(expr.eval(&tags("b,c", "linux")));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        let expr = header("!a && b");
+        assert!(expr.eval(&tags("b", "linux")));
+        assert!(!expr.eval(&tags("a,b", "linux")));
+        assert!(!expr.eval(&tags("", "linux")));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        let expr = header("(a || b) && !(c || d)");
+        assert_eq!(expr.to_string(), "(a || b) && !(c || d)");
+        assert!(expr.eval(&tags("b", "linux")));
+        assert!(!expr.eval(&tags("a,d", "linux")));
+        assert!(parse_expr("(a || b").is_none());
+        assert!(parse_expr("a &&").is_none());
+    }
+
+    #[test]
+    fn plus_build_lines() {
+        let expr = parse_file_constraint("// +build a,b !c\n\npackage foo\n").unwrap();
+        assert_eq!(expr.to_string(), "a && b || !c");
+        assert!(expr.eval(&tags("a,b,c", "linux")));
+        assert!(expr.eval(&tags("", "linux")));
+        assert!(!expr.eval(&tags("a,c", "linux")));
+
+        // Several lines are ANDed, and a go:build line wins over them.
+        let expr = parse_file_constraint("// +build a\n// +build b\n\npackage foo\n").unwrap();
+        assert_eq!(expr.to_string(), "a && b");
+        let expr = parse_file_constraint("// +build a\n//go:build c\n\npackage foo\n").unwrap();
+        assert_eq!(expr.to_string(), "c");
+    }
+
+    #[test]
+    fn header_ends_at_package_clause() {
+        assert!(parse_file_constraint("package foo\n\n//go:build a\n").is_none());
+    }
+
+    #[test]
+    fn file_name_suffixes() {
+        assert_eq!(parse_file_name("linux_test.go"), None);
+        assert_eq!(parse_file_name("foo_test.go"), None);
+        assert_eq!(
+            parse_file_name("foo_linux_test.go").unwrap().to_string(),
+            "linux"
+        );
+        assert_eq!(
+            parse_file_name("x_linux_amd64_test.go")
+                .unwrap()
+                .to_string(),
+            "linux && amd64"
+        );
+        assert_eq!(parse_file_name("x_arm64.go").unwrap().to_string(), "arm64");
+    }
+
+    #[test]
+    fn file_constraint_combines_header_and_name() {
+        let expr = file_constraint(
+            Path::new("x_linux_test.go"),
+            "//go:build integration\n\npackage foo\n",
+        )
+        .unwrap();
+        assert_eq!(expr.to_string(), "integration && linux");
+        assert!(expr.eval(&tags("integration", "linux")));
+        assert!(!expr.eval(&tags("integration", "darwin")));
+    }
+
+    #[test]
+    fn derived_platforms_satisfy_their_base() {
+        let expr = header("darwin");
+        assert!(expr.eval(&tags("", "ios")));
+        assert!(expr.eval(&tags("", "darwin")));
+        assert!(!expr.eval(&tags("", "linux")));
+        assert!(header("unix").eval(&tags("", "ios")));
+        assert!(!header("unix").eval(&tags("", "windows")));
+        assert!(header("solaris").eval(&tags("", "illumos")));
+    }
+
+    #[test]
+    fn can_hold_tries_custom_tags() {
+        let expr = header("integration && !race && linux");
+        assert!(expr.can_hold(&tags("", "linux")));
+        assert!(!expr.can_hold(&tags("", "darwin")));
+        assert_eq!(expr.custom_tags(), ["integration", "race"]);
+        assert!(header("go1.21").eval(&tags("", "linux")));
+    }
+}
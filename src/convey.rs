@@ -0,0 +1,51 @@
+//! GoConvey block discovery.
+//!
+//! Convey blocks cannot be selected on their own from the command line, so
+//! they are only listed as pseudo-subtests; choosing one runs the test
+//! function that contains it.
+
+use crate::parser::{self, GoFile, Token, TokenKind};
+
+const IMPORT_PATH: &str = "github.com/smartystreets/goconvey/convey";
+const BLOCKS: &[&str] = &["Convey", "FocusConvey", "SkipConvey"];
+
+pub fn imports_convey(file: &GoFile) -> bool {
+    file.imports.iter().any(|import| import.path == IMPORT_PATH)
+}
+
+/// Returns the path of every Convey block in a test body, with the
+/// descriptions of nested blocks joined by `/`.
+pub fn find_conveys(body: &[Token]) -> Vec<String> {
+    let mut conveys = Vec::new();
+    collect_conveys(body, &[], &mut conveys);
+    conveys
+}
+
+fn collect_conveys(tokens: &[Token], path: &[String], conveys: &mut Vec<String>) {
+    let mut i = 0;
+
+    while i + 2 < tokens.len() {
+        let name = &tokens[i];
+        let description = &tokens[i + 2];
+
+        if name.kind == TokenKind::Ident
+            && BLOCKS.contains(&name.text.as_str())
+            && tokens[i + 1].is_punct("(")
+            && description.is_string()
+        {
+            let Some(close) = parser::matching(tokens, i + 1) else {
+                break;
+            };
+            let mut block_path = path.to_vec();
+            block_path.push(description.text.clone());
+
+            conveys.push(block_path.join("/"));
+            collect_conveys(&tokens[i + 3..close], &block_path, conveys);
+
+            i = close + 1;
+            continue;
+        }
+
+        i += 1;
+    }
+}
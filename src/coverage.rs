@@ -0,0 +1,275 @@
+//! Coverage collection for `--cover`.
+//!
+//! Each `go test` run writes its own profile into a temporary directory.
+//! Once all runs are done the profiles are merged into one, summarized per
+//! package and optionally rendered with `go tool cover -html`. The temporary
+//! directory is removed when the collector is dropped.
+//!
+//! With `--base-ref`, the per-function coverage `go tool cover -func` reports
+//! for the profile of another revision is compared with the current one's.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tells apart the directories of collectors of the same process.
+static COLLECTORS: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Coverage {
+    dir: PathBuf,
+    profiles: Vec<PathBuf>,
+}
+
+impl Coverage {
+    pub fn new() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "gotestfinder-cover-{}-{}",
+            std::process::id(),
+            COLLECTORS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Coverage {
+            dir,
+            profiles: Vec::new(),
+        })
+    }
+
+    /// Reserves the profile path for the next `go test` run.
+    pub fn next_profile(&mut self) -> PathBuf {
+        let path = self.dir.join(format!("cover-{}.out", self.profiles.len()));
+        self.profiles.push(path.clone());
+        path
+    }
+
+    /// Merges the profiles written so far into `out`, or into a file in the
+    /// temporary directory. Returns `None` if no run wrote a profile.
+    pub fn merge(&self, out: Option<&Path>) -> Result<Option<PathBuf>> {
+        let mut profile = Profile::default();
+        for path in &self.profiles {
+            if let Ok(content) = std::fs::read_to_string(path) {
+                profile.add(&content);
+            }
+        }
+        let Some(mode) = &profile.mode else {
+            return Ok(None);
+        };
+
+        let path = out.map_or_else(|| self.dir.join("merged.out"), Path::to_path_buf);
+        let mut content = format!("mode: {}\n", mode);
+        for (block, count) in &profile.blocks {
+            content.push_str(&format!(
+                "{} {} {}\n",
+                block.location, block.statements, count
+            ));
+        }
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(Some(path))
+    }
+}
+
+impl Drop for Coverage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Block {
+    /// `file.go:line.col,line.col`
+    location: String,
+    statements: u64,
+}
+
+/// A parsed coverage profile. Blocks reported by several runs are merged the
+/// way `go tool covdata merge` does: counts add up, and in `set` mode a
+/// block is covered if any run covered it.
+#[derive(Default)]
+struct Profile {
+    mode: Option<String>,
+    blocks: BTreeMap<Block, u64>,
+}
+
+impl Profile {
+    fn add(&mut self, content: &str) {
+        for line in content.lines() {
+            if let Some(mode) = line.strip_prefix("mode:") {
+                self.mode.get_or_insert_with(|| mode.trim().to_string());
+                continue;
+            }
+
+            let mut fields = line.rsplitn(3, ' ');
+            let (Some(count), Some(statements), Some(location)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let (Ok(count), Ok(statements)) = (count.parse::<u64>(), statements.parse()) else {
+                continue;
+            };
+
+            let block = Block {
+                location: location.to_string(),
+                statements,
+            };
+            let merged = self.blocks.entry(block).or_default();
+            *merged = if self.mode.as_deref() == Some("set") {
+                (*merged).max(count)
+            } else {
+                *merged + count
+            };
+        }
+    }
+}
+
+/// Statement coverage of one package.
+pub struct PackageCoverage {
+    pub package: String,
+    pub covered: u64,
+    pub statements: u64,
+}
+
+impl PackageCoverage {
+    pub fn percent(&self) -> f64 {
+        if self.statements == 0 {
+            0.0
+        } else {
+            self.covered as f64 * 100.0 / self.statements as f64
+        }
+    }
+}
+
+/// Summarizes a merged profile per package, in import path order.
+pub fn summarize(profile: &Path) -> Result<Vec<PackageCoverage>> {
+    let content = std::fs::read_to_string(profile)
+        .with_context(|| format!("Failed to read {}", profile.display()))?;
+    let mut parsed = Profile::default();
+    parsed.add(&content);
+
+    let mut packages: BTreeMap<String, PackageCoverage> = BTreeMap::new();
+    for (block, count) in &parsed.blocks {
+        let file = block.location.split(':').next().unwrap_or_default();
+        let package = file.rsplit_once('/').map_or(file, |(package, _)| package);
+        let entry = packages
+            .entry(package.to_string())
+            .or_insert_with(|| PackageCoverage {
+                package: package.to_string(),
+                covered: 0,
+                statements: 0,
+            });
+        entry.statements += block.statements;
+        if *count > 0 {
+            entry.covered += block.statements;
+        }
+    }
+
+    Ok(packages.into_values().collect())
+}
+
+/// Opens the HTML coverage report of a profile in the browser.
+pub fn open_html(go: &Path, profile: &Path) -> Result<()> {
+    let status = Command::new(go)
+        .arg("tool")
+        .arg("cover")
+        .arg(format!("-html={}", profile.display()))
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("go tool cover exited with {}", status);
+    }
+    Ok(())
+}
+
+/// Statement coverage of one function, as `go tool cover -func` reports it.
+pub struct FunctionCoverage {
+    /// The file declaring the function, as `import/path/file.go`.
+    pub file: String,
+    pub name: String,
+    pub percent: f64,
+}
+
+/// The coverage of each function in a profile, resolving its files from
+/// `dir`. The total over all functions comes last, named `total`.
+pub fn functions(go: &Path, dir: &Path, profile: &Path) -> Result<Vec<FunctionCoverage>> {
+    let output = Command::new(go)
+        .arg("tool")
+        .arg("cover")
+        .arg(format!("-func={}", profile.display()))
+        .current_dir(dir)
+        .output()
+        .context("Failed to run go tool cover")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "go tool cover exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    // `example.com/p/file.go:12:  Name  75.0%`, and `total:  (statements)  60.0%`.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let location = fields.next()?;
+            let name = fields.next()?;
+            let percent = fields.next()?.strip_suffix('%')?.parse().ok()?;
+            let (file, name) = match location.split(':').next()? {
+                "total" => (String::new(), "total"),
+                file => (file.to_string(), name),
+            };
+            Some(FunctionCoverage {
+                file,
+                name: name.to_string(),
+                percent,
+            })
+        })
+        .collect())
+}
+
+/// A function whose coverage differs between two revisions; `None` where
+/// the function does not exist.
+pub struct CoverageChange {
+    pub file: String,
+    pub name: String,
+    pub base: Option<f64>,
+    pub head: Option<f64>,
+}
+
+/// The functions whose coverage changed from `base` to `head`, with the
+/// total last. Functions are matched by file and name, as their lines move.
+pub fn diff(base: &[FunctionCoverage], head: &[FunctionCoverage]) -> Vec<CoverageChange> {
+    let find = |functions: &[FunctionCoverage], function: &FunctionCoverage| {
+        functions
+            .iter()
+            .find(|other| other.file == function.file && other.name == function.name)
+            .map(|other| other.percent)
+    };
+
+    let mut changes: Vec<CoverageChange> = head
+        .iter()
+        .map(|function| CoverageChange {
+            file: function.file.clone(),
+            name: function.name.clone(),
+            base: find(base, function),
+            head: Some(function.percent),
+        })
+        .chain(
+            base.iter()
+                .filter(|function| find(head, function).is_none())
+                .map(|function| CoverageChange {
+                    file: function.file.clone(),
+                    name: function.name.clone(),
+                    base: Some(function.percent),
+                    head: None,
+                }),
+        )
+        .filter(|change| change.base != change.head)
+        .collect();
+    changes.sort_by_key(|change| change.file.is_empty());
+    changes
+}
@@ -0,0 +1,177 @@
+//! Picking with the user's own `fzf`, for `--ui fzf`.
+//!
+//! Entries are fed to fzf as `INDEX<TAB>TEXT` lines with only the text shown,
+//! so the picked lines lead back to their entries; fzf numbers the fields
+//! `--nth` names within the shown text. Previews are written to a
+//! temporary directory, one file per entry, which fzf's preview command shows.
+//! `FZF_DEFAULT_OPTS` applies as it does to any fzf run. fzf cannot be
+//! started with entries selected, so [`Picker::selected`] is left out, and
+//! [`Picker::cursor`] is placed with a `start` binding, which fzf has
+//! since 0.40.
+
+use crate::Case;
+use crate::picker::{Entry, Picked, Picker, SELECTION_ACTIONS};
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Like [`Picker::pick`], but shows the entries in fzf.
+pub fn pick(picker: &Picker, entries: &[Entry]) -> Result<Picked> {
+    let fzf = which::which("fzf").context("--ui fzf needs fzf on PATH")?;
+
+    let mut command = Command::new(fzf);
+    command
+        .args([
+            "--ansi",
+            "--delimiter=\t",
+            "--with-nth=2..",
+            "--print-query",
+        ])
+        .arg(format!("--prompt={}", picker.prompt))
+        .arg(format!("--header={}", picker.header));
+    command
+        .arg(format!("--height={}", picker.height))
+        .arg(format!("--min-height={}", picker.min_height))
+        .arg(format!("--layout={}", picker.layout.name()));
+    if !picker.query.is_empty() {
+        command.arg(format!("--query={}", picker.query));
+    }
+    if !picker.nth.is_empty() {
+        let fields: Vec<String> = picker.nth.iter().map(isize::to_string).collect();
+        command.arg(format!("--nth={}", fields.join(",")));
+    }
+    if picker.exact {
+        command.arg("--exact");
+    }
+    match picker.case {
+        Case::Smart => {}
+        Case::Sensitive => {
+            command.arg("+i");
+        }
+        Case::Insensitive => {
+            command.arg("-i");
+        }
+    }
+    // Positions count the listed entries, which are all of them in order
+    // until there is a query.
+    if let Some(cursor) = picker.cursor
+        && picker.query.is_empty()
+    {
+        command.arg(format!("--bind=start:pos({})", cursor + 1));
+    }
+    if picker.keep {
+        command.arg("--no-clear");
+    }
+    if picker.multi {
+        command.arg("--multi");
+    }
+    if let Some(color) = &picker.color {
+        command.arg(format!("--color={}", color));
+    }
+    // fzf changes the selection itself, under the same action names, and
+    // the other bindings accept it.
+    let (selection_bindings, accept_bindings): (Vec<_>, Vec<_>) = picker
+        .bindings
+        .iter()
+        .partition(|(_, action)| SELECTION_ACTIONS.contains(&action.as_str()));
+    for (key, action) in selection_bindings {
+        command.arg(format!("--bind={}:{}", key, action));
+    }
+    if !accept_bindings.is_empty() {
+        let keys: Vec<&str> = accept_bindings
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        command.arg(format!("--expect={}", keys.join(",")));
+    }
+
+    let previews = Previews::write(entries)?;
+    if let Some(previews) = &previews {
+        let dir = previews.0.to_string_lossy();
+        let dir = shlex::try_quote(&dir).context("Unusable temporary directory")?;
+        command
+            .arg(format!("--preview=cat {}/{{1}}", dir))
+            .arg("--preview-window=right:50%");
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run fzf")?;
+    let mut input = String::new();
+    for (index, entry) in entries.iter().enumerate() {
+        input.push_str(&format!("{}\t{}\n", index, entry.text.replace('\n', " ")));
+    }
+    // fzf may quit before reading everything, which is not an error.
+    let _ = child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input.as_bytes());
+    let output = child.wait_with_output().context("Failed to run fzf")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let query = lines.next().unwrap_or(picker.query).to_string();
+
+    // 1 is no match and 130 an abort, both of which pick nothing.
+    match output.status.code() {
+        Some(0) => {}
+        Some(1 | 130) => {
+            return Ok(Picked {
+                indices: Vec::new(),
+                action: None,
+                query,
+            });
+        }
+        _ => bail!("fzf failed with {}", output.status),
+    }
+
+    let action = if accept_bindings.is_empty() {
+        None
+    } else {
+        let key = lines.next().unwrap_or_default();
+        accept_bindings
+            .iter()
+            .find(|(bound, _)| bound == key)
+            .map(|(_, action)| action.clone())
+    };
+    let mut indices: Vec<usize> = lines
+        .filter_map(|line| line.split('\t').next()?.parse().ok())
+        .collect();
+    indices.sort_unstable();
+    Ok(Picked {
+        indices,
+        action,
+        query,
+    })
+}
+
+/// Preview files, removed when dropped.
+struct Previews(PathBuf);
+
+impl Previews {
+    /// Writes the previews, or returns `None` when no entry has one.
+    fn write(entries: &[Entry]) -> Result<Option<Previews>> {
+        if entries.iter().all(|entry| entry.preview.is_none()) {
+            return Ok(None);
+        }
+        let dir = std::env::temp_dir().join(format!("gotestfinder-fzf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let previews = Previews(dir);
+        for (index, entry) in entries.iter().enumerate() {
+            let preview = entry.preview.as_deref().unwrap_or_default();
+            std::fs::write(previews.0.join(index.to_string()), preview)?;
+        }
+        Ok(Some(previews))
+    }
+}
+
+impl Drop for Previews {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,79 @@
+//! Ginkgo spec discovery.
+//!
+//! Ginkgo suites expose a single `TestXxx` bootstrap to `go test`; the specs
+//! themselves are declared with nested `Describe`/`Context`/`It` calls and
+//! are selected at run time with `--ginkgo.focus`.
+
+use crate::parser::{self, GoFile, Token, TokenKind};
+
+const CONTAINERS: &[&str] = &["Describe", "Context", "When", "DescribeTable"];
+const SPECS: &[&str] = &["It", "Specify", "Entry"];
+
+pub fn imports_ginkgo(file: &GoFile) -> bool {
+    file.imports
+        .iter()
+        .any(|import| import.path.starts_with("github.com/onsi/ginkgo"))
+}
+
+/// Reports whether a test body bootstraps a Ginkgo suite.
+pub fn runs_specs(body: &[Token]) -> bool {
+    body.windows(2)
+        .any(|w| w[0].is_ident("RunSpecs") && w[1].is_punct("("))
+}
+
+/// Returns the full text of every spec declared in the file: the texts of
+/// its containers and its own text joined by spaces, which is what
+/// `--ginkgo.focus` is matched against.
+pub fn find_specs(file: &GoFile) -> Vec<String> {
+    let mut specs = Vec::new();
+    collect_specs(&file.tokens, &[], &mut specs);
+    specs
+}
+
+fn collect_specs(tokens: &[Token], path: &[String], specs: &mut Vec<String>) {
+    let mut i = 0;
+
+    while i + 2 < tokens.len() {
+        let name = &tokens[i];
+        let text = &tokens[i + 2];
+
+        if name.kind == TokenKind::Ident && tokens[i + 1].is_punct("(") && text.is_string() {
+            let node = unfocused(&name.text);
+            let is_container = CONTAINERS.contains(&node);
+
+            if is_container || SPECS.contains(&node) {
+                let Some(close) = parser::matching(tokens, i + 1) else {
+                    break;
+                };
+                let mut node_path = path.to_vec();
+                node_path.push(text.text.clone());
+
+                if is_container {
+                    collect_specs(&tokens[i + 3..close], &node_path, specs);
+                } else {
+                    specs.push(node_path.join(" "));
+                }
+
+                i = close + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+}
+
+/// Strips the focus (`F`), pending (`P`) and skip (`X`) prefixes so that
+/// `FIt` and `PDescribe` are treated like `It` and `Describe`.
+fn unfocused(name: &str) -> &str {
+    match name.strip_prefix(['F', 'P', 'X']) {
+        Some(rest) if CONTAINERS.contains(&rest) || SPECS.contains(&rest) => rest,
+        _ => name,
+    }
+}
+
+/// Builds an anchored `--ginkgo.focus` regex matching exactly the given specs.
+pub fn focus_pattern(specs: &[&str]) -> String {
+    let specs: Vec<String> = specs.iter().map(|spec| regex::escape(spec)).collect();
+    format!("^({})$", specs.join("|"))
+}
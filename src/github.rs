@@ -0,0 +1,48 @@
+//! GitHub Actions workflow commands for `--gh-annotations`.
+//!
+//! An `::error` command printed to stdout during a workflow run turns into
+//! an annotation on the given file and line, which pull requests show inline
+//! in their diff.
+
+use std::path::{Path, PathBuf};
+
+/// An `::error` workflow command, placed at `location` when it is known.
+pub fn error(location: Option<(&str, usize)>, message: &str) -> String {
+    let properties = match location {
+        Some((file, line)) => format!(
+            " file={},line={}",
+            escape_property(&workspace_path(Path::new(file))),
+            line
+        ),
+        None => String::new(),
+    };
+
+    format!("::error{}::{}", properties, escape_data(message.trim_end()))
+}
+
+/// The path relative to the checked out repository, which annotations are
+/// placed by, or relative to the current directory outside of a workflow.
+fn workspace_path(file: &Path) -> String {
+    let root = std::env::var_os("GITHUB_WORKSPACE")
+        .map(PathBuf::from)
+        .or_else(|| std::env::current_dir().ok());
+    let relative = root
+        .and_then(|root| root.canonicalize().ok())
+        .zip(file.canonicalize().ok())
+        .and_then(|(root, file)| file.strip_prefix(root).ok().map(Path::to_path_buf));
+
+    match relative {
+        Some(relative) => relative.to_string_lossy().replace('\\', "/"),
+        None => file.to_string_lossy().to_string(),
+    }
+}
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
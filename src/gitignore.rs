@@ -0,0 +1,347 @@
+//! `.gitignore` handling for discovery.
+//!
+//! Rules are read from `.gitignore` and `.ignore` files in every directory
+//! between the repository root and the file being checked, plus the
+//! repository's `.git/info/exclude` and the user's global excludes file.
+//! As in git, the last matching rule wins and deeper files take precedence.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Per-directory ignore files, in increasing order of precedence.
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+struct Rule {
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// The rules of one ignore file, matched against paths relative to `base`.
+struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    fn load(file: &Path, base: &Path) -> Option<IgnoreFile> {
+        let content = std::fs::read_to_string(file).ok()?;
+        let rules: Vec<Rule> = content.lines().filter_map(parse_rule).collect();
+        (!rules.is_empty()).then(|| IgnoreFile {
+            base: base.to_path_buf(),
+            rules,
+        })
+    }
+
+    /// Returns whether the last rule matching `path` ignores it, or `None`
+    /// if no rule matches.
+    fn matches(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.regex.is_match(&relative))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// Ignore rules for a walk rooted at one directory. Per-directory files are
+/// read lazily as the walk reaches them.
+pub struct Ignore {
+    root: PathBuf,
+    /// Outermost directory whose ignore files apply: the repository root,
+    /// or the walk root outside a repository.
+    top: PathBuf,
+    global: Vec<IgnoreFile>,
+    dirs: HashMap<PathBuf, Vec<IgnoreFile>>,
+}
+
+impl Ignore {
+    pub fn new(root: &Path) -> Ignore {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let repo = root
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .map(Path::to_path_buf);
+        let top = repo.clone().unwrap_or_else(|| root.clone());
+
+        let mut global = Vec::new();
+        if let Some(file) = global_excludes_file() {
+            global.extend(IgnoreFile::load(&file, &top));
+        }
+        if let Some(repo) = &repo {
+            global.extend(IgnoreFile::load(&repo.join(".git/info/exclude"), repo));
+        }
+
+        Ignore {
+            root,
+            top,
+            global,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// Reports whether the entry at `relative`, a path below the walk root,
+    /// is ignored.
+    pub fn is_ignored(&mut self, relative: &Path, is_dir: bool) -> bool {
+        let path = self.root.join(relative);
+        let mut ignored = false;
+
+        for file in &self.global {
+            if let Some(matched) = file.matches(&path, is_dir) {
+                ignored = matched;
+            }
+        }
+
+        let mut dirs: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.top))
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.reverse();
+
+        for dir in dirs {
+            let files = self.dirs.entry(dir).or_insert_with_key(|dir| {
+                IGNORE_FILES
+                    .iter()
+                    .filter_map(|name| IgnoreFile::load(&dir.join(name), dir))
+                    .collect()
+            });
+            for file in files.iter() {
+                if let Some(matched) = file.matches(&path, is_dir) {
+                    ignored = matched;
+                }
+            }
+        }
+
+        ignored
+    }
+}
+
+/// Locates git's global excludes file: `core.excludesFile` from the user's
+/// git config, or `$XDG_CONFIG_HOME/git/ignore` by default.
+fn global_excludes_file() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".config")));
+
+    let configs = [
+        config_home.as_ref().map(|dir| dir.join("git/config")),
+        home.as_ref().map(|home| home.join(".gitconfig")),
+    ];
+    let configured = configs
+        .iter()
+        .flatten()
+        .filter_map(|config| std::fs::read_to_string(config).ok())
+        .filter_map(|content| core_excludes_file(&content))
+        .next_back();
+
+    match configured {
+        Some(path) => match (path.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => Some(home.join(rest)),
+            _ => Some(PathBuf::from(path)),
+        },
+        None => config_home.map(|dir| dir.join("git/ignore")),
+    }
+}
+
+/// Reads `excludesFile` from the `[core]` section of a git config file.
+fn core_excludes_file(content: &str) -> Option<String> {
+    let mut in_core = false;
+    let mut value = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_core = line.eq_ignore_ascii_case("[core]");
+        } else if in_core
+            && let Some((key, rest)) = line.split_once('=')
+            && key.trim().eq_ignore_ascii_case("excludesfile")
+        {
+            value = Some(rest.trim().trim_matches('"').to_string());
+        }
+    }
+
+    value
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    if pattern.is_empty() {
+        return None;
+    }
+
+    // A pattern with a slash is relative to the ignore file's directory;
+    // one without matches a name at any depth.
+    let anchored = pattern.contains('/');
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+    let prefix = if anchored { "^" } else { "^(?:.*/)?" };
+
+    let regex = Regex::new(&format!("{}{}$", prefix, glob_to_regex(pattern))).ok()?;
+    Some(Rule {
+        regex,
+        negated,
+        dir_only,
+    })
+}
+
+/// Translates gitignore glob syntax into a regex over `/`-separated paths.
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                let at_end = i + 2 == chars.len() || chars[i + 2] == '/';
+                if at_start && at_end && i + 2 < chars.len() {
+                    // `**/` matches zero or more directories.
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                    continue;
+                } else if at_start && at_end {
+                    regex.push_str(".*");
+                } else {
+                    regex.push_str("[^/]*");
+                }
+                i += 2;
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                let close = chars[i + 1..]
+                    .iter()
+                    .skip(1)
+                    .position(|&c| c == ']')
+                    .map(|offset| i + 2 + offset);
+                match close {
+                    Some(close) => {
+                        let class: String = chars[i + 1..close].iter().collect();
+                        let class = match class.strip_prefix('!') {
+                            Some(rest) => format!("^{}", rest),
+                            None => class,
+                        };
+                        regex.push('[');
+                        regex.push_str(&class.replace('[', "\\["));
+                        regex.push(']');
+                        i = close;
+                    }
+                    None => regex.push_str("\\["),
+                }
+            }
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                regex.push_str(&regex::escape(&chars[i].to_string()));
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(rule: &str, path: &str, is_dir: bool) -> bool {
+        let rule = parse_rule(rule).unwrap();
+        (is_dir || !rule.dir_only) && rule.regex.is_match(path)
+    }
+
+    #[test]
+    fn name_without_slash_matches_at_any_depth() {
+        assert!(is_match("vendor", "vendor", true));
+        assert!(is_match("vendor", "a/b/vendor", true));
+        assert!(is_match("*.pb.go", "api/x.pb.go", false));
+        assert!(!is_match("*.go", "a.go/x", false));
+    }
+
+    #[test]
+    fn slash_anchors_to_the_file() {
+        assert!(is_match("/build", "build", true));
+        assert!(!is_match("/build", "a/build", true));
+        assert!(is_match("doc/frotz", "doc/frotz", false));
+        assert!(!is_match("doc/frotz", "a/doc/frotz", false));
+        assert!(!is_match("doc/*.go", "doc/a/b.go", false));
+    }
+
+    #[test]
+    fn double_stars() {
+        assert!(is_match("**/testdata", "testdata", true));
+        assert!(is_match("**/testdata", "a/b/testdata", true));
+        assert!(is_match("a/**/b", "a/b", false));
+        assert!(is_match("a/**/b", "a/x/y/b", false));
+        assert!(is_match("gen/**", "gen/x/y.go", false));
+        assert!(!is_match("gen/**", "gen", true));
+        assert!(is_match("a**b", "axxb", false));
+        assert!(!is_match("a**b", "a/b", false));
+    }
+
+    #[test]
+    fn trailing_slash_matches_only_directories() {
+        assert!(is_match("out/", "out", true));
+        assert!(!is_match("out/", "out", false));
+        assert!(is_match("out/", "a/out", true));
+    }
+
+    #[test]
+    fn character_classes_and_escapes() {
+        assert!(is_match("x[0-9].go", "x1.go", false));
+        assert!(!is_match("x[!0-9].go", "x1.go", false));
+        assert!(is_match("x[!0-9].go", "xa.go", false));
+        assert!(is_match("x?.go", "xa.go", false));
+        assert!(!is_match("x?.go", "x/.go", false));
+        assert!(is_match("\\!keep", "!keep", false));
+        assert!(is_match("\\#note", "#note", false));
+        assert!(parse_rule("# comment").is_none());
+        assert!(parse_rule("/").is_none());
+    }
+
+    #[test]
+    fn later_and_deeper_rules_win() {
+        let root = std::env::temp_dir().join(format!("gotestfinder-ignore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n!keep.log\nb/\n").unwrap();
+        std::fs::write(root.join("a/.gitignore"), "!b/\nkeep.log\n").unwrap();
+        std::fs::write(root.join("a/b/.ignore"), "!keep.log\n").unwrap();
+
+        let mut ignore = Ignore::new(&root);
+        assert!(ignore.is_ignored(Path::new("x.log"), false));
+        assert!(!ignore.is_ignored(Path::new("keep.log"), false));
+        assert!(ignore.is_ignored(Path::new("b"), true));
+        assert!(!ignore.is_ignored(Path::new("a/b"), true));
+        assert!(ignore.is_ignored(Path::new("a/keep.log"), false));
+        assert!(!ignore.is_ignored(Path::new("a/b/keep.log"), false));
+        assert!(ignore.is_ignored(Path::new("a/b/x.log"), false));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
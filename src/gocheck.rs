@@ -0,0 +1,88 @@
+//! gocheck (`gopkg.in/check.v1`) suite discovery.
+//!
+//! gocheck suites are registered with `check.Suite(...)` and run from a
+//! single `go test` entry point calling `check.TestingT(t)`. Individual
+//! methods are selected with `-check.f`, which matches `Suite.Method`.
+
+use crate::parser::{GoFile, Token, TokenKind};
+
+pub const IMPORT_PATH: &str = "gopkg.in/check.v1";
+
+pub fn imports_gocheck(file: &GoFile) -> bool {
+    file.imports.iter().any(|import| import.path == IMPORT_PATH)
+}
+
+/// Reports whether a test body hands control to gocheck.
+pub fn runs_suites(file: &GoFile, body: &[Token]) -> bool {
+    is_call(file, body, "TestingT")
+}
+
+/// Returns the types registered with `check.Suite(&S{})` or
+/// `check.Suite(new(S))` anywhere in the file.
+pub fn registered_suites(file: &GoFile) -> Vec<String> {
+    let tokens = &file.tokens;
+    let mut suites = Vec::new();
+
+    for i in 0..tokens.len() {
+        let Some(call) = qualified_call(tokens, i) else {
+            continue;
+        };
+        if !file.is_qualified(&call, IMPORT_PATH, "Suite") {
+            continue;
+        }
+
+        let args = &tokens[i + call_len(&tokens[i..])..];
+        let ty = match args {
+            [amp, ty, ..] if amp.is_punct("&") => ty,
+            [new, open, ty, ..] if new.is_ident("new") && open.is_punct("(") => ty,
+            [ty, ..] => ty,
+            [] => continue,
+        };
+        if ty.kind == TokenKind::Ident {
+            suites.push(ty.text.clone());
+        }
+    }
+
+    suites
+}
+
+/// Builds an anchored `-check.f` regex matching exactly the given
+/// `Suite.Method` names.
+pub fn filter_pattern(methods: &[&str]) -> String {
+    let methods: Vec<String> = methods.iter().map(|method| regex::escape(method)).collect();
+    format!("^({})$", methods.join("|"))
+}
+
+fn is_call(file: &GoFile, tokens: &[Token], ident: &str) -> bool {
+    (0..tokens.len()).any(|i| {
+        qualified_call(tokens, i).is_some_and(|call| file.is_qualified(&call, IMPORT_PATH, ident))
+    })
+}
+
+/// Returns the callee spelled by `Name(` or `pkg.Name(` starting at `i`.
+fn qualified_call(tokens: &[Token], i: usize) -> Option<String> {
+    match &tokens[i..] {
+        [pkg, dot, name, open, ..]
+            if pkg.kind == TokenKind::Ident
+                && dot.is_punct(".")
+                && name.kind == TokenKind::Ident
+                && open.is_punct("(") =>
+        {
+            Some(format!("{}.{}", pkg.text, name.text))
+        }
+        [name, open, ..] if name.kind == TokenKind::Ident && open.is_punct("(") => {
+            Some(name.text.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Number of tokens up to and including the opening parenthesis of the call
+/// recognised by `qualified_call`.
+fn call_len(tokens: &[Token]) -> usize {
+    if tokens.get(1).is_some_and(|t| t.is_punct(".")) {
+        4
+    } else {
+        2
+    }
+}
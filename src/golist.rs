@@ -0,0 +1,54 @@
+//! Discovery through `go test -list`, which reports the test functions the
+//! compiler actually builds for the current platform and build tags.
+
+use anyhow::{Result, bail};
+use std::path::Path;
+use std::process::Command;
+
+/// Lists the tests, benchmarks and fuzz targets of the package in `dir`,
+/// built by the `go` toolchain for the given tags and target platform.
+/// Examples are left out since they cannot be selected with `-run` alone.
+pub fn list_tests(
+    go: &Path,
+    dir: &Path,
+    tags: Option<&str>,
+    goos: Option<&str>,
+    goarch: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut cmd = Command::new(go);
+    cmd.args(["test", "-list", ".*"]);
+    if let Some(tags) = tags {
+        cmd.arg(format!("-tags={}", tags));
+    }
+    if let Some(goos) = goos {
+        cmd.env("GOOS", goos);
+    }
+    if let Some(goarch) = goarch {
+        cmd.env("GOARCH", goarch);
+    }
+    cmd.arg(".");
+    if !dir.as_os_str().is_empty() {
+        cmd.current_dir(dir);
+    }
+
+    let output = cmd.output()?;
+    if !output.status.success() {
+        bail!(
+            "go test -list failed in {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            ["Test", "Benchmark", "Fuzz"]
+                .iter()
+                .any(|prefix| line.starts_with(prefix))
+                && line.chars().all(|c| c.is_alphanumeric() || c == '_')
+        })
+        .map(str::to_string)
+        .collect())
+}
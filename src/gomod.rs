@@ -0,0 +1,179 @@
+//! Go module resolution.
+//!
+//! A package's import path is the path declared by the nearest enclosing
+//! `go.mod` followed by the package directory relative to that file.
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// Directory containing the `go.mod` file.
+    pub root: PathBuf,
+    /// Module path from the `module` directive.
+    pub path: String,
+}
+
+impl Module {
+    /// Returns the import path of the package in `dir`, if it lies inside
+    /// the module.
+    pub fn import_path(&self, dir: &Path) -> Option<String> {
+        let dir = dir.canonicalize().ok()?;
+        let relative = dir.strip_prefix(&self.root).ok()?;
+        let mut path = self.path.clone();
+        for component in relative.components() {
+            path.push('/');
+            path.push_str(&component.as_os_str().to_string_lossy());
+        }
+        Some(path)
+    }
+}
+
+/// Finds the module owning `dir` by looking for `go.mod` in it and each of
+/// its ancestors.
+pub fn find_module(dir: &Path) -> Option<Module> {
+    let dir = dir.canonicalize().ok()?;
+
+    for ancestor in dir.ancestors() {
+        let Ok(content) = std::fs::read_to_string(ancestor.join("go.mod")) else {
+            continue;
+        };
+        return parse_module_path(&content).map(|path| Module {
+            root: ancestor.to_path_buf(),
+            path,
+        });
+    }
+
+    None
+}
+
+/// Reads the `module` directive of a `go.mod` file.
+fn parse_module_path(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let path = line.strip_prefix("module")?;
+        if !path.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let path = path.trim().trim_matches(|c| c == '"' || c == '`');
+        (!path.is_empty()).then(|| path.to_string())
+    })
+}
+
+/// A `go.work` file and the module directories it uses.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// The `go.work` file.
+    pub file: PathBuf,
+    pub modules: Vec<PathBuf>,
+}
+
+impl Workspace {
+    pub fn contains(&self, module: &Module) -> bool {
+        self.modules.contains(&module.root)
+    }
+}
+
+/// Finds the workspace `go` would use for a module rooted at `root`: the
+/// file named by `GOWORK`, or the nearest `go.work` in `root` or one of its
+/// ancestors.
+pub fn find_workspace(root: &Path) -> Option<Workspace> {
+    let file = match std::env::var("GOWORK") {
+        Ok(value) if value == "off" => return None,
+        Ok(value) if !value.is_empty() => PathBuf::from(value).canonicalize().ok()?,
+        _ => root
+            .ancestors()
+            .map(|dir| dir.join("go.work"))
+            .find(|file| file.is_file())?,
+    };
+
+    let content = std::fs::read_to_string(&file).ok()?;
+    let dir = file.parent()?;
+    let modules = parse_work_uses(&content)
+        .into_iter()
+        .filter_map(|path| dir.join(path).canonicalize().ok())
+        .collect();
+
+    Some(Workspace { file, modules })
+}
+
+/// Reads the directories that the `replace` directives of a `go.mod` or
+/// `go.work` file replace modules with, leaving out replacements by other
+/// module versions.
+pub fn local_replacements(content: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+
+        let directive = if in_block {
+            if line == ")" {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if let Some(rest) = line.strip_prefix("replace") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+
+        let Some((_, target)) = directive.split_once("=>") else {
+            continue;
+        };
+        let Some(target) = target.split_whitespace().next() else {
+            continue;
+        };
+        let target = target.trim_matches(|c| c == '"' || c == '`');
+        // Go takes a target for a directory only when it is a path.
+        if matches!(target, "." | "..")
+            || target.starts_with("./")
+            || target.starts_with("../")
+            || Path::new(target).is_absolute()
+        {
+            dirs.push(target.to_string());
+        }
+    }
+
+    dirs
+}
+
+/// Reads the directories named by the `use` directives of a `go.work` file,
+/// in both the single-line and the parenthesized block form.
+fn parse_work_uses(content: &str) -> Vec<String> {
+    let mut uses = Vec::new();
+    let mut in_block = false;
+
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+
+        let path = if in_block {
+            if line == ")" {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if let Some(rest) = line.strip_prefix("use") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+
+        let path = path.trim_matches(|c| c == '"' || c == '`');
+        if !path.is_empty() {
+            uses.push(path.to_string());
+        }
+    }
+
+    uses
+}
@@ -0,0 +1,259 @@
+//! Go source for the selector's preview pane.
+//!
+//! Files are highlighted the way `bat` shows them, with keywords, types,
+//! calls, literals and comments in their own colors and line numbers in a
+//! gutter. A test's preview is its function, with the line naming the
+//! highlighted subtest marked: its `t.Run` call, or its case in a table.
+
+use crate::parser::{self, TokenKind};
+use crate::subtests;
+
+/// Lines kept above a subtest's line when the function is cut short to
+/// bring it into view.
+const CONTEXT_LINES: usize = 3;
+
+const KEYWORD: &str = "\x1b[35m";
+const TYPE: &str = "\x1b[33m";
+const CALL: &str = "\x1b[34m";
+const LITERAL: &str = "\x1b[36m";
+const STRING: &str = "\x1b[32m";
+const COMMENT: &str = "\x1b[90m";
+
+const KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+const TYPES: &[&str] = &[
+    "any",
+    "bool",
+    "byte",
+    "comparable",
+    "complex64",
+    "complex128",
+    "error",
+    "float32",
+    "float64",
+    "int",
+    "int8",
+    "int16",
+    "int32",
+    "int64",
+    "rune",
+    "string",
+    "uint",
+    "uint8",
+    "uint16",
+    "uint32",
+    "uint64",
+    "uintptr",
+];
+
+const CONSTANTS: &[&str] = &["true", "false", "nil", "iota"];
+
+/// A Go file, ready to preview parts of.
+pub struct Source {
+    /// The file's lines, tabs expanded and highlighted unless `plain`.
+    lines: Vec<String>,
+    tokens: Vec<parser::Token>,
+    plain: bool,
+}
+
+impl Source {
+    /// Reads `path`, or returns `None` when it cannot be read.
+    pub fn read(path: &str, plain: bool) -> Option<Source> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let text = text.replace('\r', "").replace('\t', "    ");
+        let lines = if plain {
+            text.lines().map(str::to_string).collect()
+        } else {
+            highlight(&text)
+        };
+        Some(Source {
+            lines,
+            tokens: parser::tokenize(&text),
+            plain,
+        })
+    }
+
+    /// The preview of the function declared at `line`. For one of its
+    /// subtests, the line naming it is marked and, when far down, the
+    /// function is cut after its signature to show it.
+    pub fn preview(&self, line: usize, subtest: Option<&subtests::Subtest>) -> String {
+        let end = self.function_end(line);
+        let mark = subtest.and_then(|subtest| self.subtest_line(line, end, subtest));
+        let width = end.to_string().len();
+        let marked = if self.plain { "\x1b[1m" } else { "\x1b[1;33m" };
+        // Line numbers to show, `None` standing for the lines cut.
+        let numbers: Vec<Option<usize>> = match mark {
+            Some(mark) if mark > line + CONTEXT_LINES + 1 => std::iter::once(Some(line))
+                .chain(std::iter::once(None))
+                .chain((mark - CONTEXT_LINES..=end).map(Some))
+                .collect(),
+            _ => (line..=end).map(Some).collect(),
+        };
+        let mut preview = String::new();
+        for number in numbers {
+            let Some(number) = number else {
+                preview.push_str(&format!("\x1b[2m{:>width$} ⋮\x1b[0m\n", ""));
+                continue;
+            };
+            let text = self.lines.get(number - 1).map_or("", String::as_str);
+            if Some(number) == mark {
+                preview.push_str(&format!("{}{:>width$} ▶\x1b[0m {}\n", marked, number, text));
+            } else {
+                preview.push_str(&format!("\x1b[2m{:>width$} │\x1b[0m {}\n", number, text));
+            }
+        }
+        preview
+    }
+
+    /// The line of the brace closing the function declared at `line`, or
+    /// the last line when it cannot be found.
+    fn function_end(&self, line: usize) -> usize {
+        let mut depth = 0;
+        for token in self.tokens.iter().skip_while(|token| token.line < line) {
+            if token.is_punct("{") {
+                depth += 1;
+            } else if token.is_punct("}") {
+                depth -= 1;
+                if depth == 0 {
+                    return token.line;
+                }
+            }
+        }
+        self.lines.len().max(line)
+    }
+
+    /// The line of the string naming `subtest` between `start` and `end`.
+    /// Each level of its name is looked for after the one above it, and
+    /// only a prefix of the last one is known for a dynamic subtest.
+    fn subtest_line(&self, start: usize, end: usize, subtest: &subtests::Subtest) -> Option<usize> {
+        let levels: Vec<&str> = subtest.name.split('/').collect();
+        let mut line = None;
+        let mut after = start;
+        for (index, level) in levels.iter().enumerate() {
+            let prefix = subtest.dynamic && index == levels.len() - 1;
+            let found = self
+                .tokens
+                .iter()
+                .filter(|token| token.line >= after && token.line <= end)
+                .find(|token| {
+                    let name = subtests::rewrite(&token.text);
+                    token.kind == TokenKind::String
+                        && if prefix {
+                            name.starts_with(level)
+                        } else {
+                            name == *level
+                        }
+                })?;
+            line = Some(found.line);
+            after = found.line;
+        }
+        line
+    }
+}
+
+/// Highlights Go `source`, returning its lines. Colors are closed at the
+/// end of every line, so lines can be shown on their own.
+fn highlight(source: &str) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let color = if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            Some(COMMENT)
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i = (i + 2).min(chars.len());
+            Some(COMMENT)
+        } else if c == '"' || c == '\'' || c == '`' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if c != '`' && chars[i] == '\n' {
+                    break;
+                }
+                if c != '`' && chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            Some(STRING)
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.')) {
+                i += 1;
+            }
+            Some(LITERAL)
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if KEYWORDS.contains(&word.as_str()) {
+                Some(KEYWORD)
+            } else if TYPES.contains(&word.as_str()) {
+                Some(TYPE)
+            } else if CONSTANTS.contains(&word.as_str()) {
+                Some(LITERAL)
+            } else if chars.get(i) == Some(&'(') {
+                Some(CALL)
+            } else {
+                None
+            }
+        } else {
+            i += 1;
+            None
+        };
+        let text: String = chars[start..i].iter().collect();
+        match color {
+            // Spans over several lines are colored line by line.
+            Some(color) => {
+                let lines: Vec<String> = text
+                    .split('\n')
+                    .map(|line| {
+                        if line.is_empty() {
+                            String::new()
+                        } else {
+                            format!("{}{}\x1b[0m", color, line)
+                        }
+                    })
+                    .collect();
+                out.push_str(&lines.join("\n"));
+            }
+            None => out.push_str(&text),
+        }
+    }
+    out.lines().map(str::to_string).collect()
+}
@@ -0,0 +1,266 @@
+//! Per-project run history.
+//!
+//! Results worth keeping between runs are stored below
+//! `$XDG_STATE_HOME/gotestfinder` (`~/.local/state/gotestfinder` by default),
+//! in a directory named after the project: the module root of the scanned
+//! directory, or the directory itself outside a module.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Number of runs kept; older ones are dropped as new ones are recorded.
+const MAX_RUNS: usize = 100;
+
+/// Lines of a test's output kept with its last result.
+const OUTPUT_TAIL: usize = 20;
+
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    /// Opens the history of the project containing `dir`. Nothing is written
+    /// until something is recorded.
+    pub fn open(dir: &Path) -> Option<History> {
+        let dir = dir.canonicalize().ok()?;
+        let project = crate::gomod::find_module(&dir).map_or(dir, |module| module.root);
+        let name = project.to_string_lossy().replace(['/', '\\', ':'], "-");
+
+        Some(History {
+            dir: state_dir()?.join("projects").join(name),
+        })
+    }
+
+    /// The `-shuffle` seed recorded by the last shuffled run.
+    pub fn shuffle_seed(&self) -> Option<i64> {
+        self.read("shuffle-seed")?.trim().parse().ok()
+    }
+
+    pub fn set_shuffle_seed(&self, seed: i64) -> Result<()> {
+        self.write("shuffle-seed", &format!("{}\n", seed))
+    }
+
+    /// The failures of the last run, as package and test name pairs; the
+    /// name is empty for a package that failed without a failing test.
+    pub fn failed_tests(&self) -> Vec<(String, String)> {
+        self.read("failed")
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(package, test)| (package.to_string(), test.to_string()))
+            .collect()
+    }
+
+    pub fn set_failed_tests(&self, failures: &[(String, String)]) -> Result<()> {
+        let content: String = failures
+            .iter()
+            .map(|(package, test)| format!("{}\t{}\n", package, test))
+            .collect();
+        self.write("failed", &content)
+    }
+
+    /// The duration in seconds of each test's last run, keyed by package and
+    /// test name, with subtests as `TestX/sub`.
+    pub fn durations(&self) -> HashMap<(String, String), f64> {
+        self.read("durations")
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let (package, test) = (fields.next()?, fields.next()?);
+                let seconds = fields.next()?.parse().ok()?;
+                Some(((package.to_string(), test.to_string()), seconds))
+            })
+            .collect()
+    }
+
+    /// Replaces the recorded durations of the given tests, keeping those of
+    /// the others.
+    pub fn update_durations(&self, durations: &[(String, String, f64)]) -> Result<()> {
+        let mut recorded = self.durations();
+        for (package, test, seconds) in durations {
+            recorded.insert((package.clone(), test.clone()), *seconds);
+        }
+
+        let mut lines: Vec<_> = recorded
+            .into_iter()
+            .map(|((package, test), seconds)| format!("{}\t{}\t{}\n", package, test, seconds))
+            .collect();
+        lines.sort();
+        self.write("durations", &lines.concat())
+    }
+
+    /// The last result of each test, keyed by package and test name, with
+    /// subtests as `TestX/sub`.
+    pub fn last_results(&self) -> HashMap<(String, String), LastResult> {
+        let mut results = HashMap::new();
+        let mut current: Option<(String, String)> = None;
+
+        for line in self.read("results").unwrap_or_default().lines() {
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some("result") => {
+                    let mut parse = || {
+                        let key = (fields.next()?.to_string(), fields.next()?.to_string());
+                        let result = LastResult {
+                            status: fields.next()?.to_string(),
+                            seconds: fields.next()?.parse().ok()?,
+                            time: fields.next()?.parse().ok()?,
+                            output: Vec::new(),
+                        };
+                        Some((key, result))
+                    };
+                    current = parse().map(|(key, result)| {
+                        results.insert(key.clone(), result);
+                        key
+                    });
+                }
+                Some("output") => {
+                    if let Some(result) = current.as_ref().and_then(|key| results.get_mut(key)) {
+                        result.output.push(fields.collect::<Vec<_>>().join("\t"));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        results
+    }
+
+    /// Replaces the last results of the given tests, keeping those of the
+    /// others. Only the end of each test's output is kept.
+    pub fn update_last_results(&self, updates: Vec<((String, String), LastResult)>) -> Result<()> {
+        let mut results = self.last_results();
+        for (key, mut result) in updates {
+            let excess = result.output.len().saturating_sub(OUTPUT_TAIL);
+            result.output.drain(..excess);
+            results.insert(key, result);
+        }
+
+        let mut entries: Vec<_> = results.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut content = String::new();
+        for ((package, test), result) in entries {
+            content.push_str(&format!(
+                "result\t{}\t{}\t{}\t{}\t{}\n",
+                package, test, result.status, result.seconds, result.time
+            ));
+            for line in &result.output {
+                content.push_str(&format!("output\t{}\n", line));
+            }
+        }
+        self.write("results", &content)
+    }
+
+    /// The recorded runs, oldest first.
+    pub fn runs(&self) -> Vec<Run> {
+        let mut runs = Vec::new();
+
+        for line in self.read("runs").unwrap_or_default().lines() {
+            let mut fields = line.split('\t');
+            match (fields.next(), runs.last_mut()) {
+                (Some("run"), _) => runs.push(Run {
+                    time: fields
+                        .next()
+                        .and_then(|time| time.parse().ok())
+                        .unwrap_or(0),
+                    tests: Vec::new(),
+                    commands: Vec::new(),
+                }),
+                (Some("test"), Some(run)) => {
+                    let (Some(package), Some(name)) = (fields.next(), fields.next()) else {
+                        continue;
+                    };
+                    run.tests.push(SelectedTest {
+                        package: package.to_string(),
+                        name: name.to_string(),
+                        subtest: fields.next().filter(|s| !s.is_empty()).map(str::to_string),
+                    });
+                }
+                (Some("command"), Some(run)) => {
+                    run.commands.push(fields.collect::<Vec<_>>().join("\t"));
+                }
+                _ => {}
+            }
+        }
+
+        runs
+    }
+
+    /// Records a run, dropping the oldest ones beyond the limit.
+    pub fn add_run(&self, run: Run) -> Result<()> {
+        let mut runs = self.runs();
+        runs.push(run);
+        let excess = runs.len().saturating_sub(MAX_RUNS);
+
+        let mut content = String::new();
+        for run in &runs[excess..] {
+            content.push_str(&format!("run\t{}\n", run.time));
+            for test in &run.tests {
+                content.push_str(&format!(
+                    "test\t{}\t{}\t{}\n",
+                    test.package,
+                    test.name,
+                    test.subtest.as_deref().unwrap_or_default()
+                ));
+            }
+            for command in &run.commands {
+                content.push_str(&format!("command\t{}\n", command.replace('\n', " ")));
+            }
+        }
+        self.write("runs", &content)
+    }
+
+    fn read(&self, name: &str) -> Option<String> {
+        std::fs::read_to_string(self.dir.join(name)).ok()
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.dir.join(name);
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// A recorded run: what was selected and the commands that ran it.
+#[derive(Debug, Clone)]
+pub struct Run {
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    pub tests: Vec<SelectedTest>,
+    pub commands: Vec<String>,
+}
+
+/// How a test's last run went.
+#[derive(Debug, Clone)]
+pub struct LastResult {
+    /// `pass`, `fail` or `skip`.
+    pub status: String,
+    pub seconds: f64,
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    /// The end of what the test printed, for failures and skips.
+    pub output: Vec<String>,
+}
+
+/// A selected entry, identified by name so that it can be found again after
+/// the tests were discovered anew.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedTest {
+    pub package: String,
+    pub name: String,
+    /// The subtest's run pattern below the test.
+    pub subtest: Option<String>,
+}
+
+fn state_dir() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+        })?;
+    Some(dir.join("gotestfinder"))
+}
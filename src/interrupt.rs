@@ -0,0 +1,47 @@
+//! Ctrl-C handling while `go test` owns the terminal.
+//!
+//! Ctrl-C reaches every process in the foreground group, so without this
+//! gotestfinder would die alongside `go test` and skip whatever it meant to
+//! report afterwards. A no-op handler is installed instead of `SIG_IGN`:
+//! caught signals are reset to their default on exec, so the child still
+//! gets Ctrl-C, whereas an ignored signal would stay ignored in it.
+
+/// Keeps SIGINT from terminating this process while alive.
+#[cfg(unix)]
+pub struct DeferInterrupts {
+    previous: libc::sighandler_t,
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {}
+
+#[cfg(unix)]
+impl DeferInterrupts {
+    pub fn new() -> Self {
+        let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // SAFETY: the handler does nothing, so it is async-signal-safe.
+        let previous = unsafe { libc::signal(libc::SIGINT, handler) };
+        DeferInterrupts { previous }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for DeferInterrupts {
+    fn drop(&mut self) {
+        // SAFETY: restores the disposition that was in place before `new`.
+        unsafe {
+            libc::signal(libc::SIGINT, self.previous);
+        }
+    }
+}
+
+/// Other platforms keep their default Ctrl-C handling.
+#[cfg(not(unix))]
+pub struct DeferInterrupts;
+
+#[cfg(not(unix))]
+impl DeferInterrupts {
+    pub fn new() -> Self {
+        DeferInterrupts
+    }
+}
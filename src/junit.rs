@@ -0,0 +1,138 @@
+//! JUnit XML reports of a run's results, for `--junit`.
+//!
+//! Each package becomes a `<testsuite>` and each test and subtest a
+//! `<testcase>`, with the output of failed tests in its `<failure>`. A
+//! package failing without a failed test, such as one that does not build
+//! or panics in `TestMain`, is reported as a failed `TestMain` case so that
+//! the failure is not lost.
+
+use crate::testjson::{Status, TestResult};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+
+pub fn write(path: &Path, results: &[TestResult]) -> Result<()> {
+    std::fs::write(path, render(results))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn render(results: &[TestResult]) -> String {
+    let mut packages: Vec<&str> = Vec::new();
+    for result in results {
+        if !packages.contains(&result.package.as_str()) {
+            packages.push(&result.package);
+        }
+    }
+
+    let mut suites = String::new();
+    let (mut total, mut total_failures, mut total_skipped, mut total_time) = (0, 0, 0, 0.0);
+    for package in packages {
+        let mut cases: Vec<TestResult> = results
+            .iter()
+            .filter(|result| result.package == package && result.test.is_some())
+            .cloned()
+            .collect();
+        let package_result = results
+            .iter()
+            .find(|result| result.package == package && result.test.is_none());
+        if let Some(result) = package_result
+            && result.status == Status::Fail
+            && cases.iter().all(|case| case.status != Status::Fail)
+        {
+            cases.push(TestResult {
+                test: Some("TestMain".to_string()),
+                ..result.clone()
+            });
+        }
+
+        let failures = cases
+            .iter()
+            .filter(|case| case.status == Status::Fail)
+            .count();
+        let skipped = cases
+            .iter()
+            .filter(|case| case.status == Status::Skip)
+            .count();
+        let time = package_result.map_or(0.0, |result| result.elapsed);
+        total += cases.len();
+        total_failures += failures;
+        total_skipped += skipped;
+        total_time += time;
+
+        let _ = writeln!(
+            suites,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" skipped="{}" time="{:.3}">"#,
+            escape(package),
+            cases.len(),
+            failures,
+            skipped,
+            time
+        );
+        for case in &cases {
+            let name = case.test.as_deref().unwrap_or_default();
+            let _ = write!(
+                suites,
+                r#"    <testcase classname="{}" name="{}" time="{:.3}""#,
+                escape(package),
+                escape(name),
+                case.elapsed
+            );
+            let output = escape(&case_output(case));
+            match case.status {
+                Status::Pass => suites.push_str("></testcase>\n"),
+                Status::Fail => {
+                    let _ = writeln!(
+                        suites,
+                        ">\n      <failure message=\"Failed\">{}</failure>\n    </testcase>",
+                        output
+                    );
+                }
+                Status::Skip => {
+                    let _ = writeln!(
+                        suites,
+                        ">\n      <skipped message=\"{}\"></skipped>\n    </testcase>",
+                        output.trim()
+                    );
+                }
+            }
+        }
+        suites.push_str("  </testsuite>\n");
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n\
+         {}</testsuites>\n",
+        total, total_failures, total_skipped, total_time, suites
+    )
+}
+
+/// What the test printed, without the lines a test binary ends with.
+fn case_output(case: &TestResult) -> String {
+    case.output
+        .split_inclusive('\n')
+        .filter(|line| {
+            let line = line.trim_end();
+            line != "PASS" && line != "FAIL" && !line.starts_with("FAIL\t")
+        })
+        .collect()
+}
+
+/// Escapes text for XML attributes and content, dropping the control
+/// characters XML 1.0 does not allow.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
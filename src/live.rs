@@ -0,0 +1,354 @@
+//! The live results pane, for `--live`.
+//!
+//! While the selected tests run, the terminal shows two panes instead of
+//! the rendered output: the tests on the left with their status as it
+//! changes, and on the right the output of the highlighted test. The panes
+//! are fed the same `go test -json` events the renderer is, and what would
+//! have been printed only goes to the log file meanwhile. Up and Down move
+//! the highlight, which otherwise follows the test that started last, and
+//! Ctrl-C interrupts the run. Once the run is over, the panes stay until q,
+//! Enter or Esc is pressed, and the usual report is printed after them.
+
+use crate::picker::truncate;
+use crate::testjson::{Event, Status};
+use anyhow::Result;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use crate::picker::Key;
+#[cfg(unix)]
+use crate::picker::terminal::RawTerminal;
+
+static VIEW: Mutex<Option<View>> = Mutex::new(None);
+
+/// Set once Ctrl-C was pressed in the panes, to skip the runs left.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Redraws while events pour in are at least this far apart.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long the key reader waits for a key before checking whether to stop.
+#[cfg(unix)]
+const KEY_POLL_MS: i32 = 100;
+
+/// A test or package in the left pane.
+struct Entry {
+    package: String,
+    /// `None` for the package's own result.
+    test: Option<String>,
+    /// `None` while running.
+    status: Option<Status>,
+    elapsed: Option<f64>,
+    output: Vec<String>,
+}
+
+struct View {
+    #[cfg(unix)]
+    terminal: RawTerminal,
+    #[cfg(unix)]
+    _interrupts: crate::interrupt::DeferInterrupts,
+    entries: Vec<Entry>,
+    focus: usize,
+    /// The highlight follows the test that started last until moved.
+    follow: bool,
+    done: bool,
+    drawn: Option<Instant>,
+}
+
+/// Shows the panes until dropped or, after the run, until dismissed with
+/// [`Live::finish`].
+pub struct Live {
+    #[cfg(unix)]
+    keys: Option<std::thread::JoinHandle<()>>,
+    /// Tells the key reader to stop, so that it does not take the keys
+    /// meant for what comes after the panes.
+    #[cfg(unix)]
+    stop: std::sync::Arc<AtomicBool>,
+}
+
+/// Whether the panes are showing, so output is held back.
+pub fn is_active() -> bool {
+    VIEW.lock().is_ok_and(|view| view.is_some())
+}
+
+/// Whether Ctrl-C was pressed in the panes.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+impl Live {
+    #[cfg(unix)]
+    pub fn start() -> Result<Live> {
+        let terminal = RawTerminal::open()?;
+        let mut keys = terminal.keys()?;
+        let mut view = View {
+            terminal,
+            _interrupts: crate::interrupt::DeferInterrupts::new(),
+            entries: Vec::new(),
+            focus: 0,
+            follow: true,
+            done: false,
+            drawn: None,
+        };
+        view.draw();
+        *VIEW.lock().unwrap() = Some(view);
+
+        let stop = std::sync::Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let handle = std::thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                let key = match keys.read_key_timeout(KEY_POLL_MS) {
+                    Ok(Some(key)) => key,
+                    Ok(None) => continue,
+                    Err(_) => return,
+                };
+                let mut view = VIEW.lock().unwrap();
+                let Some(view) = view.as_mut() else {
+                    return;
+                };
+                match key {
+                    Key::Up | Key::Char('k') => view.move_focus(-1),
+                    Key::Down | Key::Char('j') => view.move_focus(1),
+                    Key::Ctrl('c') if !view.done => {
+                        INTERRUPTED.store(true, Ordering::Relaxed);
+                        // go test gets Ctrl-C as it would from the terminal.
+                        // SAFETY: signals the process group, whose Ctrl-C
+                        // handling this process defers meanwhile.
+                        unsafe {
+                            libc::kill(0, libc::SIGINT);
+                        }
+                    }
+                    Key::Char('q') | Key::Enter | Key::Esc | Key::Ctrl('c') if view.done => {
+                        return;
+                    }
+                    _ => {}
+                }
+                view.drawn = None;
+                view.draw();
+            }
+        });
+
+        Ok(Live {
+            keys: Some(handle),
+            stop,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start() -> Result<Live> {
+        anyhow::bail!("--live needs a Unix terminal")
+    }
+
+    /// Marks the run as over and waits for the panes to be dismissed.
+    pub fn finish(mut self) {
+        #[cfg(unix)]
+        {
+            if let Some(view) = VIEW.lock().unwrap().as_mut() {
+                view.done = true;
+                view.drawn = None;
+                view.draw();
+            }
+            if let Some(keys) = self.keys.take() {
+                let _ = keys.join();
+            }
+        }
+    }
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        // Restores the terminal, also when the run failed.
+        VIEW.lock().unwrap().take();
+        #[cfg(unix)]
+        {
+            self.stop.store(true, Ordering::Relaxed);
+            if let Some(keys) = self.keys.take() {
+                let _ = keys.join();
+            }
+        }
+    }
+}
+
+/// Shows an event of a run.
+pub fn handle(event: &Event) {
+    let mut view = VIEW.lock().unwrap();
+    let Some(view) = view.as_mut() else {
+        return;
+    };
+    let position = view
+        .entries
+        .iter()
+        .position(|entry| entry.package == event.package && entry.test == event.test);
+
+    match event.action.as_str() {
+        // Packages are listed once they finish; tests as they start.
+        "run" => {
+            if position.is_none() {
+                view.entries.push(Entry {
+                    package: event.package.clone(),
+                    test: event.test.clone(),
+                    status: None,
+                    elapsed: None,
+                    output: Vec::new(),
+                });
+                if view.follow {
+                    view.focus = view.entries.len() - 1;
+                }
+            }
+        }
+        "output" | "build-output" => {
+            let Some(output) = &event.output else {
+                return;
+            };
+            match position {
+                Some(position) => view.entries[position].output.push(output.clone()),
+                // Package output before the package's result, such as what
+                // a build failure printed.
+                None if event.test.is_none() => view.entries.push(Entry {
+                    package: event.package.clone(),
+                    test: None,
+                    status: None,
+                    elapsed: None,
+                    output: vec![output.clone()],
+                }),
+                None => {}
+            }
+        }
+        "pass" | "fail" | "skip" | "bench" => {
+            let status = match event.action.as_str() {
+                "fail" => Status::Fail,
+                "skip" => Status::Skip,
+                _ => Status::Pass,
+            };
+            let position = position.unwrap_or_else(|| {
+                view.entries.push(Entry {
+                    package: event.package.clone(),
+                    test: event.test.clone(),
+                    status: None,
+                    elapsed: None,
+                    output: Vec::new(),
+                });
+                view.entries.len() - 1
+            });
+            let entry = &mut view.entries[position];
+            entry.status = Some(status);
+            entry.elapsed = event.elapsed;
+            view.drawn = None;
+        }
+        _ => return,
+    }
+    view.draw();
+}
+
+impl View {
+    fn move_focus(&mut self, by: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.focus = self.focus.saturating_add_signed(by).min(last);
+        self.follow = false;
+    }
+
+    #[cfg(not(unix))]
+    fn draw(&mut self) {}
+
+    #[cfg(unix)]
+    fn draw(&mut self) {
+        if self
+            .drawn
+            .is_some_and(|drawn| drawn.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.drawn = Some(Instant::now());
+
+        let (rows, cols) = self.terminal.size();
+        let screen = self.render(rows, cols);
+        let _ = self.terminal.write(&screen);
+    }
+
+    /// A status line, the tests on the left and the highlighted test's
+    /// output on the right.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn render(&self, rows: usize, cols: usize) -> String {
+        let count = |status| {
+            self.entries
+                .iter()
+                .filter(|entry| entry.test.is_some() && entry.status == status)
+                .count()
+        };
+        let status = format!(
+            "{} passed, {} failed, {} skipped, {} running",
+            count(Some(Status::Pass)),
+            count(Some(Status::Fail)),
+            count(Some(Status::Skip)),
+            count(None)
+        );
+        let help = if self.done {
+            "Done; q to leave"
+        } else {
+            "Up/Down to pick a test, Ctrl-C to interrupt"
+        };
+
+        let mut screen = String::from("\x1b[?25l\x1b[H");
+        screen.push_str(&format!(
+            "\x1b[1m{}\x1b[0m  \x1b[2m{}\x1b[0m\x1b[K",
+            truncate(&status, cols),
+            help
+        ));
+
+        let height = rows.saturating_sub(1);
+        let list_width = (cols / 3).clamp(20.min(cols), 60);
+        let offset = (self.focus + 1).saturating_sub(height);
+        for row in 0..height {
+            screen.push_str(&format!("\x1b[{};1H\x1b[K", row + 2));
+            let Some(entry) = self.entries.get(offset + row) else {
+                continue;
+            };
+            let (label, color) = match entry.status {
+                None => ("RUN ", "36"),
+                Some(Status::Pass) => ("PASS", "32"),
+                Some(Status::Fail) => ("FAIL", "31"),
+                Some(Status::Skip) => ("SKIP", "33"),
+            };
+            let name = match &entry.test {
+                Some(test) => format!("{}{}", "  ".repeat(test.matches('/').count()), test),
+                None => entry.package.clone(),
+            };
+            let marker = if offset + row == self.focus { '>' } else { ' ' };
+            let text = truncate(&name, list_width.saturating_sub(7));
+            screen.push_str(&format!(
+                "{}\x1b[{}m{}\x1b[0m {}",
+                marker, color, label, text
+            ));
+        }
+
+        // The end of the output, which is what a running test just printed.
+        let lines: Vec<String> = self
+            .entries
+            .get(self.focus)
+            .map(|entry| {
+                let mut lines: Vec<String> =
+                    entry.output.concat().lines().map(str::to_string).collect();
+                if let Some(elapsed) = entry.elapsed {
+                    lines.push(format!("\x1b[2m({:.2}s)\x1b[0m", elapsed));
+                }
+                lines
+            })
+            .unwrap_or_default();
+        let skip = lines.len().saturating_sub(height);
+        for row in 0..height {
+            let line = lines
+                .get(skip + row)
+                .map(String::as_str)
+                .unwrap_or_default();
+            screen.push_str(&format!(
+                "\x1b[{};{}H\x1b[2m│\x1b[0m {}",
+                row + 2,
+                list_width + 1,
+                truncate(line, cols.saturating_sub(list_width + 2))
+            ));
+        }
+        screen
+    }
+}
@@ -0,0 +1,148 @@
+//! Printing the report of a run, and copying it to the file given with
+//! `--log-file`.
+//!
+//! The file gets the output of the tests as shown in the terminal, along with
+//! gotestfinder's own report, without colors. `strftime` conversions in the
+//! path, such as `%Y-%m-%d_%H%M%S`, are replaced with the local time the run
+//! started at, so that every run can keep its own log.
+//!
+//! When stdout is kept for a machine-readable format, such as TAP, the
+//! report is printed to stderr instead.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static LOG: Mutex<Option<(PathBuf, File)>> = Mutex::new(None);
+
+static REPORT_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Creates the log file, replacing one of the same name, and returns its
+/// path.
+pub fn open(pattern: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(expand_time(pattern));
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let file = File::create(&path)
+        .with_context(|| format!("Failed to create log file {}", path.display()))?;
+
+    *LOG.lock().unwrap() = Some((path.clone(), file));
+    Ok(path)
+}
+
+pub fn is_open() -> bool {
+    LOG.lock().unwrap().is_some()
+}
+
+/// The path of the log file, once opened.
+pub fn path() -> Option<PathBuf> {
+    LOG.lock().unwrap().as_ref().map(|(path, _)| path.clone())
+}
+
+/// Appends `text` to the log file, if one is open, without color codes.
+pub fn write(text: &str) {
+    if let Some((_, file)) = LOG.lock().unwrap().as_mut() {
+        let _ = file.write_all(strip_ansi(text).as_bytes());
+    }
+}
+
+/// Prints the report to stderr from now on.
+pub fn report_to_stderr() {
+    REPORT_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Whether what test commands print has to pass through gotestfinder
+/// rather than go to the terminal directly.
+pub fn intercepts_output() -> bool {
+    is_open() || REPORT_TO_STDERR.load(Ordering::Relaxed) || crate::live::is_active()
+}
+
+/// Prints part of the report and copies it to the log file.
+pub fn print(text: &str) {
+    write(text);
+    // The live results pane takes the place of the report meanwhile.
+    if crate::live::is_active() {
+        return;
+    }
+    if REPORT_TO_STDERR.load(Ordering::Relaxed) {
+        let _ = std::io::stderr().lock().write_all(text.as_bytes());
+    } else {
+        let _ = std::io::stdout().lock().write_all(text.as_bytes());
+    }
+}
+
+/// Prints a line of the report and copies it to the log file.
+pub fn println(line: &str) {
+    print(&format!("{}\n", line));
+}
+
+/// Prints a line to stderr and copies it to the log file.
+pub fn eprintln(line: &str) {
+    if !crate::live::is_active() {
+        eprintln!("{}", line);
+    }
+    write(&format!("{}\n", line));
+}
+
+/// Removes the SGR escape sequences colored output is made of.
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[cfg(unix)]
+fn expand_time(pattern: &str) -> String {
+    if !pattern.contains('%') {
+        return pattern.to_string();
+    }
+    let Ok(format) = std::ffi::CString::new(pattern) else {
+        return pattern.to_string();
+    };
+
+    // SAFETY: `tm` is only read after localtime_r filled it in, and strftime
+    // writes at most `buffer.len()` bytes, returning how many it wrote.
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return pattern.to_string();
+        }
+        let mut buffer = vec![0u8; pattern.len() * 4 + 64];
+        let written = libc::strftime(
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            format.as_ptr(),
+            &tm,
+        );
+        if written == 0 {
+            return pattern.to_string();
+        }
+        String::from_utf8_lossy(&buffer[..written]).into_owned()
+    }
+}
+
+/// Other platforms take the path as given.
+#[cfg(not(unix))]
+fn expand_time(pattern: &str) -> String {
+    pattern.to_string()
+}
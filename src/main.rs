@@ -1,19 +1,75 @@
-use anyhow::Result;
+mod bincache;
+mod clipboard;
+mod config;
+mod constraint;
+mod convey;
+mod coverage;
+mod fzf;
+mod ginkgo;
+mod github;
+mod gitignore;
+mod gocheck;
+mod golist;
+mod gomod;
+mod highlight;
+mod history;
+mod interrupt;
+mod json;
+mod junit;
+mod live;
+mod logfile;
+mod parser;
+mod picker;
+mod quarantine;
+mod subtests;
+mod tap;
+mod testjson;
+mod theme;
+mod watch;
+mod worktree;
+
+use anyhow::{Context, Result};
 use clap::Parser;
 use regex::Regex;
 use skim::prelude::*;
-use std::io::Cursor;
-use std::io::{self, Write};
-use std::path::Path;
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, mpsc};
+use std::time::{Duration, Instant};
+use subtests::Subtest;
 use walkdir::WalkDir;
 
+/// `println!` for the report of a run, which `--log-file` keeps a copy of.
+macro_rules! outln {
+    () => {
+        logfile::println("")
+    };
+    ($($arg:tt)*) => {
+        logfile::println(&format!($($arg)*))
+    };
+}
+
+/// `eprintln!` for warnings during a run, which `--log-file` keeps a copy of.
+macro_rules! errln {
+    ($($arg:tt)*) => {
+        logfile::eprintln(&format!($($arg)*))
+    };
+}
+
 #[derive(Parser)]
 #[command(name = "gotestfinder")]
 #[command(about = "Find and run Go tests with fuzzy selection")]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Directory to search for tests
-    directory: String,
+    #[arg(required = true)]
+    directory: Option<String>,
 
     /// Show individual subtests
     #[arg(long, default_value = "true")]
@@ -23,6 +79,13 @@ struct Args {
     #[arg(long, default_value = "true")]
     parent: bool,
 
+    /// How test names are anchored in printed patterns and in the -run
+    /// pattern of runs: `full` (`^TestFoo$`), `prefix` (`^TestFoo`, also
+    /// matching `TestFooBar`) or `none` (`TestFoo`, for tools adding their
+    /// own anchors). Each level of a subtest's name is anchored on its own
+    #[arg(long, value_enum, default_value_t = Anchor::Full)]
+    anchor: Anchor,
+
     /// Use skim for interactive test selection and execution
     #[arg(long)]
     fzf: bool,
@@ -31,239 +94,5583 @@ struct Args {
     #[arg(long)]
     tags: Option<String>,
 
+    /// Run the selected tests with the race detector (-race)
+    #[arg(long)]
+    race: bool,
+
+    /// Run each selected test and benchmark this many times (-count); 0
+    /// leaves -count out so go may reuse cached results
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    count: u32,
+
+    /// Run at most this many parallel tests at once in each test binary
+    /// (-parallel)
+    #[arg(long, value_name = "N")]
+    parallel: Option<u32>,
+
+    /// Build and run at most this many packages at once (-p)
+    #[arg(long, value_name = "N")]
+    package_parallel: Option<u32>,
+
+    /// Set an environment variable for the test runs; may be repeated and
+    /// overrides the `[env]` of configuration files
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_var)]
+    env: Vec<(String, String)>,
+
+    /// Bind a selector key to an action (race, verbose, debug, rerun-failed,
+    /// copy, copy-pattern or run), such as `ctrl-r=race`; may be repeated
+    /// and overrides the `[keys]` of configuration files
+    #[arg(long = "bind", value_name = "KEY=ACTION", value_parser = parse_binding)]
+    bindings: Vec<(String, String)>,
+
+    /// Selector to pick tests with: `skim`, `builtin` for a simpler one
+    /// drawn without skim, for terminals where skim misbehaves, or `fzf` to
+    /// run the installed fzf
+    #[arg(long, value_enum, default_value_t = Ui::Skim)]
+    ui: Ui,
+
+    /// Selector colors: `dark`, `light`, `none` or a skim color string such
+    /// as `dark,matched:108`; detected from the terminal's background by
+    /// default, and overrides `theme` in the `[ui]` of configuration files
+    #[arg(long, value_name = "THEME")]
+    theme: Option<String>,
+
+    /// Open the selector with this query already typed, such as `Login`
+    #[arg(long, value_name = "TEXT")]
+    query: Option<String>,
+
+    /// Start selector entries with their package, shortened to what tells
+    /// packages apart, as in `pkg/auth :: TestLogin`, and keep each
+    /// package's entries together
+    #[arg(long)]
+    group: bool,
+
+    /// Match selector queries against the package and the file declaring
+    /// each test as well, such as `auth_test Login`, instead of only the
+    /// test's name
+    #[arg(long)]
+    match_all: bool,
+
+    /// Match each term of selector and --filter queries as a substring
+    /// instead of fuzzily, so `Login` no longer matches `TestLogOut/admin`.
+    /// Alt-E toggles it in the selector
+    #[arg(long)]
+    exact: bool,
+
+    /// How selector and --filter queries treat case: smart ignores it
+    /// unless a term has capitals, so `login` matches `TestLogin` and
+    /// `Login` does not match `TestloginFlow`
+    #[arg(long, value_enum, default_value_t = Case::Smart)]
+    case: Case,
+
+    /// Skip the selector and take the tests matching QUERY, fuzzily like
+    /// the selector or as a regex between slashes (`/^TestLogin/`); with
+    /// --fzf they are run, otherwise listed
+    #[arg(long, value_name = "QUERY", value_parser = parse_filter)]
+    filter: Option<TestFilter>,
+
+    /// Height of the selector, in rows or as a percentage of the terminal,
+    /// such as `100%` for the full screen
+    #[arg(long, value_name = "HEIGHT", default_value = "50%", value_parser = parse_height)]
+    height: String,
+
+    /// Minimum height of the selector in rows, when `--height` is a
+    /// percentage
+    #[arg(long, value_name = "ROWS", default_value_t = 10)]
+    min_height: usize,
+
+    /// Where the selector's prompt and entries go
+    #[arg(long, value_enum, default_value_t = Layout::Default)]
+    layout: Layout,
+
+    /// Clear the screen once the selector closes, instead of bringing back
+    /// what was on it before
+    #[arg(long, conflicts_with = "no_clear")]
+    clear: bool,
+
+    /// Leave the selector as it was last drawn once it closes, instead of
+    /// bringing back what was on the screen before
+    #[arg(long)]
+    no_clear: bool,
+
+    /// Draw the selector beneath the prompt, --height rows tall, instead of
+    /// on the alternate screen, as fzf does with --height
+    #[arg(long)]
+    inline: bool,
+
+    /// Run the tests in short mode (-short); selector entries of tests that
+    /// skip themselves under it are marked
+    #[arg(long)]
+    short: bool,
+
+    /// Stop at the first failing test (-failfast), skipping the runs of
+    /// other packages as well
+    #[arg(long)]
+    failfast: bool,
+
+    /// Run the tests in random order (-shuffle), with a time-based seed or
+    /// the one given; the seed used is kept for --replay-shuffle
+    #[arg(
+        long,
+        value_name = "SEED",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "on"
+    )]
+    shuffle: Option<String>,
+
+    /// Shuffle with the seed of the last shuffled run of this project
+    #[arg(long, conflicts_with = "shuffle")]
+    replay_shuffle: bool,
+
+    /// Skip the selector and rerun the tests that failed in the last run of
+    /// this project
+    #[arg(long)]
+    rerun_failed: bool,
+
+    /// Order of the listed tests and selector entries; `recent` puts the
+    /// tests in the files changed last first, `duration` those that took
+    /// longest in their last run and `status` those that failed in it
+    #[arg(long, value_enum, default_value_t = SortOrder::Source)]
+    sort: SortOrder,
+
+    /// Skip the selector and rerun the most recent selection of this project
+    #[arg(long, conflicts_with = "rerun_failed")]
+    last: bool,
+
+    /// Skip the selector and run every discovered test, like `go test ./...`
+    /// but leaving out benchmarks, quarantined tests unless
+    /// --ignore-quarantined is given, and what --filter or other flags
+    /// exclude
+    #[arg(long, conflicts_with_all = ["rerun_failed", "last"])]
+    all: bool,
+
+    /// Return to the selector after each run, with its query and selection
+    /// kept, until it is left with ESC
+    #[arg(long, conflicts_with_all = ["rerun_failed", "last", "all", "filter"])]
+    session: bool,
+
+    /// Open the selector with the tests of the last recorded run selected,
+    /// rather than only with the cursor on them
+    #[arg(long, conflicts_with_all = ["rerun_failed", "last", "all", "filter"])]
+    select_last: bool,
+
+    /// Keep each package's compiled test binary and rerun it directly while
+    /// the module's sources are unchanged
+    #[arg(long)]
+    binary_cache: bool,
+
+    /// Debug the selected test under Delve (dlv test) instead of running it
+    #[arg(long)]
+    debug: bool,
+
+    /// Fail a test binary that runs longer than this (-timeout, e.g. 30s)
+    #[arg(long, value_name = "DURATION")]
+    timeout: Option<String>,
+
+    /// Checks go vet runs before the tests (-vet): `off` to skip vet,
+    /// `default` for go's own selection, or a comma-separated list of
+    /// analyzers such as `atomic,copylocks,printf,unusedresult`
+    #[arg(long, value_name = "CHECKS", value_parser = parse_vet)]
+    vet: Option<String>,
+
+    /// Run each selected benchmark for this long or this many times
+    /// (-benchtime, e.g. 5s or 100x)
+    #[arg(long, value_name = "DURATION")]
+    benchtime: Option<String>,
+
+    /// Report memory allocations of selected benchmarks (-benchmem)
+    #[arg(long)]
+    benchmem: bool,
+
+    /// Fuzz each selected fuzz target for this long or this many times
+    /// (-fuzztime, e.g. 30s or 1000x); fuzzing runs until Ctrl-C otherwise
+    #[arg(long, value_name = "DURATION")]
+    fuzztime: Option<String>,
+
+    /// Collect coverage for the selected tests and print a per-package summary
+    #[arg(long)]
+    cover: bool,
+
+    /// Open the HTML coverage report once the tests have run
+    #[arg(long, requires = "cover")]
+    cover_html: bool,
+
+    /// Keep the merged coverage profile at this path
+    #[arg(long, value_name = "FILE", requires = "cover")]
+    cover_out: Option<PathBuf>,
+
+    /// Also run the selected tests with coverage on this git revision, in a
+    /// temporary worktree, and list the functions whose coverage changed.
+    /// Implies --cover
+    #[arg(long, value_name = "REF")]
+    base_ref: Option<String>,
+
+    /// Write a CPU profile of each package's tests (-cpuprofile)
+    #[arg(long)]
+    cpuprofile: bool,
+
+    /// Write a memory profile of each package's tests (-memprofile)
+    #[arg(long)]
+    memprofile: bool,
+
+    /// Write a goroutine blocking profile of each package's tests
+    /// (-blockprofile)
+    #[arg(long)]
+    blockprofile: bool,
+
+    /// Directory for the profiles and the test binaries they were taken
+    /// of; a directory in the system's temporary directory by default
+    #[arg(long, value_name = "DIR")]
+    profile_dir: Option<PathBuf>,
+
+    /// Open the profiles in `go tool pprof -http` once the tests have run
+    #[arg(long)]
+    pprof: bool,
+
+    /// Target operating system to list and run tests for (sets GOOS)
+    #[arg(long)]
+    goos: Option<String>,
+
+    /// Target architecture to list and run tests for (sets GOARCH)
+    #[arg(long)]
+    goarch: Option<String>,
+
     /// Enable verbose output (-v flag for go test)
     #[arg(short, long)]
     verbose: bool,
-}
 
-#[derive(Debug, Clone)]
-struct TestInfo {
-    name: String,
-    #[allow(dead_code)]
-    file: String,
-    #[allow(dead_code)]
-    line: usize,
-    subtests: Vec<String>,
-}
+    /// Go toolchain binary to run, e.g. gotip
+    #[arg(
+        long,
+        value_name = "PATH",
+        env = "GOTESTFINDER_GO",
+        default_value = "go"
+    )]
+    go_bin: PathBuf,
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    /// Show the plain output of go test instead of per-test result lines
+    /// rendered from its -json events
+    #[arg(long)]
+    raw: bool,
 
-    let tests = find_tests(&args.directory)?;
+    /// Show the tests' status live while they run, next to the output of
+    /// the highlighted test, instead of printing results as they come in
+    #[arg(long, conflicts_with = "raw")]
+    live: bool,
 
-    if args.fzf {
-        run_with_skim(tests, args.tags, args.verbose)?;
-    } else {
-        print_tests(&tests, args.subtests, args.parent);
-    }
+    /// Do not fail the run when only tests listed in .gotestfinder-quarantine
+    /// failed
+    #[arg(long)]
+    ignore_quarantined: bool,
 
-    Ok(())
-}
+    /// Print the commands that would run the selection instead of running it
+    #[arg(long)]
+    dry_run: bool,
 
-fn find_tests(dir: &str) -> Result<Vec<TestInfo>> {
-    let mut tests = Vec::new();
+    /// Copy the selection's go test command, or only its -run pattern, to
+    /// the clipboard instead of running it
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "command"
+    )]
+    copy: Option<CopyTarget>,
 
-    for entry in WalkDir::new(dir) {
-        let entry = entry?;
-        let path = entry.path();
+    /// Exit status policy: `propagate` go test's status, `always-zero` even
+    /// when tests fail, or `count-failures` to exit with the number of
+    /// failed tests (at most 100). Errors of gotestfinder itself exit with
+    /// 125 and runs with nothing selected with 124
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = ExitPolicy::Propagate)]
+    exit_code: ExitPolicy,
 
-        if path.extension().is_some_and(|ext| ext == "go")
-            && path
-                .file_name()
-                .is_some_and(|name| name.to_string_lossy().ends_with("_test.go"))
-        {
-            tests.extend(parse_test_file(path)?);
-        }
-    }
+    /// Also write the output of the run, without colors, to this file.
+    /// strftime conversions such as `%Y%m%d-%H%M%S` are replaced with the
+    /// current time
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<String>,
 
-    Ok(tests)
-}
+    /// Write a JUnit XML report of the results to this file
+    #[arg(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
 
-fn parse_test_file(path: &Path) -> Result<Vec<TestInfo>> {
-    let content = std::fs::read_to_string(path)?;
-    let mut tests = Vec::new();
+    /// Print a GitHub Actions `::error` annotation for each failing test, at
+    /// the line declaring it
+    #[arg(long)]
+    gh_annotations: bool,
 
-    let test_func_regex = Regex::new(r"func\s+(Test\w+)\s*\([^)]*\*testing\.[TB]\w*\)")?;
-    let subtest_regex = Regex::new(r#"\.Run\s*\(\s*"([^"]+)""#)?;
+    /// Format of the results: `text` to render them for reading, or `tap`
+    /// to print Test Anything Protocol on stdout, with the rest of the
+    /// report on stderr. When listing the tests, `json` prints them with
+    /// their subtests, files and annotations as a JSON document, and
+    /// `ndjson` as one JSON object per line, each file's as soon as it is
+    /// parsed
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
 
-    let lines: Vec<&str> = content.lines().collect();
+    /// Run the go test commands of up to N packages at once, starting each
+    /// line they print with the package
+    #[arg(long, short = 'j', value_name = "N", default_value_t = 1)]
+    jobs: usize,
 
-    for (line_num, line) in lines.iter().enumerate() {
-        if let Some(caps) = test_func_regex.captures(line) {
-            let test_name = caps.get(1).unwrap().as_str().to_string();
-            let mut subtests = Vec::new();
+    /// Run each selected test in a go test command of its own, one after
+    /// the other, so that a test crashing its binary cannot hide the
+    /// results of the others; lists each run's status and duration
+    #[arg(long, conflicts_with = "jobs")]
+    isolate: bool,
 
-            let mut brace_count = 0;
-            let mut in_function = false;
+    /// Rerun the tests that failed up to N more times; tests that pass on a
+    /// retry are reported as flaky and do not fail the run
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
 
-            for (_, &func_line) in lines.iter().enumerate().skip(line_num) {
-                if func_line.contains('{') {
-                    brace_count += func_line.matches('{').count();
-                    in_function = true;
-                }
-                if func_line.contains('}') {
-                    brace_count = brace_count.saturating_sub(func_line.matches('}').count());
-                }
+    /// Number of slowest tests listed in the summary after a run
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    slowest: usize,
 
-                if in_function && brace_count == 0 {
-                    break;
-                }
+    /// Program running the selected tests
+    #[arg(long, value_enum, default_value_t = Runner::Go)]
+    runner: Runner,
 
-                if in_function {
-                    for caps in subtest_regex.captures_iter(func_line) {
-                        if let Some(subtest_name) = caps.get(1) {
-                            subtests.push(subtest_name.as_str().to_string());
-                        }
-                    }
-                }
-            }
+    /// How test functions are discovered
+    #[arg(long, value_enum, default_value_t = Backend::Parser)]
+    backend: Backend,
 
-            tests.push(TestInfo {
-                name: test_name,
-                file: path.to_string_lossy().to_string(),
-                line: line_num + 1,
-                subtests,
-            });
+    /// Use the legacy line-based regex scanner (same as --backend=regex)
+    #[arg(long)]
+    regex: bool,
+
+    /// Extra test binary arguments for packages that define TestMain
+    #[arg(long, value_name = "ARGS")]
+    test_main_args: Option<String>,
+
+    /// Leave out tests that always call t.Skip
+    #[arg(long)]
+    hide_skipped: bool,
+
+    /// Leave out tests that skip themselves when testing.Short() is true
+    #[arg(long)]
+    hide_short_skipped: bool,
+
+    /// Only offer tests that call t.Parallel
+    #[arg(long, conflicts_with = "no_parallel")]
+    only_parallel: bool,
+
+    /// Only offer tests that do not call t.Parallel
+    #[arg(long)]
+    no_parallel: bool,
+
+    /// Only offer black-box tests from external `_test` packages
+    #[arg(long, conflicts_with = "only_internal")]
+    only_external: bool,
+
+    /// Only offer white-box tests declared in the package itself
+    #[arg(long)]
+    only_internal: bool,
+
+    /// Descend into a directory skipped by default (vendor, testdata,
+    /// node_modules, hidden directories); may be repeated
+    #[arg(long = "include-dir", value_name = "NAME")]
+    include_dirs: Vec<String>,
+
+    /// Skip directories with this name; may be repeated
+    #[arg(long = "exclude-dir", value_name = "NAME")]
+    exclude_dirs: Vec<String>,
+
+    /// Descend into symlinked directories (symlink cycles are skipped)
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Also discover tests in files ignored by .gitignore, .ignore or git's
+    /// global excludes
+    #[arg(long)]
+    no_ignore: bool,
+
+    /// Also discover tests in generated files (// Code generated ... DO NOT EDIT.)
+    #[arg(long)]
+    include_generated: bool,
+
+    /// Include benchmark functions (run with -bench instead of -run)
+    #[arg(long)]
+    benchmarks: bool,
+
+    /// List Ginkgo specs under their suite's bootstrap test
+    #[arg(long)]
+    ginkgo: bool,
+
+    /// Run go test from this directory instead of the module root of each
+    /// selected test
+    #[arg(long, value_name = "DIR")]
+    chdir: Option<PathBuf>,
+
+    /// Run selected tests with `./...` instead of only the packages that
+    /// own them
+    #[arg(long)]
+    all_packages: bool,
+
+    /// Extra arguments passed verbatim to go test (after `--`)
+    #[arg(last = true, value_name = "GO_TEST_ARGS")]
+    go_test_args: Vec<String>,
+}
+
+#[derive(clap::Subcommand)]
+enum Commands {
+    /// Select tests, run them, and rerun them whenever the Go sources below
+    /// the directory change; run flags go before `watch`
+    Watch {
+        /// Directory to search for tests and watch
+        directory: String,
+    },
+    /// Browse the recorded runs of the project and replay one; run flags go
+    /// before `history`
+    History {
+        /// Directory of the project
+        directory: String,
+    },
+}
+
+impl Args {
+    fn directory(&self) -> &str {
+        match &self.command {
+            Some(Commands::Watch { directory } | Commands::History { directory }) => directory,
+            None => self.directory.as_deref().unwrap_or("."),
         }
     }
+}
 
-    Ok(tests)
+/// Program running the selected tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Runner {
+    /// Run `go test` directly.
+    Go,
+    /// Run `go test` through `gotestsum --format testname`.
+    Gotestsum,
+    /// Use gotestsum when it is on `PATH`, `go test` otherwise.
+    Auto,
 }
 
-fn print_tests(tests: &[TestInfo], show_subtests: bool, show_parent: bool) {
-    for test in tests {
-        if test.subtests.is_empty() {
-            println!("^{}$", test.name);
-        } else {
-            if show_parent {
-                println!("^{}$", test.name);
-            }
-            if show_subtests {
-                for subtest in &test.subtests {
-                    println!("^{}/{}$", test.name, subtest);
-                }
+/// How the exit status reflects failing tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ExitPolicy {
+    /// Exit with go test's exit status.
+    Propagate,
+    /// Exit with 0 even when tests failed.
+    AlwaysZero,
+    /// Exit with the number of failed tests, at most 100.
+    CountFailures,
+}
+
+/// How the results of a run, or the listed tests, are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Colored result lines and a summary.
+    Text,
+    /// Test Anything Protocol, version 13.
+    Tap,
+    /// The listed tests as a JSON document, for editors and scripts.
+    Json,
+    /// The listed tests as one JSON object per line, streamed as they are
+    /// found.
+    Ndjson,
+}
+
+/// Which selector tests are picked with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Ui {
+    /// skim's fuzzy finder.
+    Skim,
+    /// The selector of the picker module.
+    Builtin,
+    /// The `fzf` on `PATH`.
+    Fzf,
+}
+
+/// Tests picked by `--filter`, by their name and package.
+#[derive(Debug, Clone)]
+enum TestFilter {
+    /// A query matched like the selector's.
+    Fuzzy(String),
+    Regex(Regex),
+}
+
+impl TestFilter {
+    /// Whether the test or subtest `name` of `package` matches. Fuzzy
+    /// queries see `name [package]`, as the selector shows it, and match
+    /// their terms as substrings when `exact` and treat case as `case`
+    /// says; regexes see the name alone and respect case unless they have
+    /// `(?i)`.
+    fn matches(&self, name: &str, package: &str, exact: bool, case: Case) -> bool {
+        match self {
+            TestFilter::Fuzzy(query) => {
+                picker::matches(&format!("{}  [{}]", name, package), query, exact, case)
             }
+            TestFilter::Regex(regex) => regex.is_match(name),
         }
     }
 }
 
-fn run_with_skim(tests: Vec<TestInfo>, tags: Option<String>, verbose: bool) -> Result<()> {
-    let test_patterns = collect_test_patterns(&tests);
+/// Arrangement of the selector, as skim and fzf name them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Layout {
+    /// The prompt at the bottom, entries listed upwards from it.
+    Default,
+    /// The prompt at the top, entries listed downwards from it.
+    Reverse,
+    /// The prompt at the bottom, entries listed downwards from the top.
+    ReverseList,
+}
 
-    if test_patterns.is_empty() {
-        println!("No tests found");
-        return Ok(());
+impl Layout {
+    fn name(self) -> &'static str {
+        match self {
+            Layout::Default => "default",
+            Layout::Reverse => "reverse",
+            Layout::ReverseList => "reverse-list",
+        }
     }
+}
+
+/// What the selector leaves on the terminal once it closes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    /// The selector is drawn on the alternate screen, and the screen it
+    /// covered comes back.
+    Restore,
+    /// The screen is cleared.
+    Clear,
+    /// The selector stays as it was last drawn.
+    Keep,
+}
 
-    let selected_tests = skim_select(&test_patterns)?;
+/// Whether queries match regardless of case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Case {
+    /// Ignore case unless the term has capitals.
+    Smart,
+    Sensitive,
+    Insensitive,
+}
 
-    if selected_tests.is_empty() {
-        println!("No tests selected");
-        return Ok(());
+impl Case {
+    fn skim(self) -> CaseMatching {
+        match self {
+            Case::Smart => CaseMatching::Smart,
+            Case::Sensitive => CaseMatching::Respect,
+            Case::Insensitive => CaseMatching::Ignore,
+        }
     }
+}
 
-    let run_pattern = build_run_pattern(&selected_tests);
-    execute_go_test(&run_pattern, tags, verbose)?;
-
-    Ok(())
+/// What `--copy` puts on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum CopyTarget {
+    /// The full go test command line.
+    Command,
+    /// Only the -run pattern.
+    Pattern,
 }
 
-fn collect_test_patterns(tests: &[TestInfo]) -> Vec<String> {
-    let mut patterns = Vec::new();
+/// How a test name is anchored in a `-run` pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Anchor {
+    /// Match exactly the name.
+    Full,
+    /// Match names starting with the name.
+    Prefix,
+    /// Match names containing the name.
+    None,
+}
 
-    for test in tests {
-        if test.subtests.is_empty() {
-            patterns.push(test.name.clone());
-        } else {
-            patterns.push(test.name.clone());
-            for subtest in &test.subtests {
-                patterns.push(format!("{}/{}", test.name, subtest));
-            }
+impl Anchor {
+    fn apply(self, pattern: &str) -> String {
+        match self {
+            Anchor::Full => format!("^{}$", pattern),
+            Anchor::Prefix => format!("^{}", pattern),
+            Anchor::None => pattern.to_string(),
         }
     }
 
-    patterns
+    /// Anchors each `/`-separated level of `pattern`, since `go test -run`
+    /// matches the levels of a name one by one. Levels with anchors of
+    /// their own, as those of dynamic subtests, are kept as they are.
+    fn apply_levels(self, pattern: &str) -> String {
+        pattern
+            .split('/')
+            .map(|level| {
+                if level.starts_with('^') {
+                    level.to_string()
+                } else {
+                    self.apply(level)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
 }
 
-fn skim_select(options: &[String]) -> Result<Vec<String>> {
-    let options_str = options.join("\n");
-    let item_reader = SkimItemReader::default();
-    let items = item_reader.of_bufread(Cursor::new(options_str));
+/// Order of the listed tests and selector entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum SortOrder {
+    /// The order tests are declared in, package by package.
+    Source,
+    /// By test name, and subtests by theirs.
+    Name,
+    /// By file and line.
+    File,
+    /// By import path, keeping each package's tests in source order.
+    Package,
+    /// Tests in the most recently modified files first.
+    Recent,
+    /// Slowest first by the last recorded run; tests never run come last.
+    Duration,
+    /// Failures of the last recorded runs first, latest first, then the
+    /// other tests that ran; tests never run come last.
+    Status,
+}
 
-    let skim_options = SkimOptionsBuilder::default()
-        .height("50%".to_string())
-        .color(Some("light".to_string()))
-        .multi(true)
-        .prompt("Select tests (TAB to multi-select): ".to_string())
-        .header(Some(
-            "Press TAB to select multiple tests, ENTER to confirm".to_string(),
-        ))
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to build skim options: {}", e))?;
+/// Source of the listed test functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Backend {
+    /// Parse the Go sources.
+    Parser,
+    /// The legacy line-based regex scanner.
+    Regex,
+    /// Ask `go test -list` for each package's tests, parsing the sources
+    /// for subtests.
+    GoList,
+}
 
-    let result = Skim::run_with(&skim_options, Some(items));
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TestKind {
+    Test,
+    Benchmark,
+    Fuzz,
+}
 
-    print!("\x1b[2J\x1b[H");
-    io::stdout().flush().unwrap();
+/// Whether a test calls `t.Skip`, `t.Skipf` or `t.SkipNow`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Skip {
+    /// The call is a top-level statement of the test body.
+    Always,
+    /// The call is nested in a branch or closure.
+    Conditional,
+}
 
-    if let Some(output) = result {
-        if output.is_abort {
-            return Ok(vec![]);
+/// Framework that owns a test's subtests, which decides how a selected
+/// subtest is handed to `go test`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framework {
+    Standard,
+    Ginkgo,
+    Gocheck,
+    Convey,
+}
+
+impl Framework {
+    /// Test binary arguments selecting the given subtests, for frameworks
+    /// that filter their own subtests instead of going through `-run`.
+    fn filter_args(self, subtests: &[&str]) -> Option<Vec<String>> {
+        match self {
+            Framework::Standard | Framework::Convey => None,
+            Framework::Ginkgo => Some(vec![format!(
+                "--ginkgo.focus={}",
+                ginkgo::focus_pattern(subtests)
+            )]),
+            Framework::Gocheck => Some(vec![format!(
+                "-check.f={}",
+                gocheck::filter_pattern(subtests)
+            )]),
         }
+    }
+}
 
-        Ok(output
-            .selected_items
-            .iter()
-            .map(|item| item.output().to_string())
-            .collect())
-    } else {
-        Ok(vec![])
+#[derive(Debug, Clone)]
+struct TestInfo {
+    name: String,
+    kind: TestKind,
+    /// Import path of the package the test belongs to.
+    package: String,
+    file: String,
+    line: usize,
+    subtests: Vec<Subtest>,
+    /// Receiver type of the testify suite this entry point runs, if any.
+    suite: Option<String>,
+    framework: Framework,
+    /// Build constraint of the file the test is declared in.
+    constraint: Option<constraint::Expr>,
+    skip: Option<Skip>,
+    /// Returns or skips early when `testing.Short()` is true.
+    short_skip: bool,
+    /// The test's package defines `TestMain`, which wraps every test run.
+    has_test_main: bool,
+    parallel: bool,
+    /// Declared in the external `foo_test` package rather than `foo`.
+    external: bool,
+    /// Root of the test's module when it differs from the module of the
+    /// current directory, so `go test` has to run from there, or the
+    /// directory given with `--chdir`.
+    module_dir: Option<PathBuf>,
+    /// The test's module is not listed in the enclosing `go.work`.
+    outside_workspace: bool,
+}
+
+/// Declarations that are frequently spread over several files of a package
+/// (suite methods, Ginkgo specs) and are therefore attached to their
+/// entry points once the whole tree has been walked.
+#[derive(Default)]
+struct PackageIndex {
+    /// Test methods keyed by package directory and receiver type.
+    suite_methods: HashMap<(PathBuf, String), Vec<String>>,
+    /// Ginkgo spec texts keyed by package directory.
+    ginkgo_specs: HashMap<PathBuf, Vec<String>>,
+    /// Types registered with gocheck's `check.Suite`, keyed by package
+    /// directory.
+    gocheck_suites: HashMap<PathBuf, Vec<String>>,
+    /// Directories of packages that define `TestMain`.
+    test_main: HashSet<PathBuf>,
+    /// Functions taking a `*testing.T` that run subtests, keyed by package
+    /// directory, package name and function name.
+    helpers: HashMap<(PathBuf, String, String), subtests::Helper>,
+    /// Calls tests make passing their `t` on, keyed by package directory
+    /// and test name, along with the test's package name.
+    helper_calls: HashMap<(PathBuf, String), (String, Vec<subtests::HelperCall>)>,
+}
+
+const TESTIFY_SUITE: &str = "github.com/stretchr/testify/suite";
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(outcome) => {
+            if let Some(path) = logfile::path() {
+                eprintln!("Log written to {}", path.display());
+            }
+            outcome.exit_code(args.exit_code)
+        }
+        Err(err) => {
+            errln!("Error: {:?}", err);
+            ExitCode::from(EXIT_TOOL_ERROR)
+        }
     }
 }
 
-fn build_run_pattern(selected_tests: &[String]) -> String {
-    if selected_tests.is_empty() {
-        return String::new();
+fn run(args: &Args) -> Result<Outcome> {
+    if let Some(pattern) = &args.log_file {
+        logfile::open(pattern)?;
+    }
+    if args.output == OutputFormat::Tap {
+        logfile::report_to_stderr();
+    }
+    let runs = args.command.is_some()
+        || args.fzf
+        || args.session
+        || args.rerun_failed
+        || args.last
+        || args.all;
+    let json = matches!(args.output, OutputFormat::Json | OutputFormat::Ndjson);
+    if json && runs {
+        anyhow::bail!("--output json and ndjson only apply to listing the tests");
+    }
+    if args.output == OutputFormat::Ndjson && args.backend == Backend::GoList {
+        anyhow::bail!("--output ndjson cannot stream the tests `go test -list` reports");
     }
 
-    if selected_tests.len() == 1 {
-        return selected_tests[0].clone();
+    match args.command {
+        Some(Commands::Watch { .. }) => return run_watch(args),
+        Some(Commands::History { .. }) => return run_history(args),
+        None => {}
     }
 
-    selected_tests.join("|")
+    // Rerunning earlier selections skips the selector but runs all the same.
+    if args.fzf || args.session || args.rerun_failed || args.last || args.all {
+        let options = run_options(args)?;
+        if streams_selection(args, &options) {
+            return run_streamed(args, options);
+        }
+        let (tests, filtered) = discover_for_run(args)?;
+        let options = RunOptions {
+            filtered,
+            ..options
+        };
+        run_with_skim(tests, &options)
+    } else if args.output == OutputFormat::Ndjson {
+        stream_tests_json(args)
+    } else {
+        let (mut tests, _) = discover(args)?;
+        if let Some(filter) = &args.filter {
+            filter_tests(&mut tests, filter, args.exact, args.case);
+            if tests.is_empty() {
+                eprintln!("No tests match the filter");
+                return Ok(Outcome::NothingSelected);
+            }
+        }
+        if args.sort != SortOrder::Source {
+            let history = history::History::open(Path::new(args.directory()));
+            let durations = history.as_ref().map(|h| h.durations()).unwrap_or_default();
+            let last_results = history
+                .as_ref()
+                .map(|h| h.last_results())
+                .unwrap_or_default();
+            tests = sort_tests(&tests, args.sort, &durations, &last_results)
+                .into_iter()
+                .map(|(index, subtests)| {
+                    let mut test = tests[index].clone();
+                    test.subtests = subtests
+                        .into_iter()
+                        .map(|subtest| tests[index].subtests[subtest].clone())
+                        .collect();
+                    test
+                })
+                .collect();
+        }
+        if args.output == OutputFormat::Json {
+            print_tests_json(&tests, args.anchor);
+        } else {
+            print_tests(&tests, args.subtests, args.parent, args.anchor);
+        }
+        Ok(Outcome::Passed)
+    }
 }
 
-fn execute_go_test(run_pattern: &str, tags: Option<String>, verbose: bool) -> Result<()> {
-    let mut cmd = Command::new("go");
-    cmd.args(["test", "-count=1"]);
+/// Exit status when gotestfinder itself fails, whatever the policy.
+const EXIT_TOOL_ERROR: u8 = 125;
 
-    if verbose {
-        cmd.arg("-v");
-    }
+/// Exit status when no tests were found or none were selected.
+const EXIT_NOTHING_SELECTED: u8 = 124;
 
-    if let Some(tags_value) = tags {
-        cmd.arg(format!("-tags={}", tags_value));
-    }
+/// Exit statuses of failed runs counted by `count-failures` stop here, below
+/// those reserved for gotestfinder's own outcomes.
+const MAX_FAILURE_COUNT: usize = 100;
+
+/// How a run ended, which decides the exit status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The selected tests passed, or were only printed, copied or listed.
+    Passed,
+    /// Tests failed: the exit status of the first failing run and the number
+    /// of failed tests.
+    Failed { code: i32, failures: usize },
+    /// No tests were found or none were selected.
+    NothingSelected,
+}
 
-    if !run_pattern.is_empty() {
-        cmd.arg("-run").arg(run_pattern);
+impl Outcome {
+    fn exit_code(self, policy: ExitPolicy) -> ExitCode {
+        match (self, policy) {
+            (Outcome::Passed, _) | (Outcome::Failed { .. }, ExitPolicy::AlwaysZero) => {
+                ExitCode::SUCCESS
+            }
+            (Outcome::Failed { code, .. }, ExitPolicy::Propagate) => {
+                ExitCode::from(u8::try_from(code).unwrap_or(1))
+            }
+            (Outcome::Failed { failures, .. }, ExitPolicy::CountFailures) => {
+                ExitCode::from(failures.clamp(1, MAX_FAILURE_COUNT) as u8)
+            }
+            (Outcome::NothingSelected, _) => ExitCode::from(EXIT_NOTHING_SELECTED),
+        }
     }
+}
 
-    cmd.arg("./...");
+/// Finds the tests in the directory and keeps those the flags ask for,
+/// counting the ones left out.
+fn discover(args: &Args) -> Result<(Vec<TestInfo>, usize)> {
+    discover_streamed(args, None)
+}
 
-    println!(
-        "Running: go {}",
-        cmd.get_args()
-            .map(|arg| arg.to_string_lossy())
-            .collect::<Vec<_>>()
-            .join(" ")
+/// [`discover`], also handing `found` the tests the flags ask for of each
+/// file as soon as it is parsed, with their packages resolved, and how many
+/// of the file's tests they left out. What comes
+/// from the other files of their package, such as suite methods, Ginkgo
+/// specs and `TestMain`, is not known yet then.
+fn discover_streamed(
+    args: &Args,
+    mut found: Option<&mut dyn FnMut(Vec<TestInfo>, usize)>,
+) -> Result<(Vec<TestInfo>, usize)> {
+    let build_tags = &constraint::BuildTags::new(
+        args.tags.as_deref(),
+        args.goos.as_deref(),
+        args.goarch.as_deref(),
     );
+    let mut found = found.as_mut().map(|found| {
+        move |tests: Vec<TestInfo>| {
+            let parsed = tests.len();
+            let listed: Vec<_> = tests
+                .into_iter()
+                .filter(|test| is_listed(test, args, build_tags))
+                .collect();
+            let left_out = parsed - listed.len();
+            found(listed, left_out)
+        }
+    });
+    let discovery = DiscoveryOptions {
+        backend: if args.regex {
+            Backend::Regex
+        } else {
+            args.backend
+        },
+        tags: args.tags.clone(),
+        goos: args.goos.clone(),
+        goarch: args.goarch.clone(),
+        ginkgo: args.ginkgo,
+        include_generated: args.include_generated,
+        include_dirs: args.include_dirs.clone(),
+        exclude_dirs: args.exclude_dirs.clone(),
+        respect_ignore: !args.no_ignore,
+        follow_symlinks: args.follow_symlinks,
+        go_bin: args.go_bin.clone(),
+    };
+    let mut tests = find_tests(
+        args.directory(),
+        &discovery,
+        found
+            .as_mut()
+            .map(|found| found as &mut dyn FnMut(Vec<TestInfo>)),
+    )?;
+    let found = tests.len();
+    tests.retain(|test| is_listed(test, args, build_tags));
+    let filtered = found - tests.len();
+    Ok((tests, filtered))
+}
 
-    let status = cmd.status()?;
+/// Whether the flags keep `test` among the discovered tests.
+fn is_listed(test: &TestInfo, args: &Args, build_tags: &constraint::BuildTags) -> bool {
+    // With `--tags`, only the tests the given tags build are offered.
+    // Without, tests in files excluded by their build constraint cannot run
+    // on the target platform whatever tags are given, so they are not
+    // offered, while those only missing tags are, marked with their
+    // constraint, and warned about when selected.
+    (args.benchmarks || test.kind != TestKind::Benchmark)
+        && (!args.hide_skipped || test.skip != Some(Skip::Always))
+        && (!args.hide_short_skipped || !test.short_skip)
+        && (!args.only_parallel || test.parallel)
+        && (!args.no_parallel || !test.parallel)
+        && (!args.only_external || test.external)
+        && (!args.only_internal || !test.external)
+        && test.constraint.as_ref().is_none_or(|constraint| {
+            if args.tags.is_some() {
+                constraint.eval(build_tags)
+            } else {
+                constraint.can_hold(build_tags)
+            }
+        })
+}
 
-    if !status.success() {
-        std::process::exit(status.code().unwrap_or(1));
-    }
+/// Discovers the tests to run, running them from `--chdir` if given.
+fn discover_for_run(args: &Args) -> Result<(Vec<TestInfo>, usize)> {
+    discover_for_run_streamed(args, None)
+}
 
+/// [`discover_for_run`], handing `found` the tests of each file as
+/// [`discover_streamed`] does.
+fn discover_for_run_streamed(
+    args: &Args,
+    mut found: Option<&mut dyn FnMut(Vec<TestInfo>, usize)>,
+) -> Result<(Vec<TestInfo>, usize)> {
+    let dir = &match &args.chdir {
+        Some(dir) => Some(
+            dir.canonicalize()
+                .with_context(|| format!("Invalid --chdir {}", dir.display()))?,
+        ),
+        None => None,
+    };
+    let mut found = found.as_mut().map(|found| {
+        move |mut tests: Vec<TestInfo>, left_out| {
+            if let Some(dir) = dir {
+                run_from(&mut tests, dir);
+            }
+            found(tests, left_out)
+        }
+    });
+    let (mut tests, filtered) = discover_streamed(
+        args,
+        found
+            .as_mut()
+            .map(|found| found as &mut dyn FnMut(Vec<TestInfo>, usize)),
+    )?;
+    if let Some(dir) = dir {
+        run_from(&mut tests, dir);
+    }
+    Ok((tests, filtered))
+}
+
+/// The settings of `go test` runs, from the flags and configuration files.
+fn run_options(args: &Args) -> Result<RunOptions> {
+    let test_main_args = match &args.test_main_args {
+        Some(value) => shlex::split(value)
+            .ok_or_else(|| anyhow::anyhow!("Invalid quoting in --test-main-args"))?,
+        None => Vec::new(),
+    };
+    let project_dir = PathBuf::from(args.directory());
+    let shuffle = if args.replay_shuffle {
+        let seed = history::History::open(&project_dir)
+            .and_then(|history| history.shuffle_seed())
+            .ok_or_else(|| anyhow::anyhow!("No shuffle seed recorded for this project"))?;
+        Some(seed.to_string())
+    } else {
+        args.shuffle.clone()
+    };
+    let config = config::Config::load(&project_dir)?;
+    let mut env = config.env;
+    for (key, value) in &args.env {
+        env.retain(|(k, _)| k != key);
+        env.push((key.clone(), value.clone()));
+    }
+    let mut bindings: Vec<(String, String)> = DEFAULT_BINDINGS
+        .iter()
+        .map(|(key, action)| (key.to_string(), action.to_string()))
+        .collect();
+    for (key, action) in config.keys.iter().chain(&args.bindings) {
+        if action_description(action).is_none() {
+            anyhow::bail!("Unknown action `{}` bound to {}", action, key);
+        }
+        bindings.retain(|(k, _)| k != key);
+        bindings.push((key.clone(), action.clone()));
+    }
+    Ok(RunOptions {
+        tags: args.tags.clone(),
+        verbose: args.verbose,
+        race: args.race,
+        timeout: args.timeout.clone(),
+        // `default` is what go does without the flag.
+        vet: args.vet.clone().filter(|vet| vet != "default"),
+        count: args.count,
+        short: args.short,
+        failfast: args.failfast,
+        shuffle,
+        rerun_failed: args.rerun_failed,
+        last: args.last,
+        all: args.all,
+        session: args.session,
+        preselect: Vec::new(),
+        select_last: args.select_last,
+        recent: Vec::new(),
+        sort: args.sort,
+        anchor: args.anchor,
+        debug: args.debug,
+        edit: false,
+        binary_cache: args.binary_cache,
+        raw: args.raw,
+        live: args.live,
+        slowest: args.slowest,
+        retries: args.retries,
+        jobs: args.jobs,
+        isolate: args.isolate,
+        dry_run: args.dry_run,
+        copy: args.copy,
+        junit: args.junit.clone(),
+        gh_annotations: args.gh_annotations,
+        output: args.output,
+        ignore_quarantined: args.ignore_quarantined,
+        quarantine: quarantine::Quarantine::load(&project_dir)?,
+        parallel: args.parallel,
+        package_parallel: args.package_parallel,
+        benchtime: args.benchtime.clone(),
+        benchmem: args.benchmem,
+        fuzztime: args.fuzztime.clone(),
+        cover: args.cover || args.base_ref.is_some(),
+        cover_html: args.cover_html,
+        cover_out: args.cover_out.clone(),
+        base_ref: args.base_ref.clone(),
+        profiles: [
+            ("cpu", args.cpuprofile),
+            ("mem", args.memprofile),
+            ("block", args.blockprofile),
+        ]
+        .into_iter()
+        .filter_map(|(kind, enabled)| enabled.then_some(kind))
+        .collect(),
+        profile_dir: args.profile_dir.clone().unwrap_or_else(|| {
+            std::env::temp_dir().join(format!("gotestfinder-profiles-{}", std::process::id()))
+        }),
+        pprof: args.pprof,
+        goos: args.goos.clone(),
+        goarch: args.goarch.clone(),
+        test_main_args,
+        go_test_args: args.go_test_args.clone(),
+        env,
+        bindings,
+        ui: args.ui,
+        theme: args.theme.clone().or(config.theme),
+        query: args.query.clone(),
+        filter: args.filter.clone(),
+        group: args.group,
+        match_all: args.match_all,
+        exact: args.exact,
+        case: args.case,
+        filtered: 0,
+        height: args.height.clone(),
+        min_height: args.min_height,
+        layout: args.layout,
+        screen: if args.clear {
+            Screen::Clear
+        } else if args.no_clear {
+            Screen::Keep
+        } else {
+            Screen::Restore
+        },
+        inline: args.inline,
+        all_packages: args.all_packages,
+        project_dir,
+        go_bin: args.go_bin.clone(),
+        runner: match args.runner {
+            Runner::Auto if which::which("gotestsum").is_ok() => Runner::Gotestsum,
+            Runner::Auto => Runner::Go,
+            runner => runner,
+        },
+    })
+}
+
+/// Accepts `off`, `default` or a list of vet analyzers.
+fn parse_vet(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let valid = value.split(',').all(|check| {
+        !check.is_empty() && check.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    });
+    if !valid {
+        return Err("expected `off`, `default` or a comma-separated list of analyzers".to_string());
+    }
+    Ok(value.to_string())
+}
+
+fn parse_filter(value: &str) -> Result<TestFilter, String> {
+    match value
+        .strip_prefix('/')
+        .and_then(|rest| rest.strip_suffix('/'))
+    {
+        Some(pattern) => Regex::new(pattern)
+            .map(TestFilter::Regex)
+            .map_err(|e| e.to_string()),
+        None => Ok(TestFilter::Fuzzy(value.to_string())),
+    }
+}
+
+fn parse_height(value: &str) -> Result<String, String> {
+    let number = value.strip_suffix('%').unwrap_or(value);
+    match number.parse::<u32>() {
+        Ok(n) if n > 0 && (n <= 100 || !value.ends_with('%')) => Ok(value.to_string()),
+        _ => Err("expected a number of rows or a percentage, such as `20` or `50%`".to_string()),
+    }
+}
+
+fn parse_binding(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, action)) if !key.is_empty() && action_description(action).is_some() => {
+            Ok((key.to_string(), action.to_string()))
+        }
+        Some((_, action)) if !action.is_empty() => Err(format!("unknown action `{}`", action)),
+        _ => Err(format!("expected KEY=ACTION, got `{}`", value)),
+    }
+}
+
+fn parse_env_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got `{}`", value)),
+    }
+}
+
+/// Settings controlling which files discovery reads and how.
+struct DiscoveryOptions {
+    backend: Backend,
+    /// Build tags handed to `go test -list`.
+    tags: Option<String>,
+    /// Target platform handed to `go test -list`.
+    goos: Option<String>,
+    goarch: Option<String>,
+    /// Collect Ginkgo specs.
+    ginkgo: bool,
+    /// Also read files marked `// Code generated ... DO NOT EDIT.`.
+    include_generated: bool,
+    /// Directory names to descend into despite the default exclusions.
+    include_dirs: Vec<String>,
+    /// Additional directory names to skip.
+    exclude_dirs: Vec<String>,
+    /// Skip paths ignored by git.
+    respect_ignore: bool,
+    /// Descend into symlinked directories.
+    follow_symlinks: bool,
+    /// The `go` binary running `go test -list`.
+    go_bin: PathBuf,
+}
+
+impl DiscoveryOptions {
+    /// Reports whether the walk should skip a directory with this name.
+    /// Like `go`, it leaves out `vendor`, `testdata` and directories
+    /// starting with `.` or `_` by default, along with `node_modules`.
+    fn excludes_dir(&self, name: &str) -> bool {
+        if self.include_dirs.iter().any(|dir| dir == name) {
+            return false;
+        }
+        self.exclude_dirs.iter().any(|dir| dir == name)
+            || EXCLUDED_DIRS.contains(&name)
+            || name.starts_with(['.', '_'])
+    }
+}
+
+const EXCLUDED_DIRS: &[&str] = &["vendor", "testdata", "node_modules"];
+
+/// Finds the tests below `dir`, handing `found` those of each file as soon
+/// as it is parsed, with their packages resolved.
+fn find_tests(
+    dir: &str,
+    options: &DiscoveryOptions,
+    mut found: Option<&mut dyn FnMut(Vec<TestInfo>)>,
+) -> Result<Vec<TestInfo>> {
+    let mut tests = Vec::new();
+    let mut index = PackageIndex::default();
+    // Test files read, grouped by package directory in walk order.
+    let mut packages: Vec<(PathBuf, Vec<PathBuf>)> = Vec::new();
+
+    let mut ignore = options
+        .respect_ignore
+        .then(|| gitignore::Ignore::new(Path::new(dir)));
+
+    // With symlinks followed, a directory linked from several places is
+    // only scanned the first time it is reached.
+    let mut visited = HashSet::new();
+
+    let walker = WalkDir::new(dir)
+        .follow_links(options.follow_symlinks)
+        .into_iter()
+        .filter_entry(|entry| {
+            let is_dir = entry.file_type().is_dir();
+            if entry.depth() > 0 {
+                if is_dir && options.excludes_dir(&entry.file_name().to_string_lossy()) {
+                    return false;
+                }
+                let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+                if ignore
+                    .as_mut()
+                    .is_some_and(|ignore| ignore.is_ignored(relative, is_dir))
+                {
+                    return false;
+                }
+            }
+            !(options.follow_symlinks
+                && is_dir
+                && entry
+                    .path()
+                    .canonicalize()
+                    .is_ok_and(|real| !visited.insert(real)))
+        });
+
+    for entry in walker {
+        let entry = match entry {
+            // A symlink back to one of its own ancestors.
+            Err(err) if err.loop_ancestor().is_some() => continue,
+            entry => entry?,
+        };
+        let path = entry.path();
+
+        if path.extension().is_some_and(|ext| ext == "go")
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with("_test.go"))
+        {
+            let content = std::fs::read_to_string(path)?;
+            if !options.include_generated && is_generated(&content) {
+                continue;
+            }
+
+            let dir = path.parent().unwrap_or(Path::new(""));
+            match packages.iter_mut().find(|(package, _)| package == dir) {
+                Some((_, files)) => files.push(path.to_path_buf()),
+                None => packages.push((dir.to_path_buf(), vec![path.to_path_buf()])),
+            }
+
+            let parsed = if options.backend == Backend::Regex {
+                parse_test_file_regex(path, &content)?
+            } else {
+                parse_test_file(path, &content, &mut index, options.ginkgo)
+            };
+            if let Some(found) = found.as_mut() {
+                let mut parsed = parsed.clone();
+                resolve_modules(&mut parsed);
+                found(parsed);
+            }
+            tests.extend(parsed);
+        }
+    }
+
+    if options.backend == Backend::GoList {
+        merge_go_list(&mut tests, &packages, options);
+    }
+
+    resolve_package_index(&mut tests, &index);
+    resolve_modules(&mut tests);
+
+    // Keep the tests of a package together; the sort is stable, so tests
+    // stay in file order within their package.
+    tests.sort_by(|a, b| a.package.cmp(&b.package));
+
+    Ok(tests)
+}
+
+/// Replaces the parsed top-level tests of each package with the ones
+/// `go test -list` reports, keeping the parsed details (subtests, labels) of
+/// tests found both ways. Packages `go` fails to list keep their parsed
+/// tests.
+fn merge_go_list(
+    tests: &mut Vec<TestInfo>,
+    packages: &[(PathBuf, Vec<PathBuf>)],
+    options: &DiscoveryOptions,
+) {
+    for (dir, files) in packages {
+        let listed = match golist::list_tests(
+            &options.go_bin,
+            dir,
+            options.tags.as_deref(),
+            options.goos.as_deref(),
+            options.goarch.as_deref(),
+        ) {
+            Ok(listed) => listed,
+            Err(err) => {
+                errln!("warning: {:#}", err);
+                continue;
+            }
+        };
+
+        tests.retain(|test| package_dir(test) != dir || listed.contains(&test.name));
+
+        for name in listed {
+            if tests
+                .iter()
+                .any(|test| package_dir(test) == dir && test.name == name)
+            {
+                continue;
+            }
+
+            let (file, line) = locate_func(files, &name).unwrap_or((files[0].clone(), 0));
+            let kind = if is_test_name(&name, "Benchmark") {
+                TestKind::Benchmark
+            } else if is_test_name(&name, "Fuzz") {
+                TestKind::Fuzz
+            } else {
+                TestKind::Test
+            };
+
+            tests.push(TestInfo {
+                name,
+                kind,
+                package: String::new(),
+                file: file.to_string_lossy().to_string(),
+                line,
+                subtests: Vec::new(),
+                suite: None,
+                framework: Framework::Standard,
+                constraint: None,
+                skip: None,
+                short_skip: false,
+                has_test_main: false,
+                parallel: false,
+                external: false,
+                module_dir: None,
+                outside_workspace: false,
+            });
+        }
+    }
+}
+
+/// Finds the file and line declaring the top-level function `name`.
+fn locate_func(files: &[PathBuf], name: &str) -> Option<(PathBuf, usize)> {
+    let declaration = Regex::new(&format!(r"^func\s+{}\s*[\[(]", regex::escape(name))).ok()?;
+
+    files.iter().find_map(|file| {
+        let content = std::fs::read_to_string(file).ok()?;
+        let line = content
+            .lines()
+            .position(|line| declaration.is_match(line))?;
+        Some((file.clone(), line + 1))
+    })
+}
+
+/// Reports whether the file carries the standard generated-code marker,
+/// which must appear before the package clause.
+fn is_generated(content: &str) -> bool {
+    content
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with("package "))
+        .any(|line| line.starts_with("// Code generated ") && line.ends_with(" DO NOT EDIT."))
+}
+
+fn parse_test_file(
+    path: &Path,
+    content: &str,
+    index: &mut PackageIndex,
+    ginkgo: bool,
+) -> Vec<TestInfo> {
+    let file = parser::parse(content);
+    let constraint = constraint::file_constraint(path, content);
+    let mut tests = Vec::new();
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let ginkgo = ginkgo && ginkgo::imports_ginkgo(&file);
+
+    if ginkgo {
+        index
+            .ginkgo_specs
+            .entry(dir.clone())
+            .or_default()
+            .extend(ginkgo::find_specs(&file));
+    }
+
+    let gocheck = gocheck::imports_gocheck(&file);
+    if gocheck {
+        index
+            .gocheck_suites
+            .entry(dir.clone())
+            .or_default()
+            .extend(gocheck::registered_suites(&file));
+    }
+
+    for func in &file.funcs {
+        if func.name == "TestMain"
+            && func.receiver.is_none()
+            && takes_single_param(&file, func, "testing", "M")
+        {
+            index.test_main.insert(dir.clone());
+            continue;
+        }
+
+        // testify suite methods take no arguments, gocheck ones a *check.C.
+        if let Some(receiver) = &func.receiver
+            && is_test_name(&func.name, "Test")
+            && (func.params.is_empty()
+                || takes_single_param(&file, func, gocheck::IMPORT_PATH, "C"))
+        {
+            let ty = receiver.ty.trim_start_matches('*').to_string();
+            index
+                .suite_methods
+                .entry((dir.clone(), ty))
+                .or_default()
+                .push(func.name.clone());
+            continue;
+        }
+
+        let Some(kind) = test_kind(&file, func) else {
+            if func.receiver.is_none() && takes_param(&file, func, "testing", "T") {
+                let helper = subtests::find_helper(&file, func);
+                if !helper.is_empty() {
+                    index.helpers.insert(
+                        (dir.clone(), file.package.clone(), func.name.clone()),
+                        helper,
+                    );
+                }
+            }
+            continue;
+        };
+
+        if kind == TestKind::Test
+            && let Some(t) = func.params[0].name.as_deref()
+        {
+            let calls = subtests::find_helper_calls(&file, file.body_tokens(func), t);
+            if !calls.is_empty() {
+                index.helper_calls.insert(
+                    (dir.clone(), func.name.clone()),
+                    (file.package.clone(), calls),
+                );
+            }
+        }
+
+        let convey = kind == TestKind::Test && convey::imports_convey(&file);
+        let subtests = match kind {
+            TestKind::Fuzz => find_seed_corpus(func, file.body_tokens(func)),
+            _ if convey => convey::find_conveys(file.body_tokens(func))
+                .into_iter()
+                .map(Subtest::new)
+                .collect(),
+            _ => subtests::find_subtests(&file, file.body_tokens(func)),
+        };
+
+        let suite = match kind {
+            TestKind::Test => find_suite_run(&file, file.body_tokens(func)),
+            _ => None,
+        };
+        let framework = if ginkgo && ginkgo::runs_specs(file.body_tokens(func)) {
+            Framework::Ginkgo
+        } else if gocheck && gocheck::runs_suites(&file, file.body_tokens(func)) {
+            Framework::Gocheck
+        } else if convey && !subtests.is_empty() {
+            Framework::Convey
+        } else {
+            Framework::Standard
+        };
+
+        tests.push(TestInfo {
+            name: func.name.clone(),
+            kind,
+            package: String::new(),
+            file: path.to_string_lossy().to_string(),
+            line: func.line,
+            subtests,
+            suite,
+            framework,
+            constraint: constraint.clone(),
+            skip: find_skip(func, file.body_tokens(func)),
+            short_skip: skips_in_short_mode(&file, func, file.body_tokens(func)),
+            has_test_main: false,
+            module_dir: None,
+            outside_workspace: false,
+            parallel: is_parallel(func, file.body_tokens(func)),
+            external: file.package.ends_with("_test"),
+        });
+    }
+
+    tests
+}
+
+/// Finds the suite type passed to testify's `suite.Run(t, ...)`, accepting
+/// `new(S)`, `&S{...}`, `S{...}` or a local variable initialised with one
+/// of those.
+fn find_suite_run(file: &parser::GoFile, body: &[parser::Token]) -> Option<String> {
+    let start = body.windows(4).position(|w| {
+        w[0].kind == parser::TokenKind::Ident
+            && w[1].is_punct(".")
+            && w[2].is_ident("Run")
+            && w[3].is_punct("(")
+            && file.is_qualified(&format!("{}.Run", w[0].text), TESTIFY_SUITE, "Run")
+    })?;
+
+    let open = start + 3;
+    let close = parser::matching(body, open)?;
+    let args = &body[open + 1..close];
+    let comma = args.iter().position(|token| token.is_punct(","))?;
+    let arg = &args[comma + 1..];
+
+    match arg {
+        [var] if var.kind == parser::TokenKind::Ident => {
+            let init = body.windows(2).position(|w| {
+                w[0].is_ident(&var.text) && (w[1].is_punct(":=") || w[1].is_punct("="))
+            })?;
+            suite_type(&body[init + 2..])
+        }
+        _ => suite_type(arg),
+    }
+}
+
+fn suite_type(expr: &[parser::Token]) -> Option<String> {
+    match expr {
+        [new, open, ty, ..] if new.is_ident("new") && open.is_punct("(") => Some(ty.text.clone()),
+        [amp, ty, brace, ..] if amp.is_punct("&") && brace.is_punct("{") => Some(ty.text.clone()),
+        [ty, brace, ..] if ty.kind == parser::TokenKind::Ident && brace.is_punct("{") => {
+            Some(ty.text.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Attaches package-wide declarations to their entry points: subtests run
+/// by helpers are listed under the tests calling them, testify suite
+/// methods become subtests named the way testify runs them, while Ginkgo
+/// specs and gocheck `Suite.Method` names are listed under the bootstrap test
+/// of their package.
+fn resolve_package_index(tests: &mut [TestInfo], index: &PackageIndex) {
+    for test in tests {
+        let dir = package_dir(test).to_path_buf();
+        test.has_test_main = index.test_main.contains(&dir);
+
+        if let Some((package, calls)) = index.helper_calls.get(&(dir.clone(), test.name.clone())) {
+            let helpers = subtests::expand_helper_calls(calls, |name| {
+                index
+                    .helpers
+                    .get(&(dir.clone(), package.clone(), name.to_string()))
+            });
+            for subtest in helpers {
+                if !test.subtests.iter().any(|s| s.name == subtest.name) {
+                    test.subtests.push(subtest);
+                }
+            }
+        }
+
+        if let Some(suite) = &test.suite
+            && let Some(methods) = index.suite_methods.get(&(dir.clone(), suite.clone()))
+        {
+            test.subtests.extend(methods.iter().map(Subtest::new));
+        }
+
+        if test.framework == Framework::Ginkgo
+            && let Some(specs) = index.ginkgo_specs.get(&dir)
+        {
+            test.subtests.extend(specs.iter().map(Subtest::new));
+        }
+
+        if test.framework == Framework::Gocheck
+            && let Some(suites) = index.gocheck_suites.get(&dir)
+        {
+            for suite in suites {
+                let Some(methods) = index.suite_methods.get(&(dir.clone(), suite.clone())) else {
+                    continue;
+                };
+                test.subtests.extend(
+                    methods
+                        .iter()
+                        .map(|method| Subtest::new(format!("{}.{}", suite, method))),
+                );
+            }
+        }
+    }
+}
+
+/// Where a package lives: its import path and how to reach its module.
+#[derive(Clone)]
+struct PackageLocation {
+    import_path: String,
+    module_dir: Option<PathBuf>,
+    outside_workspace: bool,
+}
+
+/// Sets each test's package import path from the `go.mod` of its module and
+/// records how `go test` reaches that module, which in a `go.work` workspace
+/// need not be the module of the current directory. Packages outside any
+/// module are identified by their directory.
+fn resolve_modules(tests: &mut [TestInfo]) {
+    let current_module = std::env::current_dir()
+        .ok()
+        .and_then(|dir| gomod::find_module(&dir));
+    let mut locations: HashMap<PathBuf, PackageLocation> = HashMap::new();
+
+    for test in tests {
+        let dir = package_dir(test).to_path_buf();
+        let location = locations
+            .entry(dir.clone())
+            .or_insert_with(|| match gomod::find_module(&dir) {
+                Some(module) => PackageLocation {
+                    import_path: module
+                        .import_path(&dir)
+                        .unwrap_or_else(|| package_pattern(&dir)),
+                    outside_workspace: gomod::find_workspace(&module.root)
+                        .is_some_and(|workspace| !workspace.contains(&module)),
+                    module_dir: current_module
+                        .as_ref()
+                        .is_none_or(|current| current.root != module.root)
+                        .then_some(module.root),
+                },
+                None => PackageLocation {
+                    import_path: package_pattern(&dir),
+                    module_dir: None,
+                    outside_workspace: false,
+                },
+            })
+            .clone();
+
+        test.package = location.import_path;
+        test.module_dir = location.module_dir;
+        test.outside_workspace = location.outside_workspace;
+    }
+}
+
+/// Makes every test run from `dir`, as asked with `--chdir`. Packages are
+/// then named by import path, and outside a module by absolute directory.
+fn run_from(tests: &mut [TestInfo], dir: &Path) {
+    let mut outside_module: HashMap<PathBuf, Option<String>> = HashMap::new();
+
+    for test in tests {
+        let package_dir = package_dir(test).to_path_buf();
+        let absolute = outside_module.entry(package_dir).or_insert_with_key(|dir| {
+            match gomod::find_module(dir) {
+                Some(_) => None,
+                None => dir
+                    .canonicalize()
+                    .ok()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+            }
+        });
+        if let Some(absolute) = absolute {
+            test.package = absolute.clone();
+        }
+        test.module_dir = Some(dir.to_path_buf());
+    }
+}
+
+fn package_dir(test: &TestInfo) -> &Path {
+    Path::new(&test.file).parent().unwrap_or(Path::new(""))
+}
+
+fn test_kind(file: &parser::GoFile, func: &parser::FuncDecl) -> Option<TestKind> {
+    if func.receiver.is_some() {
+        return None;
+    }
+
+    if is_test_name(&func.name, "Test") && takes_single_param(file, func, "testing", "T") {
+        Some(TestKind::Test)
+    } else if is_test_name(&func.name, "Benchmark")
+        && takes_single_param(file, func, "testing", "B")
+    {
+        Some(TestKind::Benchmark)
+    } else if is_test_name(&func.name, "Fuzz") && takes_single_param(file, func, "testing", "F") {
+        Some(TestKind::Fuzz)
+    } else {
+        None
+    }
+}
+
+/// Reports whether one of the parameters of `func` has type `*path.ident`.
+fn takes_param(file: &parser::GoFile, func: &parser::FuncDecl, path: &str, ident: &str) -> bool {
+    func.params.iter().any(|param| {
+        param
+            .ty
+            .strip_prefix('*')
+            .is_some_and(|ty| file.is_qualified(ty, path, ident))
+    })
+}
+
+/// Reports whether `func` takes exactly one parameter of type `*path.ident`.
+fn takes_single_param(
+    file: &parser::GoFile,
+    func: &parser::FuncDecl,
+    path: &str,
+    ident: &str,
+) -> bool {
+    match func.params.as_slice() {
+        [param] => param
+            .ty
+            .strip_prefix('*')
+            .is_some_and(|ty| file.is_qualified(ty, path, ident)),
+        _ => false,
+    }
+}
+
+/// Mirrors `go test`: the name must be `prefix` alone or `prefix` followed by
+/// something that does not start with a lowercase letter.
+fn is_test_name(name: &str, prefix: &str) -> bool {
+    match name.strip_prefix(prefix) {
+        Some(rest) => !rest.starts_with(|c: char| c.is_lowercase()),
+        None => false,
+    }
+}
+
+/// Looks for skip calls on the test's parameter. A call outside any block
+/// counts as unconditional.
+fn find_skip(func: &parser::FuncDecl, body: &[parser::Token]) -> Option<Skip> {
+    let depths = param_call_depths(func, body, &["Skip", "Skipf", "SkipNow"]);
+    if depths.contains(&0) {
+        Some(Skip::Always)
+    } else if !depths.is_empty() {
+        Some(Skip::Conditional)
+    } else {
+        None
+    }
+}
+
+/// Detects the `if testing.Short() { t.Skip(...) }` guard (or one that
+/// returns) among the top-level statements of a test.
+fn skips_in_short_mode(
+    file: &parser::GoFile,
+    func: &parser::FuncDecl,
+    body: &[parser::Token],
+) -> bool {
+    let t = func.params.first().and_then(|param| param.name.as_deref());
+    let mut depth = 0usize;
+
+    for (i, token) in body.iter().enumerate() {
+        if token.is_punct("{") {
+            depth += 1;
+        } else if token.is_punct("}") {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0
+            && token.is_ident("if")
+            && let [pkg, dot, short, open, close, brace, ..] = &body[i + 1..]
+            && dot.is_punct(".")
+            && short.is_ident("Short")
+            && open.is_punct("(")
+            && close.is_punct(")")
+            && brace.is_punct("{")
+            && file.is_qualified(&format!("{}.Short", pkg.text), "testing", "Short")
+        {
+            let open = i + 6;
+            let Some(close) = parser::matching(body, open) else {
+                break;
+            };
+            let block = &body[open + 1..close];
+            let exits = block.windows(3).any(|w| {
+                t.is_some_and(|t| w[0].is_ident(t))
+                    && w[1].is_punct(".")
+                    && ["Skip", "Skipf", "SkipNow"]
+                        .iter()
+                        .any(|method| w[2].is_ident(method))
+            });
+            if exits || block.iter().any(|token| token.is_ident("return")) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// A test is parallel when it calls `t.Parallel()` as a top-level statement;
+/// calls inside closures belong to subtests.
+fn is_parallel(func: &parser::FuncDecl, body: &[parser::Token]) -> bool {
+    param_call_depths(func, body, &["Parallel"]).contains(&0)
+}
+
+/// Returns the block nesting depth of every call to one of `methods` on the
+/// function's first parameter (the `*testing.T`, `B` or `F`).
+fn param_call_depths(
+    func: &parser::FuncDecl,
+    body: &[parser::Token],
+    methods: &[&str],
+) -> Vec<usize> {
+    let Some(t) = func.params.first().and_then(|param| param.name.as_deref()) else {
+        return Vec::new();
+    };
+    let mut depth = 0usize;
+    let mut depths = Vec::new();
+
+    for (i, token) in body.iter().enumerate() {
+        if token.is_punct("{") {
+            depth += 1;
+        } else if token.is_punct("}") {
+            depth = depth.saturating_sub(1);
+        } else if token.is_ident(t)
+            && body.get(i + 1).is_some_and(|t| t.is_punct("."))
+            && body
+                .get(i + 2)
+                .is_some_and(|t| methods.iter().any(|method| t.is_ident(method)))
+        {
+            depths.push(depth);
+        }
+    }
+
+    depths
+}
+
+/// `go test` runs each `f.Add` entry of a fuzz target as a `seed#N` subtest.
+fn find_seed_corpus(func: &parser::FuncDecl, body: &[parser::Token]) -> Vec<Subtest> {
+    let Some(f) = func.params.first().and_then(|param| param.name.as_deref()) else {
+        return Vec::new();
+    };
+
+    let seeds = body
+        .windows(4)
+        .filter(|w| {
+            w[0].is_ident(f) && w[1].is_punct(".") && w[2].is_ident("Add") && w[3].is_punct("(")
+        })
+        .count();
+
+    (0..seeds)
+        .map(|i| Subtest::new(format!("seed#{}", i)))
+        .collect()
+}
+
+fn parse_test_file_regex(path: &Path, content: &str) -> Result<Vec<TestInfo>> {
+    let constraint = constraint::file_constraint(path, content);
+    let external = Regex::new(r"(?m)^package\s+\w+_test\b")?.is_match(content);
+    let mut tests = Vec::new();
+
+    // Matched against the whole file so signatures wrapped across lines
+    // (including a trailing comma after the last parameter) are found.
+    let test_func_regex = Regex::new(
+        r"func\s+((?:Test|Benchmark|Fuzz)\w+)\s*\(\s*\w*\s*\*testing\.[TBF]\w*\s*,?\s*\)",
+    )?;
+    let subtest_regex = Regex::new(r#"\.Run\s*\(\s*"([^"]+)""#)?;
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    for caps in test_func_regex.captures_iter(content) {
+        let test_name = caps.get(1).unwrap().as_str().to_string();
+        let line_num = content[..caps.get(0).unwrap().start()]
+            .matches('\n')
+            .count();
+        let mut subtests = Vec::new();
+
+        let mut brace_count = 0;
+        let mut in_function = false;
+
+        for &func_line in lines.iter().skip(line_num) {
+            if func_line.contains('{') {
+                brace_count += func_line.matches('{').count();
+                in_function = true;
+            }
+            if func_line.contains('}') {
+                brace_count = brace_count.saturating_sub(func_line.matches('}').count());
+            }
+
+            if in_function && brace_count == 0 {
+                break;
+            }
+
+            if in_function {
+                for caps in subtest_regex.captures_iter(func_line) {
+                    if let Some(subtest_name) = caps.get(1) {
+                        subtests.push(Subtest::new(subtest_name.as_str()));
+                    }
+                }
+            }
+        }
+
+        let kind = if test_name.starts_with("Benchmark") {
+            TestKind::Benchmark
+        } else if test_name.starts_with("Fuzz") {
+            TestKind::Fuzz
+        } else {
+            TestKind::Test
+        };
+
+        tests.push(TestInfo {
+            name: test_name,
+            kind,
+            package: String::new(),
+            file: path.to_string_lossy().to_string(),
+            line: line_num + 1,
+            subtests,
+            suite: None,
+            framework: Framework::Standard,
+            constraint: constraint.clone(),
+            skip: None,
+            short_skip: false,
+            has_test_main: false,
+            module_dir: None,
+            outside_workspace: false,
+            parallel: false,
+            external,
+        });
+    }
+
+    Ok(tests)
+}
+
+/// Prints the tests grouped under a `# <import path>` line per package.
+fn print_tests(tests: &[TestInfo], show_subtests: bool, show_parent: bool, anchor: Anchor) {
+    let mut package = None;
+
+    for test in tests {
+        if package != Some(&test.package) {
+            println!("# {}", test.package);
+            package = Some(&test.package);
+        }
+
+        let labels = test_labels(test);
+        let test_line = if labels.is_empty() {
+            anchor.apply(&test.name)
+        } else {
+            format!("{} # {}", anchor.apply(&test.name), labels.join(", "))
+        };
+
+        if test.subtests.is_empty() {
+            println!("{}", test_line);
+        } else {
+            if show_parent {
+                println!("{}", test_line);
+            }
+            if show_subtests {
+                for subtest in &test.subtests {
+                    let name = anchor.apply(&test.name);
+                    if test.framework == Framework::Convey {
+                        println!("{} # {}", name, subtest.name);
+                    } else if let Some(args) = test.framework.filter_args(&[&subtest.name]) {
+                        println!("{} -args {}", name, args.join(" "));
+                    } else if subtest.dynamic {
+                        println!("{}/{} # dynamic", name, subtest.run_pattern());
+                    } else {
+                        let pattern = format!("{}/{}", test.name, subtest.run_pattern());
+                        println!("{}", anchor.apply_levels(&pattern));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Prints the tests as a JSON document, `{"tests": [...]}`, with every
+/// subtest whatever `--subtests` and `--parent` say.
+fn print_tests_json(tests: &[TestInfo], anchor: Anchor) {
+    let tests = tests.iter().map(|test| test_json(test, anchor)).collect();
+    println!(
+        "{}",
+        json::Value::Object(vec![("tests", json::Value::Array(tests))])
+    );
+}
+
+/// Prints each test matching `--filter` as a JSON object on its own line
+/// as soon as its file is parsed, in the order files are found rather than
+/// that of `--sort`. Subtests the test gets from other files of its
+/// package, like suite methods, and the `has TestMain` annotation are left
+/// out, as they are only known once the whole tree was read.
+fn stream_tests_json(args: &Args) -> Result<Outcome> {
+    let mut listed = 0;
+    let mut print = |mut tests: Vec<TestInfo>, _| {
+        if let Some(filter) = &args.filter {
+            filter_tests(&mut tests, filter, args.exact, args.case);
+        }
+        let mut stdout = io::stdout().lock();
+        for test in &tests {
+            let _ = writeln!(stdout, "{}", test_json(test, args.anchor));
+        }
+        let _ = stdout.flush();
+        listed += tests.len();
+    };
+    discover_streamed(args, Some(&mut print))?;
+    if listed == 0 && args.filter.is_some() {
+        eprintln!("No tests match the filter");
+        return Ok(Outcome::NothingSelected);
+    }
+    Ok(Outcome::Passed)
+}
+
+/// A test as the JSON listings show it. `run` is the `-run` pattern
+/// selecting the test or subtest, and `args` the test binary arguments
+/// frameworks filtering their own subtests take.
+fn test_json(test: &TestInfo, anchor: Anchor) -> json::Value {
+    let kind = match test.kind {
+        TestKind::Test => "test",
+        TestKind::Benchmark => "benchmark",
+        TestKind::Fuzz => "fuzz",
+    };
+    let framework = match test.framework {
+        Framework::Standard => "standard",
+        Framework::Ginkgo => "ginkgo",
+        Framework::Gocheck => "gocheck",
+        Framework::Convey => "convey",
+    };
+    let subtests = test
+        .subtests
+        .iter()
+        .map(|subtest| {
+            let run = if test.framework != Framework::Standard {
+                anchor.apply(&test.name)
+            } else if subtest.dynamic {
+                format!("{}/{}", anchor.apply(&test.name), subtest.run_pattern())
+            } else {
+                anchor.apply_levels(&format!("{}/{}", test.name, subtest.run_pattern()))
+            };
+            let args = test
+                .framework
+                .filter_args(&[&subtest.name])
+                .unwrap_or_default();
+            json::Value::Object(vec![
+                ("name", subtest.name.as_str().into()),
+                ("dynamic", subtest.dynamic.into()),
+                ("run", run.into()),
+                ("args", args.into()),
+            ])
+        })
+        .collect();
+    let annotations: Vec<String> = test_labels(test)
+        .into_iter()
+        .map(|label| label.into_owned())
+        .collect();
+    json::Value::Object(vec![
+        ("name", test.name.as_str().into()),
+        ("package", test.package.as_str().into()),
+        ("file", display_path(&test.file).into()),
+        ("line", test.line.into()),
+        ("kind", kind.into()),
+        ("framework", framework.into()),
+        ("suite", test.suite.as_deref().into()),
+        (
+            "tags",
+            test.constraint
+                .as_ref()
+                .map(|constraint| constraint.to_string())
+                .into(),
+        ),
+        ("annotations", annotations.into()),
+        ("run", anchor.apply(&test.name).into()),
+        ("subtests", json::Value::Array(subtests)),
+    ])
+}
+
+/// Whether `--all` runs the selector entry: whole tests, which run their
+/// subtests, other than benchmarks, tests needing tags `--tags` does not
+/// give and, unless their failures are ignored, quarantined tests. Fuzz
+/// targets run their seed corpus.
+fn runs_with_all(test: &TestInfo, selection: &Selection, options: &RunOptions) -> bool {
+    let built = || {
+        let build_tags = constraint::BuildTags::new(
+            options.tags.as_deref(),
+            options.goos.as_deref(),
+            options.goarch.as_deref(),
+        );
+        test.constraint
+            .as_ref()
+            .is_none_or(|constraint| constraint.eval(&build_tags))
+    };
+    selection.subtest.is_none()
+        && test.kind != TestKind::Benchmark
+        && (options.ignore_quarantined || !options.quarantine.contains(&test.package, &test.name))
+        && built()
+}
+
+/// Keeps the tests and subtests matching `--filter`, along with the tests
+/// of matching subtests.
+fn filter_tests(tests: &mut Vec<TestInfo>, filter: &TestFilter, exact: bool, case: Case) {
+    tests.retain_mut(|test| {
+        let matched = filter.matches(&test.name, &test.package, exact, case);
+        test.subtests.retain(|subtest| {
+            filter.matches(
+                &format!("{}/{}", test.name, subtest.name),
+                &test.package,
+                exact,
+                case,
+            )
+        });
+        matched || !test.subtests.is_empty()
+    });
+}
+
+/// Annotations shown next to a test in the listing and the selector.
+fn test_labels(test: &TestInfo) -> Vec<Cow<'static, str>> {
+    let mut labels = Vec::new();
+
+    match test.skip {
+        Some(Skip::Always) => labels.push("skipped".into()),
+        Some(Skip::Conditional) => labels.push("may skip".into()),
+        None => {}
+    }
+    if test.short_skip {
+        labels.push("skipped with -short".into());
+    }
+    if test.parallel {
+        labels.push("parallel".into());
+    }
+    if test.external {
+        labels.push("external".into());
+    }
+    if test.has_test_main {
+        labels.push("has TestMain".into());
+    }
+    // Constraints on the platform alone go without saying.
+    if let Some(constraint) = &test.constraint
+        && !constraint.custom_tags().is_empty()
+    {
+        labels.push(format!("tags: {}", constraint).into());
+    }
+
+    labels
+}
+
+/// A single `go test` run over one package pattern.
+#[derive(Clone)]
+struct Invocation {
+    /// Module directory to run from instead of the current directory.
+    dir: Option<PathBuf>,
+    /// Run with `GOWORK=off` because the module is not in the workspace.
+    workspace_off: bool,
+    package: String,
+    /// Directory of the package, unless the run covers `./...`.
+    package_dir: Option<PathBuf>,
+    /// Import path of the package, unless the run covers `./...`.
+    import_path: Option<String>,
+    run_patterns: Vec<String>,
+    bench_patterns: Vec<String>,
+    /// Arguments for the test binary, passed after `-args`.
+    test_args: Vec<String>,
+    /// The fuzz target to fuzz, for a run of a single fuzz target.
+    fuzz: Option<FuzzTarget>,
+}
+
+/// A fuzz target selected for fuzzing, with where `go test -fuzz` keeps its
+/// inputs.
+#[derive(Clone)]
+struct FuzzTarget {
+    name: String,
+    /// Import path of the target's package.
+    package: String,
+    /// `testdata/fuzz/FuzzXxx` in the package, where failing inputs go.
+    corpus_dir: PathBuf,
+}
+
+/// Settings for the `go test` runs of a selection.
+#[derive(Clone)]
+struct RunOptions {
+    tags: Option<String>,
+    verbose: bool,
+    race: bool,
+    timeout: Option<String>,
+    /// `-vet` value; `None` leaves go's default checks.
+    vet: Option<String>,
+    /// `-count` value; 0 omits the flag.
+    count: u32,
+    short: bool,
+    failfast: bool,
+    /// `-shuffle` value: `on` or a seed.
+    shuffle: Option<String>,
+    /// Select the failures of the last run instead of showing the selector.
+    rerun_failed: bool,
+    /// Select what the last recorded run selected instead.
+    last: bool,
+    /// Select every test instead.
+    all: bool,
+    /// Return to the selector after each run.
+    session: bool,
+    /// Entries selected when the selector opens: the last selection of a
+    /// session.
+    preselect: Vec<Selection>,
+    /// Select the tests of the last recorded run when the selector opens.
+    select_last: bool,
+    /// Tests the selector's cursor starts on, the first listed of them:
+    /// those of the last recorded run.
+    recent: Vec<Selection>,
+    /// Order of the selector entries.
+    sort: SortOrder,
+    /// How selected test names are anchored in -run patterns.
+    anchor: Anchor,
+    /// Run the selection under Delve instead.
+    debug: bool,
+    /// Open the selection's first test in the editor instead.
+    edit: bool,
+    /// Rerun cached test binaries instead of `go test` where possible.
+    binary_cache: bool,
+    /// Show the live results pane during runs.
+    live: bool,
+    /// Pass the output of `go test` through instead of rendering events.
+    raw: bool,
+    /// Slowest tests listed in the summary.
+    slowest: usize,
+    /// Times failed tests are rerun.
+    retries: u32,
+    /// Invocations run at once.
+    jobs: usize,
+    /// Run each selected test on its own.
+    isolate: bool,
+    /// Print the commands instead of running them.
+    dry_run: bool,
+    /// Copy the commands or patterns instead of running them.
+    copy: Option<CopyTarget>,
+    /// Where to write a JUnit XML report of the results.
+    junit: Option<PathBuf>,
+    /// Annotate failing tests for GitHub Actions.
+    gh_annotations: bool,
+    /// How the results are printed.
+    output: OutputFormat,
+    /// Failures of quarantined tests alone do not fail the run.
+    ignore_quarantined: bool,
+    /// Known-flaky tests, marked in the selector.
+    quarantine: quarantine::Quarantine,
+    /// `-parallel`, the tests of a binary running at once.
+    parallel: Option<u32>,
+    /// `-p`, the packages built and tested at once.
+    package_parallel: Option<u32>,
+    benchtime: Option<String>,
+    benchmem: bool,
+    fuzztime: Option<String>,
+    cover: bool,
+    cover_html: bool,
+    /// Where to keep the merged coverage profile.
+    cover_out: Option<PathBuf>,
+    /// Revision to compare coverage with.
+    base_ref: Option<String>,
+    /// Kinds of profiles to write, such as `cpu` for `-cpuprofile`.
+    profiles: Vec<&'static str>,
+    profile_dir: PathBuf,
+    /// Open the profiles in pprof's web interface.
+    pprof: bool,
+    /// Target platform, passed to `go` as `GOOS`/`GOARCH`.
+    goos: Option<String>,
+    goarch: Option<String>,
+    /// Extra test binary arguments for packages that define `TestMain`.
+    test_main_args: Vec<String>,
+    /// Arguments given after `--`, passed to `go test` as they are.
+    go_test_args: Vec<String>,
+    /// Run plain tests in one run over `./...` rather than one per package.
+    all_packages: bool,
+    /// Environment variables from `--env` and configuration files.
+    env: Vec<(String, String)>,
+    /// Selector keys and the actions they trigger.
+    bindings: Vec<(String, String)>,
+    /// Selector the tests are picked with.
+    ui: Ui,
+    /// `--theme`, resolved when the selector is shown.
+    theme: Option<String>,
+    /// Initial query of the test selector.
+    query: Option<String>,
+    /// Run the tests matching this instead of showing the selector.
+    filter: Option<TestFilter>,
+    /// Start entries with their package and keep packages together.
+    group: bool,
+    /// Match queries against every field of the entries, not just the test.
+    match_all: bool,
+    /// Match query terms as substrings rather than fuzzily.
+    exact: bool,
+    case: Case,
+    /// Discovered tests the flags left out, counted in the selector header.
+    filtered: usize,
+    /// Selector height, in rows or as a percentage.
+    height: String,
+    min_height: usize,
+    layout: Layout,
+    screen: Screen,
+    /// Draw the selector beneath the prompt.
+    inline: bool,
+    /// The scanned directory, which locates the project's history.
+    project_dir: PathBuf,
+    /// The `go` binary to run.
+    go_bin: PathBuf,
+    /// `Go` or `Gotestsum`, with `Auto` resolved.
+    runner: Runner,
+}
+
+fn run_with_skim(tests: Vec<TestInfo>, options: &RunOptions) -> Result<Outcome> {
+    if options.session {
+        return run_session(&tests, options.clone(), Outcome::NothingSelected);
+    }
+
+    let Some(selected) = select_tests(&tests, options)? else {
+        return Ok(Outcome::NothingSelected);
+    };
+
+    run_selection(&tests, &selected.tests, &selected.run_options())
+}
+
+/// Tests discovery found while the selector is open.
+struct Found {
+    items: Vec<TestItem>,
+    /// The header's account of what discovery found so far.
+    summary: String,
+}
+
+/// Whether the selector opens while discovery goes on, listing the tests as
+/// they are found. They are listed in the order found, so tests sorted or
+/// grouped otherwise wait for the full scan, as do those of `go test -list`,
+/// fzf's, and selections carried over from the last run.
+fn streams_selection(args: &Args, options: &RunOptions) -> bool {
+    !(options.rerun_failed || options.last || options.all || options.select_last)
+        && options.filter.is_none()
+        && options.ui != Ui::Fzf
+        && options.sort == SortOrder::Source
+        && !options.group
+        && args.backend != Backend::GoList
+}
+
+/// [`run_with_skim`] with the selector open while the tests are still being
+/// discovered. The built-in selector's header counts them as they come in;
+/// skim's header cannot change once open, so there its own counter tells
+/// how many are listed. Once it is left, the scan is waited for and the
+/// picked tests are found among all those discovered. Nothing found by the
+/// time it would open, or a flag toggled in it, leaves the rest to
+/// [`run_with_skim`].
+fn run_streamed(args: &Args, options: RunOptions) -> Result<Outcome> {
+    let history = history::History::open(&options.project_dir);
+    let durations = history
+        .as_ref()
+        .map(|history| history.durations())
+        .unwrap_or_default();
+    let last_results = history
+        .as_ref()
+        .map(|history| history.last_results())
+        .unwrap_or_default();
+
+    let (choice, streamed, tests, filtered) = std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        let (options, durations, last_results) = (&options, &durations, &last_results);
+        let discovery = scope.spawn(move || {
+            let mut streamed: Vec<TestInfo> = Vec::new();
+            let mut left_out = 0;
+            let mut found = |tests: Vec<TestInfo>, filtered| {
+                let mut items = collect_test_items(&tests, options, durations, last_results);
+                for item in &mut items {
+                    item.selection.test += streamed.len();
+                }
+                streamed.extend(tests);
+                left_out += filtered;
+                if !items.is_empty() {
+                    let summary = discovery_summary(&streamed, left_out);
+                    let _ = sender.send(Found { items, summary });
+                }
+            };
+            let (tests, filtered) = discover_for_run_streamed(args, Some(&mut found))?;
+            // The tests left out are counted again once whole packages are
+            // known.
+            if !streamed.is_empty() {
+                let summary = discovery_summary(&streamed, filtered);
+                let _ = sender.send(Found {
+                    items: Vec::new(),
+                    summary,
+                });
+            }
+            anyhow::Ok((streamed, tests, filtered))
+        });
+
+        let choice = match receiver.recv() {
+            Ok(found) => {
+                let header = selector_header(None, options);
+                Some(match options.ui {
+                    Ui::Skim => skim_select(found.items, &header, options, Some(receiver))?,
+                    _ => {
+                        let header = format!("{}\n{}", found.summary, header);
+                        picker_select(found.items, &header, options, Some(receiver))?
+                    }
+                })
+            }
+            Err(_) => None,
+        };
+        let (streamed, tests, filtered) = discovery.join().unwrap()?;
+        anyhow::Ok((choice, streamed, tests, filtered))
+    })?;
+    let mut options = RunOptions {
+        filtered,
+        ..options
+    };
+    let Some(choice) = choice else {
+        return run_with_skim(tests, &options);
+    };
+
+    options.query = Some(choice.query.clone());
+    if let Some(flag) = toggled_flag(&mut options, choice.action.as_deref()) {
+        *flag = !*flag;
+        return run_with_skim(tests, &options);
+    }
+    let selections = choice
+        .selections
+        .iter()
+        .filter_map(|selection| {
+            let test = &streamed[selection.test];
+            let index = tests.iter().position(|other| {
+                other.package == test.package && other.name == test.name && other.file == test.file
+            })?;
+            Some(Selection {
+                test: index,
+                subtest: selection.subtest.clone(),
+            })
+        })
+        .collect();
+    let Some(selected) = confirm_selection(&tests, selections, choice.action, options.clone())
+    else {
+        return Ok(Outcome::NothingSelected);
+    };
+
+    let outcome = run_selection(&tests, &selected.tests, &selected.run_options())?;
+    if !options.session {
+        return Ok(outcome);
+    }
+    let session = RunOptions {
+        preselect: selected.tests,
+        ..selected.confirmed
+    };
+    run_session(&tests, session, outcome)
+}
+
+/// Selects and runs tests until the selector is left, reopening it after
+/// each run with the query, selection and toggled flags it was confirmed
+/// with. Actions such as race apply to their own run only. The outcome is
+/// the last run's, or `outcome` when the selector is left at once.
+fn run_session(
+    tests: &[TestInfo],
+    mut session: RunOptions,
+    mut outcome: Outcome,
+) -> Result<Outcome> {
+    while let Some(selected) = select_tests(tests, &session)? {
+        outcome = run_selection(tests, &selected.tests, &selected.run_options())?;
+        session = RunOptions {
+            preselect: selected.tests,
+            ..selected.confirmed
+        };
+    }
+    Ok(outcome)
+}
+
+/// Tests picked to run, with what they were picked with.
+struct Selected {
+    tests: Vec<Selection>,
+    /// The options as the selector left them, with its query, matching mode
+    /// and toggled flags.
+    confirmed: RunOptions,
+    /// The accept action the selection was confirmed with.
+    action: Option<String>,
+}
+
+impl Selected {
+    /// The options to run the tests with: the confirmed ones, changed by
+    /// the accept action.
+    fn run_options(&self) -> RunOptions {
+        let mut options = self.confirmed.clone();
+        match self.action.as_deref() {
+            Some(RACE_ACTION) => options.race = true,
+            Some(VERBOSE_ACTION) => options.verbose = true,
+            Some(COPY_PATTERN_ACTION) => options.copy = Some(CopyTarget::Pattern),
+            Some(DEBUG_ACTION) => options.debug = true,
+            Some(EDIT_ACTION) => options.edit = true,
+            Some(COPY_ACTION) => options.copy = Some(options.copy.unwrap_or(CopyTarget::Command)),
+            _ => {}
+        }
+        options
+    }
+}
+
+/// Selects the tests to run, or returns `None` when there is nothing to
+/// run.
+fn select_tests(tests: &[TestInfo], options: &RunOptions) -> Result<Option<Selected>> {
+    let history = history::History::open(&options.project_dir);
+    let durations = history
+        .as_ref()
+        .map(|history| history.durations())
+        .unwrap_or_default();
+    let last_results = history
+        .as_ref()
+        .map(|history| history.last_results())
+        .unwrap_or_default();
+    let items = collect_test_items(tests, options, &durations, &last_results);
+
+    if items.is_empty() {
+        outln!("No tests found");
+        return Ok(None);
+    }
+
+    // The selector leaves its query, matching mode and toggled flags to the
+    // options the selection is confirmed with.
+    let mut confirmed = options.clone();
+    // It starts where the last run left off, unless a session carries its
+    // own selection over.
+    confirmed.recent = history
+        .as_ref()
+        .and_then(|history| history.runs().pop())
+        .map(|run| {
+            run.tests
+                .iter()
+                .filter_map(|selected| lookup_selection(tests, selected))
+                .collect()
+        })
+        .unwrap_or_default();
+    if options.select_last && confirmed.preselect.is_empty() {
+        confirmed.preselect = confirmed.recent.clone();
+    }
+    let (selected_tests, action) = if options.rerun_failed {
+        (failed_selections(tests, options), None)
+    } else if options.last {
+        let run =
+            history::History::open(&options.project_dir).and_then(|history| history.runs().pop());
+        let Some(run) = run else {
+            outln!("No runs recorded for this project");
+            return Ok(None);
+        };
+        (find_selection(tests, &run.tests), None)
+    } else if options.all || options.filter.is_some() {
+        let selections: Vec<_> = items
+            .iter()
+            .filter(|item| {
+                let test = &tests[item.selection.test];
+                options.filter.as_ref().is_none_or(|filter| {
+                    filter.matches(&item.name, &test.package, options.exact, options.case)
+                }) && (!options.all || runs_with_all(test, &item.selection, options))
+            })
+            .map(|item| item.selection.clone())
+            .collect();
+        if selections.is_empty() {
+            if options.filter.is_some() {
+                outln!("No tests match the filter");
+            } else {
+                outln!("No tests to run");
+            }
+            return Ok(None);
+        }
+        (selections, None)
+    } else {
+        let choice = show_selector(tests, items, &durations, &last_results, &mut confirmed)?;
+        (choice.selections, choice.action)
+    };
+    Ok(confirm_selection(tests, selected_tests, action, confirmed))
+}
+
+/// The tests picked with `action` to run, the last failures in their place
+/// for the rerun-failed action, or `None` when that leaves nothing to run.
+fn confirm_selection(
+    tests: &[TestInfo],
+    mut selected_tests: Vec<Selection>,
+    action: Option<String>,
+    confirmed: RunOptions,
+) -> Option<Selected> {
+    let rerun_failed = confirmed.rerun_failed || action.as_deref() == Some(RERUN_FAILED_ACTION);
+    if action.as_deref() == Some(RERUN_FAILED_ACTION) {
+        selected_tests = failed_selections(tests, &confirmed);
+    }
+
+    if selected_tests.is_empty() {
+        if rerun_failed {
+            outln!("No failed tests recorded for this project");
+        } else if !confirmed.session {
+            // Leaving a session is no reason to say so.
+            outln!("No tests selected");
+        }
+        return None;
+    }
+
+    Some(Selected {
+        tests: selected_tests,
+        confirmed,
+        action,
+    })
+}
+
+/// Runs the selected tests and reports on them.
+fn run_selection(
+    tests: &[TestInfo],
+    selected_tests: &[Selection],
+    options: &RunOptions,
+) -> Result<Outcome> {
+    if options.edit {
+        edit_selection(tests, selected_tests)?;
+        return Ok(Outcome::Passed);
+    }
+
+    if options.dry_run {
+        for command in selection_commands(tests, selected_tests, options)? {
+            println!("{}", command);
+        }
+        return Ok(Outcome::Passed);
+    }
+
+    if let Some(copy) = options.copy {
+        copy_selection(tests, selected_tests, options, copy)?;
+        return Ok(Outcome::Passed);
+    }
+
+    if options.debug {
+        let status = debug_with_delve(tests, selected_tests, options)?;
+        if status.success() {
+            return Ok(Outcome::Passed);
+        }
+        return Ok(Outcome::Failed {
+            code: status.code().unwrap_or(1),
+            failures: 1,
+        });
+    }
+
+    if options.test_main_args.is_empty() {
+        warn_test_main(tests, selected_tests);
+    }
+    if options.short {
+        warn_short_skips(tests, selected_tests);
+    }
+    warn_build_tags(tests, selected_tests, options);
+
+    let mut coverage = if options.cover {
+        Some(coverage::Coverage::new()?)
+    } else {
+        None
+    };
+
+    let invocations = plan_invocations(tests, selected_tests, options);
+    if !options.profiles.is_empty() {
+        std::fs::create_dir_all(&options.profile_dir)
+            .with_context(|| format!("Failed to create {}", options.profile_dir.display()))?;
+    }
+    // go test rejects -coverprofile together with -fuzz.
+    let profiles: Vec<_> = invocations
+        .iter()
+        .map(|invocation| {
+            coverage
+                .as_mut()
+                .filter(|_| invocation.fuzz.is_none())
+                .map(coverage::Coverage::next_profile)
+        })
+        .collect();
+
+    // The panes are drawn from go test's events, which fuzzing and
+    // gotestsum do not give.
+    let live = if options.live
+        && options.runner == Runner::Go
+        && invocations
+            .iter()
+            .all(|invocation| invocation.fuzz.is_none())
+    {
+        Some(live::Live::start()?)
+    } else {
+        None
+    };
+
+    // Fuzzing takes over the terminal, so it never runs alongside others.
+    let outcomes = if options.jobs > 1
+        && invocations.len() > 1
+        && invocations
+            .iter()
+            .all(|invocation| invocation.fuzz.is_none())
+    {
+        run_in_parallel(&invocations, &profiles, options)?
+    } else {
+        let mut outcomes = Vec::new();
+        for (invocation, profile) in invocations.iter().zip(&profiles) {
+            let outcome = match &invocation.fuzz {
+                Some(target) => {
+                    let _interrupts = interrupt::DeferInterrupts::new();
+                    let outcome = execute_go_test(invocation, options, profile.as_deref(), None)?;
+                    report_fuzz_corpus(target, options);
+                    outcome
+                }
+                None => execute_go_test(invocation, options, profile.as_deref(), None)?,
+            };
+            let failed = !outcome.status.success();
+            outcomes.push(outcome);
+            // -failfast only stops the current run; skip the remaining ones
+            // too.
+            if (options.failfast && failed) || live::interrupted() {
+                break;
+            }
+        }
+        outcomes
+    };
+
+    let mut exit_code = None;
+    let mut shuffle_seeds = Vec::new();
+    let mut results = Vec::new();
+    let mut commands = Vec::new();
+    let mut isolated = Vec::new();
+    for (invocation, outcome) in invocations.iter().zip(outcomes) {
+        let status = outcome.status;
+        if options.isolate {
+            isolated.push((invocation, status, outcome.elapsed));
+        }
+        commands.push(outcome.command);
+        shuffle_seeds.extend(outcome.shuffle_seeds);
+        results.extend(outcome.results);
+        if !status.success() && exit_code.is_none() {
+            exit_code = Some(status.code().unwrap_or(1));
+        }
+    }
+
+    let mut retried = Vec::new();
+    if options.retries > 0 && exit_code.is_some() {
+        if results.is_empty() {
+            errln!("warning: --retries needs the rendered results of go test");
+        } else {
+            exit_code = retry_failures(
+                tests,
+                options,
+                exit_code,
+                &mut results,
+                &mut commands,
+                &mut retried,
+            )?;
+        }
+    }
+
+    if let Some(live) = live {
+        live.finish();
+    }
+
+    if options.ignore_quarantined
+        && exit_code.is_some()
+        && only_quarantined_failed(&results, options)
+    {
+        outln!("\nOnly quarantined tests failed; not failing the run");
+        exit_code = None;
+    }
+
+    record_run(tests, selected_tests, commands, options);
+
+    if !results.is_empty() {
+        print_summary(&results, tests, options.slowest);
+        print_retried(&retried, &results);
+        record_failures(&results, options);
+        record_durations(&results, options);
+        record_last_results(&results, options);
+        if options.gh_annotations {
+            print_annotations(&results, tests);
+        }
+    }
+    if !isolated.is_empty() {
+        print_isolated(&isolated);
+    }
+
+    if options.output == OutputFormat::Tap {
+        if results.is_empty() {
+            errln!("warning: --output tap needs the rendered results of go test");
+        } else {
+            print!("{}", tap::render(&results));
+        }
+    }
+
+    if let Some(path) = &options.junit {
+        if results.is_empty() {
+            errln!("warning: --junit needs the rendered results of go test");
+        } else {
+            junit::write(path, &results)?;
+            outln!("JUnit report written to {}", path.display());
+        }
+    }
+
+    if !options.profiles.is_empty() {
+        report_profiles(&invocations, options)?;
+    }
+
+    if let Some(coverage) = coverage {
+        report_coverage(&coverage, options)?;
+        if let Some(base_ref) = &options.base_ref {
+            compare_coverage(&invocations, &coverage, base_ref, options)?;
+        }
+    }
+
+    if options.shuffle.is_some() {
+        record_shuffle_seed(&shuffle_seeds, options);
+    }
+
+    Ok(match exit_code {
+        Some(code) => Outcome::Failed {
+            code,
+            failures: innermost_failures(&results).len(),
+        },
+        None => Outcome::Passed,
+    })
+}
+
+/// Selects tests once, runs them, and reruns them after every change to the
+/// watched directory. Tests are discovered again before each rerun, so the
+/// selection follows them as they move around.
+fn run_watch(args: &Args) -> Result<Outcome> {
+    let (tests, filtered) = discover_for_run(args)?;
+    let options = RunOptions {
+        filtered,
+        ..run_options(args)?
+    };
+    let Some(selected) = select_tests(&tests, &options)? else {
+        return Ok(Outcome::NothingSelected);
+    };
+    let options = selected.run_options();
+    let selected_tests = selected.tests;
+    if options.debug {
+        return run_selection(&tests, &selected_tests, &options);
+    }
+
+    let names = selection_names(&tests, &selected_tests);
+
+    let mut watcher = watch::Watcher::new(Path::new(args.directory()));
+    run_selection(&tests, &selected_tests, &options)?;
+
+    loop {
+        outln!(
+            "\nWatching {} for changes (Ctrl-C to stop)",
+            args.directory()
+        );
+        watcher.wait();
+
+        let tests = match discover_for_run(args) {
+            Ok((tests, _)) => tests,
+            Err(err) => {
+                errln!("error: {:#}", err);
+                continue;
+            }
+        };
+        let selected_tests = find_selection(&tests, &names);
+        if selected_tests.is_empty() {
+            outln!("None of the selected tests exist anymore");
+            continue;
+        }
+
+        outln!();
+        run_selection(&tests, &selected_tests, &options)?;
+    }
+}
+
+/// Runs the invocations `--jobs` at a time, starting each line they print
+/// with their package. The outcomes are in the order of the invocations;
+/// with `-failfast`, those not started by the first failure are skipped.
+fn run_in_parallel(
+    invocations: &[Invocation],
+    profiles: &[Option<PathBuf>],
+    options: &RunOptions,
+) -> Result<Vec<RunOutcome>> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let outcomes: Mutex<Vec<Option<Result<RunOutcome>>>> =
+        Mutex::new(invocations.iter().map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.jobs.min(invocations.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= invocations.len()
+                        || (options.failfast && failed.load(Ordering::SeqCst))
+                    {
+                        break;
+                    }
+                    let invocation = &invocations[index];
+                    let prefix = format!("[{}] ", invocation.package);
+                    let outcome = execute_go_test(
+                        invocation,
+                        options,
+                        profiles[index].as_deref(),
+                        Some(&prefix),
+                    );
+                    if outcome
+                        .as_ref()
+                        .is_ok_and(|outcome| !outcome.status.success())
+                    {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    outcomes.lock().unwrap()[index] = Some(outcome);
+                }
+            });
+        }
+    });
+
+    outcomes
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Reruns the failed tests up to `--retries` times, merging each round's
+/// results into `results` and adding the tests retried to `retried`. Returns
+/// the exit code of the last round, `exit_code` if nothing could be retried,
+/// or `None` once everything passed.
+fn retry_failures(
+    tests: &[TestInfo],
+    options: &RunOptions,
+    mut exit_code: Option<i32>,
+    results: &mut Vec<testjson::TestResult>,
+    commands: &mut Vec<String>,
+    retried: &mut Vec<(String, String)>,
+) -> Result<Option<i32>> {
+    for attempt in 1..=options.retries {
+        let failures: Vec<_> = innermost_failures(results)
+            .into_iter()
+            .map(|result| {
+                (
+                    result.package.clone(),
+                    result.test.clone().unwrap_or_default(),
+                )
+            })
+            .collect();
+        // Fuzzing a failed target again would not retry the failure.
+        let selected: Vec<_> = failure_selections(tests, &failures)
+            .into_iter()
+            .filter(|selection| {
+                tests[selection.test].kind != TestKind::Fuzz || selection.subtest.is_some()
+            })
+            .collect();
+        if selected.is_empty() {
+            break;
+        }
+        for failure in failures {
+            if !retried.contains(&failure) {
+                retried.push(failure);
+            }
+        }
+
+        outln!(
+            "\nRetry {} of {}: rerunning {} failed test(s)",
+            attempt,
+            options.retries,
+            selected.len()
+        );
+        exit_code = None;
+        let mut round = Vec::new();
+        for invocation in plan_invocations(tests, &selected, options) {
+            let outcome = execute_go_test(&invocation, options, None, None)?;
+            commands.push(outcome.command);
+            round.extend(outcome.results);
+            if !outcome.status.success() && exit_code.is_none() {
+                exit_code = Some(outcome.status.code().unwrap_or(1));
+            }
+        }
+
+        // The latest result of each test and package replaces earlier ones.
+        results.retain(|result| {
+            !round
+                .iter()
+                .any(|new| new.package == result.package && new.test == result.test)
+        });
+        results.extend(round);
+        if exit_code.is_none() {
+            break;
+        }
+    }
+
+    Ok(exit_code)
+}
+
+/// Whether every failure is one of a quarantined test. Packages failing
+/// outside their tests, such as those that did not build, never are.
+fn only_quarantined_failed(results: &[testjson::TestResult], options: &RunOptions) -> bool {
+    let failures = innermost_failures(results);
+    !failures.is_empty()
+        && failures.iter().all(|result| {
+            result
+                .test
+                .as_ref()
+                .is_some_and(|test| options.quarantine.contains(&result.package, test))
+        })
+}
+
+/// Lists the status and duration of each run of `--isolate`.
+fn print_isolated(runs: &[(&Invocation, ExitStatus, Duration)]) {
+    let colors = testjson::Colors::detect();
+    outln!("\nIsolated runs:");
+    for (invocation, status, elapsed) in runs {
+        let pattern = invocation
+            .run_patterns
+            .iter()
+            .chain(&invocation.bench_patterns)
+            .next()
+            .map_or(invocation.package.as_str(), String::as_str);
+        let status = match status.code() {
+            Some(0) => colors.green("PASS"),
+            Some(1) | None => colors.red("FAIL"),
+            Some(code) => colors.red(&format!("FAIL (exit status {})", code)),
+        };
+        outln!(
+            "  {}  {}  [{}]  {:.2}s",
+            status,
+            pattern,
+            invocation.package,
+            elapsed.as_secs_f64()
+        );
+    }
+}
+
+/// Prints the final status of each retried test: flaky when it passed on a
+/// retry, failed otherwise.
+fn print_retried(retried: &[(String, String)], results: &[testjson::TestResult]) {
+    if retried.is_empty() {
+        return;
+    }
+
+    let colors = testjson::Colors::detect();
+    outln!("Retried:");
+    for (package, test) in retried {
+        let passed = results.iter().any(|result| {
+            result.package == *package
+                && result.test.as_deref().unwrap_or_default() == test
+                && result.status == testjson::Status::Pass
+        });
+        let status = if passed {
+            colors.yellow("FLAKY")
+        } else {
+            colors.red("FAIL ")
+        };
+        let name = if test.is_empty() { package } else { test };
+        outln!("  {}  {}  [{}]", status, name, package);
+    }
+}
+
+/// Shows the recorded runs of the project, newest first, and replays the
+/// selection of the one picked.
+fn run_history(args: &Args) -> Result<Outcome> {
+    let (tests, _) = discover_for_run(args)?;
+    let options = run_options(args)?;
+
+    let mut runs = history::History::open(&options.project_dir)
+        .map(|history| history.runs())
+        .unwrap_or_default();
+    if runs.is_empty() {
+        outln!("No runs recorded for this project");
+        return Ok(Outcome::NothingSelected);
+    }
+    runs.reverse();
+
+    let run = match options.ui {
+        Ui::Skim => skim_select_run(runs, &options)?,
+        _ => picker_select_run(runs, &options)?,
+    };
+    let Some(run) = run else {
+        outln!("No run selected");
+        return Ok(Outcome::NothingSelected);
+    };
+    let selected_tests = find_selection(&tests, &run.tests);
+    if selected_tests.is_empty() {
+        outln!("None of the tests of this run exist anymore");
+        return Ok(Outcome::NothingSelected);
+    }
+
+    run_selection(&tests, &selected_tests, &options)
+}
+
+/// Names the selected entries so that they can be found again once the
+/// tests were discovered anew.
+fn selection_names(tests: &[TestInfo], selected: &[Selection]) -> Vec<history::SelectedTest> {
+    selected
+        .iter()
+        .map(|selection| {
+            let test = &tests[selection.test];
+            history::SelectedTest {
+                package: test.package.clone(),
+                name: test.name.clone(),
+                subtest: selection.subtest.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Finds named entries among the discovered tests, warning about those that
+/// are gone.
+fn find_selection(tests: &[TestInfo], names: &[history::SelectedTest]) -> Vec<Selection> {
+    names
+        .iter()
+        .filter_map(|selected| {
+            let selection = lookup_selection(tests, selected);
+            if selection.is_none() {
+                errln!(
+                    "warning: {} is no longer in {}",
+                    selected.name,
+                    selected.package
+                );
+            }
+            selection
+        })
+        .collect()
+}
+
+/// The discovered test a recorded one names, if it is still there.
+fn lookup_selection(tests: &[TestInfo], selected: &history::SelectedTest) -> Option<Selection> {
+    let index = tests
+        .iter()
+        .position(|test| test.package == selected.package && test.name == selected.name)?;
+    Some(Selection {
+        test: index,
+        subtest: selected.subtest.clone(),
+    })
+}
+
+/// Adds the run to the project's history for --last and `history`.
+fn record_run(
+    tests: &[TestInfo],
+    selected: &[Selection],
+    commands: Vec<String>,
+    options: &RunOptions,
+) {
+    let run = history::Run {
+        time: unix_time(),
+        tests: selection_names(tests, selected),
+        commands,
+    };
+    if let Some(history) = history::History::open(&options.project_dir)
+        && let Err(err) = history.add_run(run)
+    {
+        errln!("warning: {:#}", err);
+    }
+}
+
+/// Prints the counts of passed, failed and skipped tests, the slowest tests
+/// and where the failing ones are declared.
+fn print_summary(results: &[testjson::TestResult], tests: &[TestInfo], slowest: usize) {
+    use testjson::Status;
+
+    let colors = testjson::Colors::detect();
+    let top_level: Vec<_> = results
+        .iter()
+        .filter(|result| result.test.as_ref().is_some_and(|test| !test.contains('/')))
+        .collect();
+    let count = |status| top_level.iter().filter(|r| r.status == status).count();
+
+    outln!(
+        "\nSummary: {} passed, {} failed, {} skipped",
+        colors.green(&count(Status::Pass).to_string()),
+        colors.red(&count(Status::Fail).to_string()),
+        colors.yellow(&count(Status::Skip).to_string()),
+    );
+
+    let mut by_duration = top_level.clone();
+    by_duration.sort_by(|a, b| b.elapsed.total_cmp(&a.elapsed));
+    by_duration.retain(|result| result.status != Status::Skip);
+    if slowest > 0 && !by_duration.is_empty() {
+        outln!("Slowest:");
+        for result in by_duration.iter().take(slowest) {
+            outln!(
+                "  {:>7.2}s  {}  [{}]",
+                result.elapsed,
+                result.test.as_deref().unwrap_or_default(),
+                result.package
+            );
+        }
+    }
+
+    let mut header = false;
+    for result in innermost_failures(results) {
+        if !header {
+            outln!("Failed:");
+            header = true;
+        }
+        let Some(test) = &result.test else {
+            outln!("  {}  (package failed)", colors.red(&result.package));
+            continue;
+        };
+        let location = declaration(tests, result)
+            .map(|info| format!("  {}:{}", info.file, info.line))
+            .unwrap_or_default();
+        outln!("  {}{}", colors.red(test), location);
+    }
+}
+
+/// The test a result is of, or of the subtest of.
+fn declaration<'a>(tests: &'a [TestInfo], result: &testjson::TestResult) -> Option<&'a TestInfo> {
+    let name = result
+        .test
+        .as_deref()?
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    tests
+        .iter()
+        .find(|info| info.package == result.package && info.name == name)
+}
+
+/// Prints an `::error` workflow command per failing test, which GitHub
+/// Actions shows at the line declaring the test.
+fn print_annotations(results: &[testjson::TestResult], tests: &[TestInfo]) {
+    for result in innermost_failures(results) {
+        let name = result.test.as_deref().unwrap_or(&result.package);
+        let mut message = format!("{} failed", name);
+        // Test output is indented below the test's own line.
+        for line in result.output.lines() {
+            message.push('\n');
+            message.push_str(line.trim_start());
+        }
+        let location = declaration(tests, result).map(|info| (info.file.as_str(), info.line));
+        outln!("{}", github::error(location, &message));
+    }
+}
+
+/// The failed tests, leaving out those that only failed because one of their
+/// subtests did, and the packages that failed without a failing test: those
+/// that did not build or crashed outside the tests.
+fn innermost_failures(results: &[testjson::TestResult]) -> Vec<&testjson::TestResult> {
+    let failed: Vec<_> = results
+        .iter()
+        .filter(|result| result.status == testjson::Status::Fail)
+        .collect();
+
+    failed
+        .iter()
+        .filter(|result| {
+            let Some(test) = &result.test else {
+                return !failed
+                    .iter()
+                    .any(|other| other.package == result.package && other.test.is_some());
+            };
+            !failed.iter().any(|other| {
+                other.package == result.package
+                    && other
+                        .test
+                        .as_ref()
+                        .is_some_and(|other| other.starts_with(&format!("{}/", test)))
+            })
+        })
+        .copied()
+        .collect()
+}
+
+/// Keeps the failures of this run for --rerun-failed, replacing those of the
+/// previous one.
+fn record_failures(results: &[testjson::TestResult], options: &RunOptions) {
+    let failures: Vec<_> = innermost_failures(results)
+        .into_iter()
+        .map(|result| {
+            (
+                result.package.clone(),
+                result.test.clone().unwrap_or_default(),
+            )
+        })
+        .collect();
+    if let Some(history) = history::History::open(&options.project_dir)
+        && let Err(err) = history.set_failed_tests(&failures)
+    {
+        errln!("warning: {:#}", err);
+    }
+}
+
+/// Keeps each test's result, with the end of its output, for the preview
+/// of the selector. A test's output includes that of its failed subtests.
+fn record_last_results(results: &[testjson::TestResult], options: &RunOptions) {
+    let time = unix_time();
+    let updates: Vec<_> = results
+        .iter()
+        .filter_map(|result| {
+            let test = result.test.clone()?;
+            let subtests = format!("{}/", test);
+            let output: String = results
+                .iter()
+                .filter(|other| {
+                    other.package == result.package
+                        && other.test.as_deref().is_some_and(|name| {
+                            name == test
+                                || (name.starts_with(&subtests)
+                                    && other.status == testjson::Status::Fail)
+                        })
+                })
+                .map(|other| other.output.as_str())
+                .collect();
+            let status = match result.status {
+                testjson::Status::Pass => "pass",
+                testjson::Status::Fail => "fail",
+                testjson::Status::Skip => "skip",
+            };
+            let last = history::LastResult {
+                status: status.to_string(),
+                seconds: result.elapsed,
+                time,
+                output: output.lines().map(str::to_string).collect(),
+            };
+            Some(((result.package.clone(), test), last))
+        })
+        .collect();
+    if let Some(history) = history::History::open(&options.project_dir)
+        && let Err(err) = history.update_last_results(updates)
+    {
+        errln!("warning: {:#}", err);
+    }
+}
+
+/// Keeps how long each test took for the selector's duration column.
+/// Skipped tests are left out, as they did not really run.
+fn record_durations(results: &[testjson::TestResult], options: &RunOptions) {
+    let durations: Vec<_> = results
+        .iter()
+        .filter(|result| result.status != testjson::Status::Skip)
+        .filter_map(|result| {
+            let test = result.test.clone()?;
+            Some((result.package.clone(), test, result.elapsed))
+        })
+        .collect();
+    if let Some(history) = history::History::open(&options.project_dir)
+        && let Err(err) = history.update_durations(&durations)
+    {
+        errln!("warning: {:#}", err);
+    }
+}
+
+/// Selects the recorded failures of the last run. A failed subtest selects
+/// just that subtest, and a package that failed as a whole all of its tests.
+fn failed_selections(tests: &[TestInfo], options: &RunOptions) -> Vec<Selection> {
+    let failures = history::History::open(&options.project_dir)
+        .map(|history| history.failed_tests())
+        .unwrap_or_default();
+    failure_selections(tests, &failures)
+}
+
+/// Selects failures given as package and test name pairs, where an empty
+/// name stands for the whole package.
+fn failure_selections(tests: &[TestInfo], failures: &[(String, String)]) -> Vec<Selection> {
+    let mut selections = Vec::new();
+    for (package, test) in failures {
+        if test.is_empty() {
+            selections.extend(
+                tests
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, info)| info.package == *package)
+                    .map(|(index, _)| Selection {
+                        test: index,
+                        subtest: None,
+                    }),
+            );
+            continue;
+        }
+
+        let mut levels = test.split('/');
+        let name = levels.next().unwrap_or_default();
+        let Some(index) = tests
+            .iter()
+            .position(|info| info.package == *package && info.name == name)
+        else {
+            errln!("warning: {} is no longer in {}", name, package);
+            continue;
+        };
+        // go reports subtests by their exact names, so each level matches
+        // only itself.
+        let subtest = levels
+            .map(|level| format!("^{}$", regex::escape(level)))
+            .collect::<Vec<_>>();
+        selections.push(Selection {
+            test: index,
+            subtest: (!subtest.is_empty()).then(|| subtest.join("/")),
+        });
+    }
+
+    selections
+}
+
+/// Keeps the seed worth replaying: that of the first package that failed, or
+/// the last one printed when everything passed.
+fn record_shuffle_seed(seeds: &[ShuffleSeed], options: &RunOptions) {
+    let Some(seed) = seeds
+        .iter()
+        .find(|seed| seed.failed)
+        .or_else(|| seeds.last())
+    else {
+        return;
+    };
+
+    outln!(
+        "\nShuffle seed: {} ({}); rerun in the same order with --replay-shuffle",
+        seed.seed,
+        seed.package
+    );
+    if let Some(history) = history::History::open(&options.project_dir)
+        && let Err(err) = history.set_shuffle_seed(seed.seed)
+    {
+        errln!("warning: {:#}", err);
+    }
+}
+
+/// Merges the profiles of all runs, prints the per-package summary and opens
+/// the HTML report when asked to.
+fn report_coverage(coverage: &coverage::Coverage, options: &RunOptions) -> Result<()> {
+    let Some(profile) = coverage.merge(options.cover_out.as_deref())? else {
+        outln!("No coverage profile was written");
+        return Ok(());
+    };
+
+    let packages = coverage::summarize(&profile)?;
+    let width = packages
+        .iter()
+        .map(|package| package.package.len())
+        .max()
+        .unwrap_or(0);
+
+    outln!("\nCoverage:");
+    for package in &packages {
+        outln!(
+            "  {:<width$}  {:5.1}% ({}/{} statements)",
+            package.package,
+            package.percent(),
+            package.covered,
+            package.statements,
+        );
+    }
+
+    if options.cover_html {
+        coverage::open_html(&options.go_bin, &profile)?;
+    }
+
+    Ok(())
+}
+
+/// Where the profile of `kind` of an invocation's package goes, named after
+/// the package's directory; `test` is the test binary.
+fn profile_path(invocation: &Invocation, options: &RunOptions, kind: &str) -> PathBuf {
+    let name = invocation
+        .package
+        .trim_start_matches("./")
+        .trim_end_matches("/...")
+        .replace(['/', '.'], "_");
+    let name = if name.is_empty() { "root" } else { &name };
+    let file = match kind {
+        "test" => format!("{}.test", name),
+        _ => format!("{}.{}.pprof", name, kind),
+    };
+    options.profile_dir.join(file)
+}
+
+/// Lists the profiles the runs wrote and, with `--pprof`, opens those of the
+/// first kind in pprof's web interface, merged across packages.
+fn report_profiles(invocations: &[Invocation], options: &RunOptions) -> Result<()> {
+    let mut written = Vec::new();
+    outln!("\nProfiles:");
+    for kind in &options.profiles {
+        for invocation in invocations {
+            let path = profile_path(invocation, options, kind);
+            if path.exists() {
+                outln!("  {}", path.display());
+                written.push((*kind, path));
+            }
+        }
+    }
+    if written.is_empty() {
+        outln!("  none were written");
+        return Ok(());
+    }
+
+    if options.pprof {
+        let kind = written[0].0;
+        let paths: Vec<_> = written
+            .iter()
+            .filter(|(other, _)| *other == kind)
+            .map(|(_, path)| path)
+            .collect();
+        outln!(
+            "Opening the {} profile in pprof; press Ctrl-C to stop",
+            kind
+        );
+        let _interrupts = interrupt::DeferInterrupts::new();
+        let status = Command::new(&options.go_bin)
+            .args(["tool", "pprof", "-http=localhost:0"])
+            .args(&paths)
+            .status()
+            .context("Failed to run go tool pprof")?;
+        if !status.success() && status.code().is_some() {
+            errln!("warning: go tool pprof exited with {}", status);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the invocations with coverage again on `base_ref` and lists the
+/// functions whose coverage differs. Tests that do not exist there yet run
+/// nothing, so the difference shows what they cover.
+fn compare_coverage(
+    invocations: &[Invocation],
+    head: &coverage::Coverage,
+    base_ref: &str,
+    options: &RunOptions,
+) -> Result<()> {
+    let Some(head_profile) = head.merge(None)? else {
+        return Ok(());
+    };
+
+    let worktree = worktree::Worktree::add(base_ref)?;
+    let mut base = coverage::Coverage::new()?;
+    let prefix = format!("[{}] ", base_ref);
+    outln!("\nRunning the selected tests on {}", base_ref);
+    // Failures on the base revision do not fail the run.
+    for invocation in invocations
+        .iter()
+        .filter(|invocation| invocation.fuzz.is_none())
+    {
+        let mut invocation = invocation.clone();
+        invocation.dir = Some(worktree.map(invocation.dir.as_deref())?);
+        execute_go_test(
+            &invocation,
+            options,
+            Some(&base.next_profile()),
+            Some(&prefix),
+        )?;
+    }
+    let Some(base_profile) = base.merge(None)? else {
+        outln!("No coverage profile was written on {}", base_ref);
+        return Ok(());
+    };
+
+    let head_functions =
+        coverage::functions(&options.go_bin, &std::env::current_dir()?, &head_profile)?;
+    let base_functions = coverage::functions(&options.go_bin, &worktree.map(None)?, &base_profile)?;
+    let changes = coverage::diff(&base_functions, &head_functions);
+    if changes.is_empty() {
+        outln!("\nNo coverage changes against {}", base_ref);
+        return Ok(());
+    }
+
+    let colors = testjson::Colors::detect();
+    let percent = |percent: Option<f64>| percent.map_or("-".to_string(), |p| format!("{:.1}%", p));
+    let width = changes
+        .iter()
+        .map(|change| change.file.len() + change.name.len() + 1)
+        .max()
+        .unwrap_or(0);
+    outln!("\nCoverage changes against {}:", base_ref);
+    for change in &changes {
+        let name = if change.file.is_empty() {
+            change.name.clone()
+        } else {
+            format!("{}:{}", change.file, change.name)
+        };
+        let arrow = format!("{:>6} -> {:>6}", percent(change.base), percent(change.head));
+        let arrow = if change.head.unwrap_or_default() > change.base.unwrap_or_default() {
+            colors.green(&arrow)
+        } else {
+            colors.red(&arrow)
+        };
+        outln!("  {:<width$}  {}", name, arrow);
+    }
+
+    Ok(())
+}
+
+/// Runs the selection under Delve instead of `go test`. Delve debugs a
+/// single test binary, so the selection has to come from one package.
+fn debug_with_delve(
+    tests: &[TestInfo],
+    selected: &[Selection],
+    options: &RunOptions,
+) -> Result<ExitStatus> {
+    let mut cmd = delve_command(tests, selected, options)?;
+    let command = cmd
+        .get_args()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    outln!("Running: dlv {}", command);
+
+    // Ctrl-C interrupts the debugged program; Delve handles it.
+    let _interrupts = interrupt::DeferInterrupts::new();
+    cmd.status()
+        .map_err(|err| anyhow::anyhow!("Failed to run dlv: {}", err))
+}
+
+/// Opens the first selected test in `$VISUAL` or `$EDITOR`, or vi, at the
+/// line declaring it.
+fn edit_selection(tests: &[TestInfo], selected: &[Selection]) -> Result<()> {
+    let Some(selection) = selected.first() else {
+        return Ok(());
+    };
+    let test = &tests[selection.test];
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut words = shlex::split(&editor)
+        .filter(|words| !words.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Invalid editor command: {}", editor))?;
+    let program = words.remove(0);
+
+    let mut cmd = Command::new(&program);
+    cmd.args(words)
+        .args(editor_location(&program, &test.file, test.line));
+    // Ctrl-C is the editor's to handle.
+    let _interrupts = interrupt::DeferInterrupts::new();
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+/// The arguments opening `file` at `line`, as the editor `program` takes
+/// them: `+LINE FILE` unless it is known to want `FILE:LINE`.
+fn editor_location(program: &str, file: &str, line: usize) -> Vec<String> {
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program);
+    match name {
+        "code" | "code-insiders" | "codium" | "cursor" => {
+            vec!["--goto".to_string(), format!("{}:{}", file, line)]
+        }
+        "subl" | "zed" | "hx" => vec![format!("{}:{}", file, line)],
+        _ => vec![format!("+{}", line), file.to_string()],
+    }
+}
+
+/// The `dlv test` command debugging the selection, which must be in a single
+/// package.
+fn delve_command(
+    tests: &[TestInfo],
+    selected: &[Selection],
+    options: &RunOptions,
+) -> Result<Command> {
+    let options = RunOptions {
+        all_packages: false,
+        ..options.clone()
+    };
+    let invocations = plan_invocations(tests, selected, &options);
+    let [invocation] = invocations.as_slice() else {
+        anyhow::bail!("--debug needs the selected tests to be in a single package");
+    };
+
+    let mut cmd = Command::new("dlv");
+    cmd.arg("test").arg(&invocation.package);
+
+    let mut build_flags = Vec::new();
+    if let Some(tags) = &options.tags {
+        build_flags.push(format!("-tags={}", tags));
+    }
+    if options.race {
+        build_flags.push("-race".to_string());
+    }
+    if !build_flags.is_empty() {
+        cmd.arg(format!("--build-flags={}", build_flags.join(" ")));
+    }
+
+    cmd.arg("--");
+    if !invocation.run_patterns.is_empty() {
+        cmd.arg("-test.run")
+            .arg(build_run_pattern(&invocation.run_patterns));
+    } else {
+        cmd.arg("-test.run").arg("^$");
+    }
+    if !invocation.bench_patterns.is_empty() {
+        cmd.arg("-test.bench")
+            .arg(build_run_pattern(&invocation.bench_patterns));
+    }
+    if options.verbose {
+        cmd.arg("-test.v");
+    }
+    cmd.args(&invocation.test_args);
+
+    if let Some(dir) = &invocation.dir {
+        cmd.current_dir(dir);
+    }
+    cmd.envs(options.env.iter().map(|(key, value)| (key, value)));
+    if invocation.workspace_off {
+        cmd.env("GOWORK", "off");
+    }
+
+    Ok(cmd)
+}
+
+/// Points out where the fuzzing run left its inputs: new failing inputs are
+/// written to the package's testdata, while the interesting inputs found
+/// along the way go to the build cache.
+fn report_fuzz_corpus(target: &FuzzTarget, options: &RunOptions) {
+    outln!("\nFuzz corpus of {}:", target.name);
+    outln!("  failing inputs: {}", target.corpus_dir.display());
+
+    let mut cmd = Command::new(&options.go_bin);
+    cmd.args(["env", "GOCACHE"]);
+    if let Some(goos) = &options.goos {
+        cmd.env("GOOS", goos);
+    }
+    if let Some(goarch) = &options.goarch {
+        cmd.env("GOARCH", goarch);
+    }
+    if let Ok(output) = cmd.output()
+        && output.status.success()
+    {
+        let cache = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !cache.is_empty() {
+            let dir = Path::new(&cache)
+                .join("fuzz")
+                .join(&target.package)
+                .join(&target.name);
+            outln!("  generated corpus: {}", dir.display());
+        }
+    }
+}
+
+/// Packages with a `TestMain` often expect flags or environment that a
+/// plain `-run` does not provide, so point that out before running.
+fn warn_test_main(tests: &[TestInfo], selected: &[Selection]) {
+    let mut warned = HashSet::new();
+
+    for selection in selected {
+        let test = &tests[selection.test];
+        if test.has_test_main && warned.insert(&test.package) {
+            errln!(
+                "warning: {} defines TestMain; pass any flags it needs with --test-main-args",
+                test.package
+            );
+        }
+    }
+}
+
+/// Selected tests whose files need tags `--tags` does not give are not built,
+/// so `-run` matches nothing for them.
+fn warn_build_tags(tests: &[TestInfo], selected: &[Selection], options: &RunOptions) {
+    let build_tags = constraint::BuildTags::new(
+        options.tags.as_deref(),
+        options.goos.as_deref(),
+        options.goarch.as_deref(),
+    );
+    let mut warned = HashSet::new();
+
+    for selection in selected {
+        let test = &tests[selection.test];
+        if let Some(constraint) = &test.constraint
+            && !constraint.eval(&build_tags)
+            && warned.insert(selection.test)
+        {
+            errln!(
+                "warning: {} is only built with `{}`; without matching --tags its run matches nothing",
+                test.name,
+                constraint
+            );
+        }
+    }
+}
+
+/// Lists the selected tests that will not run because they skip themselves
+/// under `-short`.
+fn warn_short_skips(tests: &[TestInfo], selected: &[Selection]) {
+    let mut warned = HashSet::new();
+
+    for selection in selected {
+        let test = &tests[selection.test];
+        if test.short_skip && warned.insert(selection.test) {
+            errln!("note: {} skips itself with -short", test.name);
+        }
+    }
+}
+
+/// Splits the selection into `go test` runs. Plain tests and benchmarks run
+/// in one run per package, each with only that package's patterns, or share
+/// one run over `./...` with `--all-packages`. Ginkgo and gocheck subtests
+/// are selected with test binary flags that other packages would reject, so
+/// each of those suites gets a run of its own. A Convey block runs its whole
+/// test. `--test-main-args` likewise moves tests of TestMain packages into
+/// runs of their own, and with `--all-packages` so does a name shared by
+/// tests in several packages, so that only the selected one runs. Each
+/// selected fuzz target is fuzzed in a run of its own, since `go test -fuzz`
+/// takes one target in one package; its seed entries run like subtests.
+fn plan_invocations(
+    tests: &[TestInfo],
+    selected: &[Selection],
+    options: &RunOptions,
+) -> Vec<Invocation> {
+    let test_main_args = options.test_main_args.as_slice();
+    let mut invocations = Vec::new();
+    let mut filtered: Vec<(&TestInfo, Vec<&str>)> = Vec::new();
+    let duplicates = duplicate_names(tests);
+
+    for selection in selected {
+        let test = &tests[selection.test];
+
+        // `--all` runs the seed corpus of fuzz targets, as go test does.
+        if test.kind == TestKind::Fuzz && selection.subtest.is_none() && !options.all {
+            invocations.push(fuzz_invocation(test, test_main_args));
+            continue;
+        }
+
+        let pattern = match selection.subtest.as_deref() {
+            // Benchmark names are anchored so that selecting `BenchmarkX`
+            // does not also run the slow `BenchmarkXLarge`.
+            None if test.kind == TestKind::Benchmark => format!("^{}$", test.name),
+            Some(subtest) if test.kind == TestKind::Benchmark => {
+                Anchor::Full.apply_levels(&format!("{}/{}", test.name, subtest))
+            }
+            None => options.anchor.apply(&test.name),
+            Some(_) if test.framework == Framework::Convey => format!("^{}$", test.name),
+            Some(subtest) if test.framework != Framework::Standard => {
+                match filtered.iter_mut().find(|(t, _)| std::ptr::eq(*t, test)) {
+                    Some((_, subtests)) => subtests.push(subtest),
+                    None => filtered.push((test, vec![subtest])),
+                }
+                continue;
+            }
+            Some(subtest) => options
+                .anchor
+                .apply_levels(&format!("{}/{}", test.name, subtest)),
+        };
+
+        let scoped = duplicates.contains(test.name.as_str());
+        let invocation = shared_invocation(
+            &mut invocations,
+            test,
+            test_main_args,
+            scoped,
+            options.all_packages,
+        );
+        let patterns = match test.kind {
+            TestKind::Benchmark => &mut invocation.bench_patterns,
+            _ => &mut invocation.run_patterns,
+        };
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+
+    for (test, subtests) in filtered {
+        let mut test_args = test.framework.filter_args(&subtests).unwrap_or_default();
+        if test.has_test_main {
+            test_args.extend(test_main_args.iter().cloned());
+        }
+
+        invocations.push(Invocation {
+            dir: test.module_dir.clone(),
+            workspace_off: test.outside_workspace,
+            package: scoped_package(test),
+            package_dir: Some(package_dir(test).to_path_buf()),
+            import_path: Some(test.package.clone()),
+            run_patterns: vec![format!("^{}$", test.name)],
+            bench_patterns: Vec::new(),
+            test_args,
+            fuzz: None,
+        });
+    }
+
+    if options.isolate {
+        return isolate_invocations(invocations);
+    }
+    invocations
+}
+
+/// Splits invocations of several tests into one per test, for `--isolate`.
+fn isolate_invocations(invocations: Vec<Invocation>) -> Vec<Invocation> {
+    let mut isolated = Vec::new();
+    for invocation in invocations {
+        if invocation.run_patterns.len() + invocation.bench_patterns.len() <= 1 {
+            isolated.push(invocation);
+            continue;
+        }
+        for pattern in &invocation.run_patterns {
+            isolated.push(Invocation {
+                run_patterns: vec![pattern.clone()],
+                bench_patterns: Vec::new(),
+                ..invocation.clone()
+            });
+        }
+        for pattern in &invocation.bench_patterns {
+            isolated.push(Invocation {
+                run_patterns: Vec::new(),
+                bench_patterns: vec![pattern.clone()],
+                ..invocation.clone()
+            });
+        }
+    }
+    isolated
+}
+
+/// A run fuzzing one target. Its seed corpus runs first, through `-run`, and
+/// the other tests of the package are left out.
+fn fuzz_invocation(test: &TestInfo, test_main_args: &[String]) -> Invocation {
+    let test_args = if test.has_test_main {
+        test_main_args.to_vec()
+    } else {
+        Vec::new()
+    };
+
+    Invocation {
+        dir: test.module_dir.clone(),
+        workspace_off: test.outside_workspace,
+        package: scoped_package(test),
+        package_dir: Some(package_dir(test).to_path_buf()),
+        import_path: Some(test.package.clone()),
+        run_patterns: vec![format!("^{}$", test.name)],
+        bench_patterns: Vec::new(),
+        test_args,
+        fuzz: Some(FuzzTarget {
+            name: test.name.clone(),
+            package: test.package.clone(),
+            corpus_dir: package_dir(test)
+                .join("testdata")
+                .join("fuzz")
+                .join(&test.name),
+        }),
+    }
+}
+
+/// Names of tests declared in more than one package.
+fn duplicate_names(tests: &[TestInfo]) -> HashSet<&str> {
+    let mut packages: HashMap<&str, &str> = HashMap::new();
+    let mut duplicates = HashSet::new();
+
+    for test in tests {
+        match packages.get(test.name.as_str()) {
+            Some(package) if *package != test.package => {
+                duplicates.insert(test.name.as_str());
+            }
+            Some(_) => {}
+            None => {
+                packages.insert(&test.name, &test.package);
+            }
+        }
+    }
+
+    duplicates
+}
+
+/// Returns the run a plain test or benchmark joins: the run of its package,
+/// or with `all_packages` the shared run over `./...` of its module unless
+/// it is `scoped` or its TestMain gets extra arguments.
+fn shared_invocation<'a>(
+    invocations: &'a mut Vec<Invocation>,
+    test: &TestInfo,
+    test_main_args: &[String],
+    scoped: bool,
+    all_packages: bool,
+) -> &'a mut Invocation {
+    let test_args = if test.has_test_main {
+        test_main_args.to_vec()
+    } else {
+        Vec::new()
+    };
+    let package = if all_packages && !scoped && test_args.is_empty() {
+        "./...".to_string()
+    } else {
+        scoped_package(test)
+    };
+
+    let index = invocations
+        .iter()
+        .position(|invocation| {
+            invocation.dir == test.module_dir
+                && invocation.package == package
+                && invocation.fuzz.is_none()
+                && invocation.test_args == test_args
+        })
+        .unwrap_or_else(|| {
+            invocations.push(Invocation {
+                dir: test.module_dir.clone(),
+                workspace_off: test.outside_workspace,
+                package_dir: (package != "./...").then(|| package_dir(test).to_path_buf()),
+                import_path: (package != "./...").then(|| test.package.clone()),
+                package,
+                run_patterns: Vec::new(),
+                bench_patterns: Vec::new(),
+                test_args,
+                fuzz: None,
+            });
+            invocations.len() - 1
+        });
+
+    &mut invocations[index]
+}
+
+/// The package argument for a run scoped to the test's package. Runs from
+/// another module's directory name the package by import path.
+fn scoped_package(test: &TestInfo) -> String {
+    if test.module_dir.is_some() {
+        test.package.clone()
+    } else {
+        package_pattern(package_dir(test))
+    }
+}
+
+/// Turns a package directory into an argument `go test` treats as a path
+/// rather than an import path.
+fn package_pattern(dir: &Path) -> String {
+    if dir.as_os_str().is_empty() {
+        ".".to_string()
+    } else if dir.is_absolute() || dir.starts_with(".") {
+        dir.to_string_lossy().to_string()
+    } else {
+        format!("./{}", dir.to_string_lossy())
+    }
+}
+
+/// A selected entry: the test it belongs to and, for a subtest, its name or
+/// run pattern below the test.
+#[derive(Clone, PartialEq)]
+struct Selection {
+    /// Index into the discovered tests.
+    test: usize,
+    subtest: Option<String>,
+}
+
+/// A selector line. The displayed text carries annotations and the package;
+/// selecting it yields `selection`.
+#[derive(Clone)]
+struct TestItem {
+    /// Tab-separated fields: the last run's duration once durations were
+    /// recorded, the test with its annotations and its package, in either
+    /// order, and where it is declared.
+    text: String,
+    /// The test's name, with the subtest's for subtests.
+    name: String,
+    selection: Selection,
+    /// The test's source, with how its last run went once results were
+    /// recorded, shown in the preview pane.
+    preview: Option<String>,
+}
+
+/// A [`TestItem`] as skim takes it, matching the fields it is told to of
+/// the text without colors and showing the text with them.
+struct SkimTestItem {
+    item: TestItem,
+    plain: String,
+    /// Byte ranges of the matched fields in `plain`.
+    ranges: Vec<(usize, usize)>,
+}
+
+impl SkimTestItem {
+    fn new(item: TestItem, nth: &[isize]) -> SkimTestItem {
+        let plain = logfile::strip_ansi(&item.text);
+        let ranges = picker::field_ranges(&plain, nth);
+        SkimTestItem {
+            item,
+            plain,
+            ranges,
+        }
+    }
+}
+
+impl SkimItem for SkimTestItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.plain)
+    }
+
+    fn display<'a>(&'a self, context: DisplayContext<'a>) -> AnsiString<'a> {
+        let attr = context.highlight_attr;
+        let matched = match context.matches {
+            Matches::CharIndices(indices) => indices
+                .iter()
+                .map(|&index| (attr, (index as u32, index as u32 + 1)))
+                .collect(),
+            Matches::CharRange(start, end) => vec![(attr, (start as u32, end as u32))],
+            Matches::ByteRange(start, end) => {
+                let chars = |end: usize| self.plain[..end].chars().count() as u32;
+                vec![(attr, (chars(start), chars(end)))]
+            }
+            Matches::None => Vec::new(),
+        };
+        let mut display = AnsiString::parse(&self.item.text);
+        display.override_attrs(matched);
+        display
+    }
+
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        ItemPreview::AnsiText(self.item.preview.clone().unwrap_or_default())
+    }
+
+    fn get_matching_ranges(&self) -> Option<&[(usize, usize)]> {
+        Some(&self.ranges)
+    }
+}
+
+/// Builds the selector entries. In `-short` mode the tests that skip
+/// themselves under it say so more prominently, and quarantined tests are
+/// marked. Once durations were recorded, each entry starts with the time it
+/// took in its last run, and once results were, with a glyph for how that
+/// run went: green for a pass, red for a failure, yellow for a skip and grey
+/// when the test has not run. The preview shows the test's source, highlighted,
+/// and once results were recorded, its last run: the output of a failed
+/// run before the source, that of others after it.
+/// With `--group`, entries start with their package and are kept together
+/// by package.
+fn collect_test_items(
+    tests: &[TestInfo],
+    options: &RunOptions,
+    durations: &HashMap<(String, String), f64>,
+    last_results: &HashMap<(String, String), history::LastResult>,
+) -> Vec<TestItem> {
+    let (short, sort) = (options.short, options.sort);
+    let mut items = Vec::new();
+    let now = unix_time();
+    let short_packages = if options.group {
+        short_package_names(tests)
+    } else {
+        HashMap::new()
+    };
+    let entry = |text: String, package: &str| match short_packages.get(package) {
+        Some(short) => format!("{} ::\t{}", short, text),
+        None => format!("{}\t[{}]", text, package),
+    };
+    // Only a theme given turns colors off, so the terminal is not asked.
+    let plain = options
+        .theme
+        .as_deref()
+        .is_some_and(|theme| theme::is_plain(theme::resolve(Some(theme)).as_deref()));
+    let sources: RefCell<HashMap<String, Option<highlight::Source>>> = RefCell::default();
+    let preview = |test: &TestInfo, name: String, subtest: Option<&Subtest>| {
+        let source = sources
+            .borrow_mut()
+            .entry(test.file.clone())
+            .or_insert_with(|| highlight::Source::read(&test.file, plain))
+            .as_ref()
+            .map(|source| source.preview(test.line, subtest));
+        let result = last_results.get(&(test.package.clone(), name));
+        let mut preview = match result {
+            Some(result) => format!(
+                "Last run {}: {} in {:.2}s\n\n",
+                format_age(now.saturating_sub(result.time)),
+                result.status.to_uppercase(),
+                result.seconds
+            ),
+            None if !last_results.is_empty() => "Not run yet\n\n".to_string(),
+            None => String::new(),
+        };
+        let output = result
+            .filter(|result| !result.output.is_empty())
+            .map(|result| format!("{}\n", result.output.join("\n")));
+        let failed = result.is_some_and(|result| result.status == "fail");
+        let parts = if failed {
+            [output, source]
+        } else {
+            [source, output]
+        };
+        preview.push_str(&parts.into_iter().flatten().collect::<Vec<_>>().join("\n"));
+        (!preview.is_empty()).then_some(preview)
+    };
+    let status = |test: &TestInfo, name: String| {
+        if last_results.is_empty() {
+            return String::new();
+        }
+        let status = last_results
+            .get(&(test.package.clone(), name))
+            .map(|result| result.status.as_str());
+        let (color, glyph) = match status {
+            Some("pass") => ("\x1b[32m", "✓"),
+            Some("fail") => ("\x1b[31m", "✗"),
+            Some("skip") => ("\x1b[33m", "»"),
+            _ => ("\x1b[90m", "·"),
+        };
+        // The glyph starts the duration's field when there is one, and
+        // takes a field of its own otherwise, which queries do not match.
+        let separator = if durations.is_empty() { '\t' } else { ' ' };
+        if plain {
+            format!("{}{}", glyph, separator)
+        } else {
+            format!("{}{}\x1b[0m{}", color, glyph, separator)
+        }
+    };
+    let duration = |test: &TestInfo, name: String| durations.get(&(test.package.clone(), name));
+    let column = |duration: Option<&f64>| {
+        if durations.is_empty() {
+            return String::new();
+        }
+        // Narrower after a status glyph, so the two still fit a tab stop.
+        let width = if last_results.is_empty() { 6 } else { 5 };
+        match duration {
+            Some(seconds) => format!("{:>width$}\t", format!("{:.2}s", seconds)),
+            None => format!("{:>width$}\t", "-"),
+        }
+    };
+
+    for (index, subtests) in sort_tests(tests, sort, durations, last_results) {
+        let test = &tests[index];
+        let mut labels = test_labels(test);
+        if short && test.short_skip {
+            labels.retain(|label| *label != "skipped with -short");
+            labels.insert(0, "WILL SKIP: -short".into());
+        }
+        if options.quarantine.contains(&test.package, &test.name) {
+            labels.insert(0, "QUARANTINED".into());
+        }
+        let text = if labels.is_empty() {
+            test.name.clone()
+        } else {
+            format!("{} ({})", test.name, labels.join(", "))
+        };
+        let text = format!(
+            "{}{}{}",
+            status(test, test.name.clone()),
+            column(duration(test, test.name.clone())),
+            entry(text, &test.package)
+        );
+        items.push(TestItem {
+            text,
+            name: test.name.clone(),
+            selection: Selection {
+                test: index,
+                subtest: None,
+            },
+            preview: preview(test, test.name.clone(), None),
+        });
+
+        for subtest in subtests {
+            let subtest = &test.subtests[subtest];
+            let seconds = duration(test, format!("{}/{}", test.name, subtest.name));
+            let mut text = if subtest.dynamic {
+                format!("{}/{}* (dynamic)", test.name, subtest.name)
+            } else {
+                format!("{}/{}", test.name, subtest.name)
+            };
+            if short && test.short_skip {
+                text.push_str(" (WILL SKIP: -short)");
+            }
+            let name = format!("{}/{}", test.name, subtest.name);
+            let status = status(test, name.clone());
+            if options.quarantine.contains(&test.package, &name) {
+                text.push_str(" (QUARANTINED)");
+            }
+            // Frameworks filtering their own subtests take the plain name.
+            let pattern = if test.framework == Framework::Standard {
+                subtest.run_pattern()
+            } else {
+                subtest.name.clone()
+            };
+            items.push(TestItem {
+                text: format!(
+                    "{}{}{}",
+                    status,
+                    column(seconds),
+                    entry(text, &test.package)
+                ),
+                name: name.clone(),
+                selection: Selection {
+                    test: index,
+                    subtest: Some(pattern),
+                },
+                preview: preview(test, name, Some(subtest)),
+            });
+        }
+    }
+
+    // A dimmed last field with where each test is declared and how many
+    // subtests it has. The fields are lined up by padding each to the
+    // longest of its column, up to a limit.
+    let mut widths: Vec<usize> = Vec::new();
+    for item in &items {
+        for (field, text) in item.text.split('\t').enumerate() {
+            let width = visible_width(text).min(MAX_FIELD_WIDTH);
+            match widths.get_mut(field) {
+                Some(widest) => *widest = (*widest).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    for item in &mut items {
+        let test = &tests[item.selection.test];
+        let mut column = format!("{}:{}", display_path(&test.file), test.line);
+        if item.selection.subtest.is_none() && !test.subtests.is_empty() {
+            let count = test.subtests.len();
+            let plural = if count == 1 { "" } else { "s" };
+            column.push_str(&format!("  {} subtest{}", count, plural));
+        }
+        let fields: Vec<String> = item
+            .text
+            .split('\t')
+            .zip(&widths)
+            .map(|(text, &width)| {
+                let padding = width.saturating_sub(visible_width(text));
+                format!("{}{}", text, " ".repeat(padding))
+            })
+            .collect();
+        item.text = format!("{}\t\x1b[2m{}\x1b[0m", fields.join("\t"), column);
+    }
+
+    if options.group {
+        let mut packages: Vec<&str> = Vec::new();
+        for test in tests {
+            if !packages.contains(&test.package.as_str()) {
+                packages.push(&test.package);
+            }
+        }
+        items.sort_by_key(|item| {
+            let package = &tests[item.selection.test].package;
+            packages.iter().position(|p| p == package)
+        });
+    }
+
+    items
+}
+
+/// The order `sort` lists the tests in, as indices, each with the order of
+/// its subtests. Sorting is stable, so ties keep the source order.
+fn sort_tests(
+    tests: &[TestInfo],
+    sort: SortOrder,
+    durations: &HashMap<(String, String), f64>,
+    last_results: &HashMap<(String, String), history::LastResult>,
+) -> Vec<(usize, Vec<usize>)> {
+    use std::cmp::{Ordering, Reverse};
+
+    let key = |test: &TestInfo, name: String| (test.package.clone(), name);
+    let duration = |test: &TestInfo, name: String| durations.get(&key(test, name));
+    // Failures first, latest first, then the other results, latest first.
+    let status = |test: &TestInfo, name: String| match last_results.get(&key(test, name)) {
+        Some(result) => (u8::from(result.status != "fail"), Reverse(result.time)),
+        None => (2, Reverse(0)),
+    };
+    let slowest_first =
+        |a: Option<&f64>, b: Option<&f64>| b.partial_cmp(&a).unwrap_or(Ordering::Equal);
+    let subtest_name =
+        |test: &TestInfo, subtest: usize| format!("{}/{}", test.name, test.subtests[subtest].name);
+
+    let mut order: Vec<usize> = (0..tests.len()).collect();
+    match sort {
+        SortOrder::Source => {}
+        SortOrder::Name => order.sort_by(|&a, &b| tests[a].name.cmp(&tests[b].name)),
+        SortOrder::File => order.sort_by(|&a, &b| {
+            (&tests[a].file, tests[a].line).cmp(&(&tests[b].file, tests[b].line))
+        }),
+        SortOrder::Package => order.sort_by(|&a, &b| tests[a].package.cmp(&tests[b].package)),
+        SortOrder::Recent => {
+            let mut modified = HashMap::new();
+            for test in tests {
+                modified.entry(test.file.as_str()).or_insert_with(|| {
+                    std::fs::metadata(&test.file)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                });
+            }
+            order.sort_by_key(|&index| Reverse(modified[tests[index].file.as_str()]));
+        }
+        SortOrder::Duration => order.sort_by(|&a, &b| {
+            slowest_first(
+                duration(&tests[a], tests[a].name.clone()),
+                duration(&tests[b], tests[b].name.clone()),
+            )
+        }),
+        SortOrder::Status => {
+            order.sort_by_key(|&index| status(&tests[index], tests[index].name.clone()))
+        }
+    }
+
+    order
+        .into_iter()
+        .map(|index| {
+            let test = &tests[index];
+            let mut subtests: Vec<usize> = (0..test.subtests.len()).collect();
+            match sort {
+                SortOrder::Name => {
+                    subtests.sort_by(|&a, &b| test.subtests[a].name.cmp(&test.subtests[b].name))
+                }
+                SortOrder::Duration => subtests.sort_by(|&a, &b| {
+                    slowest_first(
+                        duration(test, subtest_name(test, a)),
+                        duration(test, subtest_name(test, b)),
+                    )
+                }),
+                SortOrder::Status => {
+                    subtests.sort_by_key(|&subtest| status(test, subtest_name(test, subtest)))
+                }
+                _ => {}
+            }
+            (index, subtests)
+        })
+        .collect()
+}
+
+/// Selector fields longer than this are not lined up with the others.
+const MAX_FIELD_WIDTH: usize = 48;
+
+/// How many columns `text` takes, leaving out its colors.
+fn visible_width(text: &str) -> usize {
+    logfile::strip_ansi(text).chars().count()
+}
+
+/// A test file's path relative to the current directory where possible.
+fn display_path(file: &str) -> String {
+    let path = Path::new(file);
+    let relative = std::env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path);
+    let relative = relative.strip_prefix(".").unwrap_or(relative);
+    relative.to_string_lossy().to_string()
+}
+
+/// Package import paths shortened to what tells them apart: the leading
+/// path elements they all share are dropped, keeping at least the last
+/// element of each, so `example.com/app/pkg/auth` becomes `pkg/auth`.
+fn short_package_names(tests: &[TestInfo]) -> HashMap<String, String> {
+    let packages: HashSet<&str> = tests.iter().map(|test| test.package.as_str()).collect();
+    let shared = packages
+        .iter()
+        .map(|package| package.split('/').collect::<Vec<_>>())
+        .reduce(|shared, elements| {
+            let common = shared
+                .iter()
+                .zip(&elements)
+                .take_while(|(a, b)| a == b)
+                .count();
+            shared[..common.min(elements.len() - 1)].to_vec()
+        })
+        .map_or(0, |shared| shared.len());
+
+    packages
+        .into_iter()
+        .map(|package| {
+            let elements: Vec<_> = package.split('/').collect();
+            let skip = shared.min(elements.len() - 1);
+            (package.to_string(), elements[skip..].join("/"))
+        })
+        .collect()
+}
+
+/// Accept action of the binding that runs the selection with `-race`.
+const RACE_ACTION: &str = "race";
+
+/// Accept action of the binding that runs the selection with `-v`.
+const VERBOSE_ACTION: &str = "verbose";
+
+/// Accept action of the binding that debugs the selection with Delve.
+const DEBUG_ACTION: &str = "debug";
+
+/// Accept action of the binding that reruns the last run's failures.
+const RERUN_FAILED_ACTION: &str = "rerun-failed";
+
+/// Accept action of the binding that copies the selection's command.
+const COPY_ACTION: &str = "copy";
+
+/// Accept action of the binding that copies the selection's -run pattern.
+const COPY_PATTERN_ACTION: &str = "copy-pattern";
+
+/// Action of a binding that runs the selection like Enter does.
+const RUN_ACTION: &str = "run";
+
+/// Accept action of the binding that opens the highlighted test in the
+/// editor.
+const EDIT_ACTION: &str = "edit";
+
+/// Action of the binding that switches between fuzzy and exact matching.
+const TOGGLE_EXACT_ACTION: &str = "toggle-exact";
+
+/// Actions of the bindings that switch a flag of the runs on or off.
+const TOGGLE_VERBOSE_ACTION: &str = "toggle-verbose";
+const TOGGLE_RACE_ACTION: &str = "toggle-race";
+const TOGGLE_SHORT_ACTION: &str = "toggle-short";
+const TOGGLE_COVER_ACTION: &str = "toggle-cover";
+
+/// Keys bound unless configured otherwise.
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("alt-r", RACE_ACTION),
+    ("alt-d", DEBUG_ACTION),
+    ("alt-f", RERUN_FAILED_ACTION),
+    ("alt-y", COPY_ACTION),
+    ("alt-e", TOGGLE_EXACT_ACTION),
+    ("ctrl-o", EDIT_ACTION),
+    ("alt-v", TOGGLE_VERBOSE_ACTION),
+    ("alt-x", TOGGLE_RACE_ACTION),
+    ("alt-s", TOGGLE_SHORT_ACTION),
+    ("alt-c", TOGGLE_COVER_ACTION),
+    ("alt-a", picker::TOGGLE_ALL_ACTION),
+];
+
+/// What an action does, as the selector's header puts it, or `None` for an
+/// unknown action.
+fn action_description(action: &str) -> Option<&'static str> {
+    Some(match action {
+        RACE_ACTION => "run with -race",
+        VERBOSE_ACTION => "run with -v",
+        DEBUG_ACTION => "debug with Delve",
+        RERUN_FAILED_ACTION => "rerun the last failures",
+        COPY_ACTION => "copy the command",
+        COPY_PATTERN_ACTION => "copy the -run pattern",
+        RUN_ACTION => "run",
+        TOGGLE_EXACT_ACTION => "toggle exact matching",
+        EDIT_ACTION => "open in $EDITOR",
+        TOGGLE_VERBOSE_ACTION => "toggle -v",
+        TOGGLE_RACE_ACTION => "toggle -race",
+        TOGGLE_SHORT_ACTION => "toggle -short",
+        TOGGLE_COVER_ACTION => "toggle -cover",
+        picker::SELECT_ALL_ACTION => "select all",
+        picker::DESELECT_ALL_ACTION => "deselect all",
+        picker::TOGGLE_ALL_ACTION => "toggle all",
+        _ => return None,
+    })
+}
+
+/// The selector's header: what discovery found, unless `tests` are still
+/// being found, and which flags the runs get, above the key help listing
+/// the bound actions.
+fn selector_header(tests: Option<&[TestInfo]>, options: &RunOptions) -> String {
+    let mut header = String::new();
+    if let Some(tests) = tests {
+        header.push_str(&discovery_summary(tests, options.filtered));
+        header.push('\n');
+    }
+    let flags: Vec<String> = [
+        ("-v", options.verbose),
+        ("-race", options.race),
+        ("-short", options.short),
+        ("-cover", options.cover),
+    ]
+    .iter()
+    .map(|(flag, on)| format!("{} {}", flag, if *on { "on" } else { "off" }))
+    .collect();
+    header.push_str(&format!("Flags: {}\n", flags.join(", ")));
+
+    header.push_str("Press TAB to select multiple tests, ENTER to confirm");
+    for (key, action) in &options.bindings {
+        if let Some(description) = action_description(action) {
+            header.push_str(&format!(", {} to {}", key.to_uppercase(), description));
+        }
+    }
+    if options.session {
+        header.push_str(", ESC to quit");
+    }
+    header
+}
+
+/// What discovery found, counting the `filtered` tests the flags left out.
+fn discovery_summary(tests: &[TestInfo], filtered: usize) -> String {
+    let packages: HashSet<&str> = tests.iter().map(|test| test.package.as_str()).collect();
+    let subtests: usize = tests.iter().map(|test| test.subtests.len()).sum();
+    format!(
+        "{} in {} ({}), filtered: {}",
+        counted(tests.len(), "test"),
+        counted(packages.len(), "package"),
+        counted(subtests, "subtest"),
+        filtered
+    )
+}
+
+/// `count` and the noun, plural unless `count` is 1.
+fn counted(count: usize, noun: &str) -> String {
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{}", count, noun, plural)
+}
+
+/// What the test selector was left with.
+struct Choice {
+    /// The picked entries; none when the selector was cancelled.
+    selections: Vec<Selection>,
+    /// The accept action, when the selection was confirmed with a binding
+    /// other than Enter.
+    action: Option<String>,
+    query: String,
+}
+
+/// Entries selected from the start, by their index.
+struct Preselect(Vec<usize>);
+
+impl Selector for Preselect {
+    fn should_select(&self, index: usize, _item: &dyn SkimItem) -> bool {
+        self.0.contains(&index)
+    }
+}
+
+/// The fields of the selector entries that queries match: the test's, or
+/// with `--match-all` all but the duration, which comes first when there is
+/// one.
+fn selector_fields(options: &RunOptions) -> Vec<isize> {
+    if options.match_all {
+        // The test, its package and its file, in either order.
+        vec![-3, -2, -1]
+    } else if options.group {
+        vec![-2]
+    } else {
+        vec![-3]
+    }
+}
+
+/// Index of the item the cursor starts on: the first preselected one, or
+/// else the first of the last run's.
+fn cursor_item(items: &[TestItem], options: &RunOptions) -> Option<usize> {
+    let position = |selections: &[Selection]| {
+        items
+            .iter()
+            .position(|item| selections.contains(&item.selection))
+    };
+    position(&options.preselect).or_else(|| position(&options.recent))
+}
+
+/// Indices of the items `options.preselect` selects.
+fn preselected(items: &[TestItem], options: &RunOptions) -> Vec<usize> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| options.preselect.contains(&item.selection))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Shows the selector until it is left with something other than a toggle,
+/// such as of exact matching or `-v`, which reopens it with the query kept,
+/// and leaves the query, matching mode and flags in `options`.
+fn show_selector(
+    tests: &[TestInfo],
+    mut items: Vec<TestItem>,
+    durations: &HashMap<(String, String), f64>,
+    last_results: &HashMap<(String, String), history::LastResult>,
+    options: &mut RunOptions,
+) -> Result<Choice> {
+    loop {
+        let header = selector_header(Some(tests), options);
+        let choice = match options.ui {
+            Ui::Skim => skim_select(items.clone(), &header, options, None)?,
+            _ => picker_select(items.clone(), &header, options, None)?,
+        };
+        options.query = Some(choice.query.clone());
+        let Some(flag) = toggled_flag(options, choice.action.as_deref()) else {
+            return Ok(choice);
+        };
+        *flag = !*flag;
+        // Entries mark the tests that skip themselves under -short.
+        items = collect_test_items(tests, options, durations, last_results);
+    }
+}
+
+/// The setting a toggle action, such as of exact matching, switches.
+fn toggled_flag<'a>(options: &'a mut RunOptions, action: Option<&str>) -> Option<&'a mut bool> {
+    match action {
+        Some(TOGGLE_EXACT_ACTION) => Some(&mut options.exact),
+        Some(TOGGLE_VERBOSE_ACTION) => Some(&mut options.verbose),
+        Some(TOGGLE_RACE_ACTION) => Some(&mut options.race),
+        Some(TOGGLE_SHORT_ACTION) => Some(&mut options.short),
+        Some(TOGGLE_COVER_ACTION) => Some(&mut options.cover),
+        _ => None,
+    }
+}
+
+/// The selector's prompt, which tells when matching is exact.
+fn selector_prompt(options: &RunOptions) -> &'static str {
+    if options.exact {
+        "Select tests (TAB to multi-select, exact): "
+    } else {
+        "Select tests (TAB to multi-select): "
+    }
+}
+
+/// Shows the selector and returns what it was left with. Items `incoming`
+/// hands over are listed as they arrive, until it is closed.
+fn skim_select(
+    mut items: Vec<TestItem>,
+    header: &str,
+    options: &RunOptions,
+    incoming: Option<mpsc::Receiver<Found>>,
+) -> Result<Choice> {
+    // skim's cursor starts on the first item, so that item goes first.
+    if let Some(cursor) = cursor_item(&items, options) {
+        items[..=cursor].rotate_right(1);
+    }
+    let bindings = &options.bindings;
+    let preview = incoming.is_some() || items.iter().any(|item| item.preview.is_some());
+    let preselect = preselected(&items, options);
+    let nth = &selector_fields(options);
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    for item in items {
+        tx.send(Arc::new(SkimTestItem::new(item, nth)))?;
+    }
+
+    let skim_options = SkimOptionsBuilder::default()
+        .height(options.height.clone())
+        .min_height(options.min_height.to_string())
+        .layout(options.layout.name().to_string())
+        .no_clear(options.screen == Screen::Keep)
+        .color(theme::resolve(options.theme.as_deref()))
+        .ansi(true)
+        .query(options.query.clone())
+        .exact(options.exact)
+        .case(options.case.skim())
+        .multi(true)
+        .selector(
+            (!preselect.is_empty()).then(|| Rc::new(Preselect(preselect)) as Rc<dyn Selector>),
+        )
+        .preview(preview.then(String::new))
+        .preview_window("right:50%".to_string())
+        .bind(
+            bindings
+                .iter()
+                .map(|(key, action)| match action.as_str() {
+                    RUN_ACTION => format!("{}:accept", key),
+                    action if picker::SELECTION_ACTIONS.contains(&action) => {
+                        format!("{}:{}", key, action)
+                    }
+                    _ => format!("{}:accept({})", key, action),
+                })
+                .collect(),
+        )
+        .prompt(selector_prompt(options).to_string())
+        .header(Some(header.to_string()))
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build skim options: {}", e))?;
+
+    // skim stops reading once closed, while the items keep coming in
+    // until the end of the scan.
+    let result = std::thread::scope(|scope| {
+        if let Some(incoming) = incoming {
+            scope.spawn(move || {
+                for item in incoming.into_iter().flat_map(|found| found.items) {
+                    let _ = tx.send(Arc::new(SkimTestItem::new(item, nth)));
+                }
+            });
+        } else {
+            drop(tx);
+        }
+        on_screen(options, || Skim::run_with(&skim_options, Some(rx)))
+    });
+
+    let Some(output) = result else {
+        return Ok(Choice {
+            selections: Vec::new(),
+            action: None,
+            query: options.query.clone().unwrap_or_default(),
+        });
+    };
+    if output.is_abort {
+        return Ok(Choice {
+            selections: Vec::new(),
+            action: None,
+            query: output.query,
+        });
+    }
+
+    let action = match output.final_event {
+        Event::EvActAccept(action) => action,
+        _ => None,
+    };
+    let selections = output
+        .selected_items
+        .iter()
+        .filter_map(|item| item.as_any().downcast_ref::<SkimTestItem>())
+        .map(|item| item.item.selection.clone())
+        .collect();
+    Ok(Choice {
+        selections,
+        action,
+        query: output.query,
+    })
+}
+
+/// [`skim_select`] with the built-in selector or fzf; only the built-in
+/// selector takes `incoming` items. While they come in, the first line of
+/// `header` is replaced with their summary.
+fn picker_select(
+    mut items: Vec<TestItem>,
+    header: &str,
+    options: &RunOptions,
+    incoming: Option<mpsc::Receiver<Found>>,
+) -> Result<Choice> {
+    let entries: Vec<_> = (0..items.len())
+        .map(|index| picker_entry(&items, index))
+        .collect();
+    let mut header = header.to_string();
+    if options.ui == Ui::Builtin
+        && (incoming.is_some() || entries.iter().any(|entry| entry.parent.is_some()))
+    {
+        header
+            .push_str(", RIGHT/LEFT to expand/collapse, CTRL-T to select a test with its subtests");
+    }
+    let selected = preselected(&items, options);
+    let picker = picker::Picker {
+        prompt: selector_prompt(options),
+        header: &header,
+        multi: true,
+        query: options.query.as_deref().unwrap_or_default(),
+        selected: &selected,
+        cursor: cursor_item(&items, options),
+        nth: &selector_fields(options),
+        exact: options.exact,
+        case: options.case,
+        bindings: &options.bindings,
+        color: theme::resolve(options.theme.as_deref()),
+        layout: options.layout,
+        keep: options.screen == Screen::Keep,
+        inline: options.inline,
+        height: &options.height,
+        min_height: options.min_height,
+    };
+    let picked = match incoming {
+        None => pick(&picker, &entries, options)?,
+        Some(incoming) => {
+            let (sender, receiver) = mpsc::channel();
+            let listed = std::mem::take(&mut items);
+            let (picked, listed) = std::thread::scope(|scope| {
+                let controls = header.split_once('\n').map_or("", |(_, rest)| rest);
+                let forward = scope.spawn(move || {
+                    let mut items = listed;
+                    for found in incoming {
+                        let start = items.len();
+                        items.extend(found.items);
+                        let entries = (start..items.len())
+                            .map(|index| picker_entry(&items, index))
+                            .collect();
+                        let _ = sender.send(picker::Incoming::Entries(entries));
+                        let header = format!("{}\n{}", found.summary, controls);
+                        let _ = sender.send(picker::Incoming::Header(header));
+                    }
+                    items
+                });
+                let picked = picker.pick_streamed(entries, receiver);
+                (picked, forward.join().unwrap())
+            });
+            if options.screen == Screen::Clear {
+                clear_screen();
+            }
+            items = listed;
+            picked?
+        }
+    };
+
+    Ok(Choice {
+        selections: picked
+            .indices
+            .into_iter()
+            .map(|index| items[index].selection.clone())
+            .collect(),
+        action: picked.action.filter(|action| action != RUN_ACTION),
+        query: picked.query,
+    })
+}
+
+/// The picker's entry for `items[index]`. Subtests go under their test, or
+/// under the subtest their name extends.
+fn picker_entry(items: &[TestItem], index: usize) -> picker::Entry {
+    let item = &items[index];
+    let parent = || {
+        item.selection.subtest.as_ref()?;
+        let (parent_name, _) = item.name.rsplit_once('/')?;
+        let siblings = || {
+            items
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| other.selection.test == item.selection.test)
+        };
+        siblings()
+            .find(|(_, other)| other.name == parent_name)
+            .or_else(|| siblings().find(|(_, other)| other.selection.subtest.is_none()))
+            .map(|(index, _)| index)
+    };
+    picker::Entry {
+        text: item.text.clone(),
+        preview: item.preview.clone(),
+        parent: parent(),
+    }
+}
+
+/// A selector line for a recorded run, previewing its tests and commands.
+struct RunItem {
+    text: String,
+    run: history::Run,
+}
+
+impl SkimItem for RunItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.text)
+    }
+
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        ItemPreview::Text(run_preview(&self.run))
+    }
+}
+
+/// The tests and commands of a recorded run.
+fn run_preview(run: &history::Run) -> String {
+    let mut preview = String::from("Tests:\n");
+    for test in &run.tests {
+        preview.push_str(&format!("  {}  [{}]\n", display_name(test), test.package));
+    }
+    preview.push_str("\nCommands:\n");
+    for command in &run.commands {
+        preview.push_str(&format!("  {}\n", command));
+    }
+    preview
+}
+
+/// A recorded run's selector line: when it happened and what it ran.
+fn run_text(run: &history::Run, now: u64) -> String {
+    let tests: Vec<_> = run.tests.iter().map(display_name).collect();
+    format!(
+        "{:>14}  {}",
+        format_age(now.saturating_sub(run.time)),
+        tests.join(", ")
+    )
+}
+
+fn display_name(test: &history::SelectedTest) -> String {
+    match &test.subtest {
+        Some(subtest) => format!("{}/{}", test.name, subtest),
+        None => test.name.clone(),
+    }
+}
+
+/// Shows the recorded runs, newest first, and returns the one picked.
+fn skim_select_run(runs: Vec<history::Run>, options: &RunOptions) -> Result<Option<history::Run>> {
+    let now = unix_time();
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    for run in runs {
+        let text = run_text(&run, now);
+        tx.send(Arc::new(RunItem { text, run }))?;
+    }
+    drop(tx);
+
+    let skim_options = SkimOptionsBuilder::default()
+        .height(options.height.clone())
+        .min_height(options.min_height.to_string())
+        .layout(options.layout.name().to_string())
+        .no_clear(options.screen == Screen::Keep)
+        .color(theme::resolve(options.theme.as_deref()))
+        .preview(Some(String::new()))
+        .preview_window("down:50%".to_string())
+        .exact(options.exact)
+        .case(options.case.skim())
+        .prompt("Replay run: ".to_string())
+        .header(Some(
+            "Press ENTER to run the selected tests again".to_string(),
+        ))
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build skim options: {}", e))?;
+
+    let result = on_screen(options, || Skim::run_with(&skim_options, Some(rx)));
+
+    Ok(result
+        .filter(|output| !output.is_abort)
+        .and_then(|output| output.selected_items.into_iter().next())
+        .and_then(|item| {
+            item.as_any()
+                .downcast_ref::<RunItem>()
+                .map(|item| item.run.clone())
+        }))
+}
+
+/// [`skim_select_run`] with the built-in selector or fzf.
+fn picker_select_run(
+    mut runs: Vec<history::Run>,
+    options: &RunOptions,
+) -> Result<Option<history::Run>> {
+    let now = unix_time();
+    let entries: Vec<_> = runs
+        .iter()
+        .map(|run| picker::Entry {
+            text: run_text(run, now),
+            preview: Some(run_preview(run)),
+            parent: None,
+        })
+        .collect();
+    let picker = picker::Picker {
+        prompt: "Replay run: ",
+        header: "Press ENTER to run the selected tests again",
+        multi: false,
+        query: "",
+        selected: &[],
+        cursor: None,
+        nth: &[],
+        exact: options.exact,
+        case: options.case,
+        bindings: &[],
+        color: theme::resolve(options.theme.as_deref()),
+        layout: options.layout,
+        keep: options.screen == Screen::Keep,
+        inline: options.inline,
+        height: &options.height,
+        min_height: options.min_height,
+    };
+    let picked = pick(&picker, &entries, options)?;
+
+    Ok(picked.indices.first().map(|&index| runs.swap_remove(index)))
+}
+
+fn pick(
+    picker: &picker::Picker,
+    entries: &[picker::Entry],
+    options: &RunOptions,
+) -> Result<picker::Picked> {
+    match options.ui {
+        Ui::Fzf => on_screen(options, || fzf::pick(picker, entries)),
+        // The built-in selector restores the screen itself.
+        _ => {
+            let picked = picker.pick(entries);
+            if options.screen == Screen::Clear {
+                clear_screen();
+            }
+            picked
+        }
+    }
+}
+
+/// Runs skim or fzf and leaves the terminal as `--clear` and `--no-clear`
+/// say. Drawn inline, they clear the lines they took themselves; otherwise
+/// they only leave the screen they covered as it was when they take all of
+/// it, so to restore it they are run on the alternate screen.
+fn on_screen<T>(options: &RunOptions, select: impl FnOnce() -> T) -> T {
+    let alternate = options.screen == Screen::Restore && !options.inline;
+    if alternate {
+        print!("\x1b[?1049h");
+        io::stdout().flush().unwrap();
+    }
+    let result = select();
+    match options.screen {
+        Screen::Restore if !alternate => {}
+        Screen::Restore => {
+            print!("\x1b[?1049l");
+            io::stdout().flush().unwrap();
+        }
+        Screen::Clear => clear_screen(),
+        Screen::Keep => {}
+    }
+    result
+}
+
+fn clear_screen() {
+    print!("\x1b[2J\x1b[H");
+    io::stdout().flush().unwrap();
+}
+
+/// Seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// How long ago something happened, in the largest whole unit.
+fn format_age(seconds: u64) -> String {
+    let (count, unit) = match seconds {
+        0..60 => return "just now".to_string(),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+fn build_run_pattern(selected_tests: &[String]) -> String {
+    if selected_tests.is_empty() {
+        return String::new();
+    }
+
+    if selected_tests.len() == 1 {
+        return selected_tests[0].clone();
+    }
+
+    selected_tests.join("|")
+}
+
+/// What a `go test` run reported besides its exit status.
+struct RunOutcome {
+    /// The command line that ran, as printed.
+    command: String,
+    status: ExitStatus,
+    /// How long the command took, build included.
+    elapsed: Duration,
+    shuffle_seeds: Vec<ShuffleSeed>,
+    /// Test results, when the run's events were rendered.
+    results: Vec<testjson::TestResult>,
+}
+
+/// The `-test.shuffle` seed a test binary printed, with the package result
+/// that followed it.
+struct ShuffleSeed {
+    package: String,
+    seed: i64,
+    failed: bool,
+}
+
+/// Collects shuffle seeds from `go test` output. In plain output a
+/// package's output is printed in one piece, so a seed belongs to the next
+/// result line; events name their package.
+#[derive(Default)]
+struct SeedScanner {
+    pending: Option<i64>,
+    pending_by_package: HashMap<String, i64>,
+    seeds: Vec<ShuffleSeed>,
+}
+
+impl SeedScanner {
+    fn observe(&mut self, event: &testjson::Event) {
+        if let Some(seed) = event
+            .output
+            .as_deref()
+            .and_then(|output| output.strip_prefix("-test.shuffle "))
+            .and_then(|seed| seed.trim().parse().ok())
+        {
+            self.pending_by_package.insert(event.package.clone(), seed);
+        } else if event.test.is_none()
+            && matches!(event.action.as_str(), "pass" | "fail")
+            && let Some(seed) = self.pending_by_package.remove(&event.package)
+        {
+            self.seeds.push(ShuffleSeed {
+                package: event.package.clone(),
+                seed,
+                failed: event.action == "fail",
+            });
+        }
+    }
+
+    fn scan(&mut self, line: &str) {
+        if let Some(seed) = line.strip_prefix("-test.shuffle ") {
+            self.pending = seed.trim().parse().ok();
+            return;
+        }
+
+        let (failed, rest) = if let Some(rest) = line.strip_prefix("ok ") {
+            (false, rest)
+        } else if let Some(rest) = line.strip_prefix("FAIL\t") {
+            (true, rest)
+        } else {
+            return;
+        };
+        if let Some(seed) = self.pending.take() {
+            let package = rest.split_whitespace().next().unwrap_or_default();
+            self.seeds.push(ShuffleSeed {
+                package: package.to_string(),
+                seed,
+                failed,
+            });
+        }
+    }
+}
+
+/// Runs one invocation, starting each line it prints with `prefix` if given.
+fn execute_go_test(
+    invocation: &Invocation,
+    options: &RunOptions,
+    cover_profile: Option<&Path>,
+    prefix: Option<&str>,
+) -> Result<RunOutcome> {
+    if options.binary_cache
+        && cover_profile.is_none()
+        && options.profiles.is_empty()
+        && let Some((cmd, json)) = cached_binary_command(invocation, options)?
+    {
+        return run_command(cmd, options, json, prefix);
+    }
+
+    let (cmd, json) = go_test_command(invocation, options, cover_profile);
+    run_command(cmd, options, json, prefix)
+}
+
+/// The `go test` (or gotestsum) command running the invocation, and whether
+/// it prints `-json` events.
+fn go_test_command(
+    invocation: &Invocation,
+    options: &RunOptions,
+    cover_profile: Option<&Path>,
+) -> (Command, bool) {
+    // gotestsum takes the `go test` flags after `--`.
+    let mut cmd = if options.runner == Runner::Gotestsum {
+        let mut cmd = Command::new("gotestsum");
+        cmd.args(["--format", "testname", "--"]);
+        cmd
+    } else {
+        let mut cmd = Command::new(&options.go_bin);
+        cmd.arg("test");
+        cmd
+    };
+
+    if options.count > 0 {
+        cmd.arg(format!("-count={}", options.count));
+    }
+
+    // Fuzzing reports progress as it goes, which reads better unrendered,
+    // and gotestsum renders the events itself.
+    let json = !options.raw && options.runner == Runner::Go && invocation.fuzz.is_none();
+    if json {
+        cmd.arg("-json");
+    } else if options.verbose {
+        cmd.arg("-v");
+    }
+
+    if options.race {
+        cmd.arg("-race");
+    }
+
+    if options.short {
+        cmd.arg("-short");
+    }
+
+    if options.failfast {
+        cmd.arg("-failfast");
+    }
+
+    if let Some(shuffle) = &options.shuffle {
+        cmd.arg(format!("-shuffle={}", shuffle));
+    }
+
+    if let Some(parallel) = options.parallel {
+        cmd.arg(format!("-parallel={}", parallel));
+    }
+
+    if let Some(packages) = options.package_parallel {
+        cmd.arg(format!("-p={}", packages));
+    }
+
+    if let Some(timeout) = &options.timeout {
+        cmd.arg(format!("-timeout={}", timeout));
+    }
+
+    if let Some(vet) = &options.vet {
+        cmd.arg(format!("-vet={}", vet));
+    }
+
+    if let Some(tags_value) = &options.tags {
+        cmd.arg(format!("-tags={}", tags_value));
+    }
+
+    if let Some(profile) = cover_profile {
+        cmd.arg(format!("-coverprofile={}", profile.display()));
+    }
+
+    // Profiles are taken of one package's test binary, which go test would
+    // otherwise leave in the current directory.
+    if !options.profiles.is_empty() {
+        for kind in &options.profiles {
+            cmd.arg(format!(
+                "-{}profile={}",
+                kind,
+                profile_path(invocation, options, kind).display()
+            ));
+        }
+        cmd.arg("-o").arg(profile_path(invocation, options, "test"));
+    }
+
+    if !invocation.run_patterns.is_empty() {
+        cmd.arg("-run")
+            .arg(build_run_pattern(&invocation.run_patterns));
+    } else if !invocation.bench_patterns.is_empty() {
+        // Only benchmarks were selected; keep the regular tests from running.
+        cmd.arg("-run").arg("^$");
+    }
+
+    if !invocation.bench_patterns.is_empty() {
+        cmd.arg("-bench")
+            .arg(build_run_pattern(&invocation.bench_patterns));
+        if let Some(benchtime) = &options.benchtime {
+            cmd.arg(format!("-benchtime={}", benchtime));
+        }
+        if options.benchmem {
+            cmd.arg("-benchmem");
+        }
+    }
+
+    if let Some(target) = &invocation.fuzz {
+        cmd.arg("-fuzz").arg(format!("^{}$", target.name));
+        if let Some(fuzztime) = &options.fuzztime {
+            cmd.arg(format!("-fuzztime={}", fuzztime));
+        }
+    }
+
+    cmd.args(&options.go_test_args);
+    cmd.arg(&invocation.package);
+
+    if !invocation.test_args.is_empty() {
+        cmd.arg("-args").args(&invocation.test_args);
+    }
+
+    if let Some(dir) = &invocation.dir {
+        cmd.current_dir(dir);
+    }
+    cmd.envs(options.env.iter().map(|(key, value)| (key, value)));
+    if invocation.workspace_off {
+        cmd.env("GOWORK", "off");
+    }
+    if let Some(goos) = &options.goos {
+        cmd.env("GOOS", goos);
+    }
+    if let Some(goarch) = &options.goarch {
+        cmd.env("GOARCH", goarch);
+    }
+
+    (cmd, json)
+}
+
+/// The command lines the selection would run, for `--dry-run` and
+/// `--copy`.
+fn selection_commands(
+    tests: &[TestInfo],
+    selected: &[Selection],
+    options: &RunOptions,
+) -> Result<Vec<String>> {
+    if options.debug {
+        return Ok(vec![shell_command(&delve_command(
+            tests, selected, options,
+        )?)]);
+    }
+
+    Ok(plan_invocations(tests, selected, options)
+        .iter()
+        .map(|invocation| shell_command(&go_test_command(invocation, options, None).0))
+        .collect())
+}
+
+/// Copies the commands or `-run` patterns of the selection, one per line,
+/// to the clipboard.
+fn copy_selection(
+    tests: &[TestInfo],
+    selected: &[Selection],
+    options: &RunOptions,
+    copy: CopyTarget,
+) -> Result<()> {
+    // Pasted commands are read by people, who want go's own output rather
+    // than the -json events rendered here.
+    let options = &RunOptions {
+        raw: true,
+        ..options.clone()
+    };
+    let lines = match copy {
+        CopyTarget::Command => selection_commands(tests, selected, options)?,
+        CopyTarget::Pattern => plan_invocations(tests, selected, options)
+            .iter()
+            .filter(|invocation| !invocation.run_patterns.is_empty())
+            .map(|invocation| build_run_pattern(&invocation.run_patterns))
+            .collect(),
+    };
+    let text = lines.join("\n");
+
+    let tool = clipboard::copy(&text)?;
+    outln!("Copied to the clipboard through {}:\n{}", tool, text);
     Ok(())
 }
+
+/// Spells out a command as a shell command line, changing to its directory
+/// and setting its environment first.
+fn shell_command(cmd: &Command) -> String {
+    // Words of only these characters mean the same to the shell unquoted.
+    let quote = |text: &std::ffi::OsStr| {
+        let text = text.to_string_lossy();
+        let plain = !text.is_empty()
+            && text
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_=+/.,:@%^".contains(c));
+        if plain {
+            text.to_string()
+        } else {
+            format!("'{}'", text.replace('\'', r"'\''"))
+        }
+    };
+
+    let mut words = Vec::new();
+    if let Some(dir) = cmd.get_current_dir() {
+        words.extend(["cd".to_string(), quote(dir.as_os_str()), "&&".to_string()]);
+    }
+    for (key, value) in cmd.get_envs() {
+        if let Some(value) = value {
+            words.push(format!("{}={}", key.to_string_lossy(), quote(value)));
+        }
+    }
+    words.push(quote(cmd.get_program()));
+    words.extend(cmd.get_args().map(quote));
+    words.join(" ")
+}
+
+/// Returns a command running the package's cached test binary, building it
+/// first if the sources changed since, and whether it prints `-json` events.
+/// Runs the binary cannot stand in for (several packages, fuzzing,
+/// cross-compiling, gotestsum) and packages that fail to build are left to
+/// `go test`, which reports the failure.
+fn cached_binary_command(
+    invocation: &Invocation,
+    options: &RunOptions,
+) -> Result<Option<(Command, bool)>> {
+    let (Some(package_dir), Some(import_path)) = (&invocation.package_dir, &invocation.import_path)
+    else {
+        return Ok(None);
+    };
+    if invocation.fuzz.is_some()
+        || options.goos.is_some()
+        || options.goarch.is_some()
+        || options.runner == Runner::Gotestsum
+    {
+        return Ok(None);
+    }
+    let Some(cache) = bincache::BinaryCache::open() else {
+        return Ok(None);
+    };
+
+    let mut build_flags = Vec::new();
+    if options.race {
+        build_flags.push("-race".to_string());
+    }
+    if let Some(tags) = &options.tags {
+        build_flags.push(format!("-tags={}", tags));
+    }
+    build_flags.extend(options.go_test_args.iter().cloned());
+
+    let mut settings = vec![options.go_bin.to_string_lossy().to_string()];
+    settings.extend(build_flags.iter().cloned());
+    settings.extend(
+        options
+            .env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value)),
+    );
+    let Some(binary) = cache.binary(package_dir, &settings, invocation.workspace_off) else {
+        return Ok(None);
+    };
+
+    if !binary.exists() {
+        cache.prepare()?;
+        outln!("Building test binary for {}", invocation.package);
+        let mut build = Command::new(&options.go_bin);
+        build
+            .args(["test", "-c", "-o"])
+            .arg(&binary)
+            .args(&build_flags)
+            .arg(&invocation.package)
+            .envs(options.env.iter().map(|(key, value)| (key, value)))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(dir) = &invocation.dir {
+            build.current_dir(dir);
+        }
+        if invocation.workspace_off {
+            build.env("GOWORK", "off");
+        }
+        // A package without test files builds no binary.
+        if !build.status()?.success() || !binary.exists() {
+            return Ok(None);
+        }
+        cache.prune(&binary);
+    }
+
+    // test2json turns the binary's output into the events `go test -json`
+    // prints, for the results to be rendered and recorded the same way.
+    let json = !options.raw;
+    let mut cmd = if json {
+        let mut cmd = Command::new(&options.go_bin);
+        cmd.args(["tool", "test2json", "-t", "-p", import_path])
+            .arg(&binary)
+            .arg("-test.v=test2json");
+        cmd
+    } else {
+        let mut cmd = Command::new(&binary);
+        if options.verbose {
+            cmd.arg("-test.v");
+        }
+        cmd
+    };
+    // Flags of `go test` are spelled `-test.*` by the binary itself.
+    if options.count > 1 {
+        cmd.arg(format!("-test.count={}", options.count));
+    }
+    if options.short {
+        cmd.arg("-test.short");
+    }
+    if options.failfast {
+        cmd.arg("-test.failfast");
+    }
+    if let Some(shuffle) = &options.shuffle {
+        cmd.arg(format!("-test.shuffle={}", shuffle));
+    }
+    if let Some(parallel) = options.parallel {
+        cmd.arg(format!("-test.parallel={}", parallel));
+    }
+    // go test applies a 10 minute timeout by default.
+    let timeout = options.timeout.as_deref().unwrap_or("10m");
+    cmd.arg(format!("-test.timeout={}", timeout));
+
+    if !invocation.run_patterns.is_empty() {
+        cmd.arg("-test.run")
+            .arg(build_run_pattern(&invocation.run_patterns));
+    } else if !invocation.bench_patterns.is_empty() {
+        cmd.arg("-test.run").arg("^$");
+    }
+    if !invocation.bench_patterns.is_empty() {
+        cmd.arg("-test.bench")
+            .arg(build_run_pattern(&invocation.bench_patterns));
+        if let Some(benchtime) = &options.benchtime {
+            cmd.arg(format!("-test.benchtime={}", benchtime));
+        }
+        if options.benchmem {
+            cmd.arg("-test.benchmem");
+        }
+    }
+    cmd.args(&invocation.test_args);
+
+    // go test runs test binaries from the package directory.
+    cmd.current_dir(package_dir);
+    cmd.envs(options.env.iter().map(|(key, value)| (key, value)));
+
+    Ok(Some((cmd, json)))
+}
+
+/// Runs a test command, rendering its events when it prints `-json` output.
+/// With a `prefix`, every line it prints starts with it.
+fn run_command(
+    mut cmd: Command,
+    options: &RunOptions,
+    json: bool,
+    prefix: Option<&str>,
+) -> Result<RunOutcome> {
+    let command = cmd
+        .get_args()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let command = match cmd.get_current_dir() {
+        Some(dir) => format!("{} {} (in {})", program, command, dir.display()),
+        None => format!("{} {}", program, command),
+    };
+    testjson::print_prefixed(prefix, &format!("Running: {}\n", command));
+
+    let started = Instant::now();
+    if !json && options.shuffle.is_none() && prefix.is_none() && !logfile::intercepts_output() {
+        let status = cmd.status()?;
+        return Ok(RunOutcome {
+            command,
+            status,
+            elapsed: started.elapsed(),
+            shuffle_seeds: Vec::new(),
+            results: Vec::new(),
+        });
+    }
+
+    // The shuffle seed is only printed, so read the output as it passes.
+    cmd.stdout(Stdio::piped());
+    if prefix.is_some() || logfile::intercepts_output() {
+        cmd.stderr(Stdio::piped());
+    }
+    let mut child = cmd.spawn()?;
+    let stderr = child.stderr.take().map(|stderr| {
+        let prefix = prefix.unwrap_or_default().to_string();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                errln!("{}{}", prefix, line);
+            }
+        })
+    });
+    let mut scanner = SeedScanner::default();
+    let mut renderer = testjson::Renderer::new(options.verbose, prefix.map(str::to_string));
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            match testjson::parse_event(&line).filter(|_| json) {
+                Some(event) => {
+                    scanner.observe(&event);
+                    renderer.handle(&event);
+                }
+                None => {
+                    testjson::print_prefixed(prefix, &format!("{}\n", line));
+                    scanner.scan(&line);
+                }
+            }
+        }
+    }
+    if let Some(stderr) = stderr {
+        let _ = stderr.join();
+    }
+
+    let status = child.wait()?;
+    Ok(RunOutcome {
+        command,
+        status,
+        elapsed: started.elapsed(),
+        shuffle_seeds: scanner.seeds,
+        results: renderer.into_results(),
+    })
+}
@@ -0,0 +1,643 @@
+//! A small Go syntax parser used for test discovery.
+//!
+//! This is not a complete Go parser. It tokenizes a source file (dropping
+//! comments and decoding string literals) and recovers the package clause,
+//! imports and top-level function declarations, which is all discovery needs
+//! to stay accurate in the presence of comments, string literals and
+//! signatures that span several lines.
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    String,
+    Char,
+    Number,
+    Punct,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Identifier or operator text; for string literals, the decoded value.
+    pub text: String,
+    pub line: usize,
+}
+
+impl Token {
+    pub fn is_ident(&self, name: &str) -> bool {
+        self.kind == TokenKind::Ident && self.text == name
+    }
+
+    pub fn is_punct(&self, punct: &str) -> bool {
+        self.kind == TokenKind::Punct && self.text == punct
+    }
+
+    pub fn is_string(&self) -> bool {
+        self.kind == TokenKind::String
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub name: Option<String>,
+    pub path: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: Option<String>,
+    pub ty: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FuncDecl {
+    pub name: String,
+    pub line: usize,
+    pub receiver: Option<Param>,
+    pub params: Vec<Param>,
+    /// Token indices strictly inside the body braces.
+    pub body: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GoFile {
+    pub package: String,
+    pub imports: Vec<Import>,
+    pub tokens: Vec<Token>,
+    pub funcs: Vec<FuncDecl>,
+}
+
+impl GoFile {
+    /// Reports whether `name` refers to `ident` from the package at `path`,
+    /// taking import aliases and dot imports into account.
+    pub fn is_qualified(&self, name: &str, path: &str, ident: &str) -> bool {
+        self.imports
+            .iter()
+            .filter(|import| import.path == path)
+            .any(|import| match import.name.as_deref() {
+                Some("_") => false,
+                Some(".") => name == ident,
+                Some(alias) => name.strip_prefix(alias) == Some(&format!(".{}", ident)),
+                None => {
+                    name.strip_prefix(default_package_name(path)) == Some(&format!(".{}", ident))
+                }
+            })
+    }
+
+    pub fn body_tokens(&self, func: &FuncDecl) -> &[Token] {
+        func.body.clone().map_or(&[][..], |body| &self.tokens[body])
+    }
+}
+
+/// Guesses the package name of an unaliased import from its path, dropping
+/// major version suffixes such as `/v2` and `gopkg.in`'s `.v1`.
+fn default_package_name(path: &str) -> &str {
+    let is_version = |s: &str| {
+        s.strip_prefix('v')
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    };
+
+    let mut segments = path.rsplit('/');
+    let mut name = segments.next().unwrap_or(path);
+    if is_version(name) {
+        name = segments.next().unwrap_or(name);
+    }
+    match name.rsplit_once('.') {
+        Some((base, version)) if is_version(version) => base,
+        _ => name,
+    }
+}
+
+pub fn parse(source: &str) -> GoFile {
+    let tokens = tokenize(source);
+    let mut file = GoFile::default();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+
+        if depth == 0 && token.kind == TokenKind::Ident {
+            match token.text.as_str() {
+                "package" if file.package.is_empty() => {
+                    if let Some(name) = tokens.get(i + 1).filter(|t| t.kind == TokenKind::Ident) {
+                        file.package = name.text.clone();
+                        i += 2;
+                        continue;
+                    }
+                }
+                "import" => {
+                    i = parse_imports(&tokens, i + 1, &mut file.imports);
+                    continue;
+                }
+                "func" if i == 0 || tokens[i - 1].line < token.line => {
+                    if let Some((func, next)) = parse_func(&tokens, i) {
+                        file.funcs.push(func);
+                        i = next;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match token.text.as_str() {
+            "(" | "[" | "{" if token.kind == TokenKind::Punct => depth += 1,
+            ")" | "]" | "}" if token.kind == TokenKind::Punct => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    file.tokens = tokens;
+    file
+}
+
+fn parse_imports(tokens: &[Token], start: usize, imports: &mut Vec<Import>) -> usize {
+    let Some(first) = tokens.get(start) else {
+        return start;
+    };
+
+    if first.is_punct("(") {
+        let Some(close) = matching(tokens, start) else {
+            return tokens.len();
+        };
+        let mut i = start + 1;
+        while i < close {
+            i = parse_import_spec(tokens, i, imports);
+        }
+        close + 1
+    } else {
+        parse_import_spec(tokens, start, imports)
+    }
+}
+
+fn parse_import_spec(tokens: &[Token], start: usize, imports: &mut Vec<Import>) -> usize {
+    let token = &tokens[start];
+    if token.is_string() {
+        imports.push(Import {
+            name: None,
+            path: token.text.clone(),
+        });
+        return start + 1;
+    }
+
+    let is_name = token.kind == TokenKind::Ident || token.is_punct(".");
+    match tokens.get(start + 1) {
+        Some(path) if is_name && path.is_string() => {
+            imports.push(Import {
+                name: Some(token.text.clone()),
+                path: path.text.clone(),
+            });
+            start + 2
+        }
+        _ => start + 1,
+    }
+}
+
+fn parse_func(tokens: &[Token], start: usize) -> Option<(FuncDecl, usize)> {
+    let mut i = start + 1;
+
+    let receiver = if tokens.get(i)?.is_punct("(") {
+        let close = matching(tokens, i)?;
+        let receiver = parse_params(&tokens[i + 1..close]).into_iter().next();
+        i = close + 1;
+        receiver
+    } else {
+        None
+    };
+
+    let name = tokens.get(i).filter(|t| t.kind == TokenKind::Ident)?;
+    i += 1;
+
+    if tokens.get(i)?.is_punct("[") {
+        i = matching(tokens, i)? + 1;
+    }
+
+    if !tokens.get(i)?.is_punct("(") {
+        return None;
+    }
+    let close = matching(tokens, i)?;
+    let params = parse_params(&tokens[i + 1..close]);
+    i = close + 1;
+
+    // Skip over the result list until the opening brace of the body. A
+    // declaration without a body (implemented in assembly) ends at the line
+    // break, since Go does not allow the brace on its own line.
+    let mut body = None;
+    while let Some(token) = tokens.get(i) {
+        if token.line > tokens[i - 1].line {
+            break;
+        }
+        if token.is_punct("(") || token.is_punct("[") {
+            i = matching(tokens, i)? + 1;
+        } else if token.is_punct("{") {
+            let close = matching(tokens, i)?;
+            let prev = &tokens[i - 1];
+            if prev.is_ident("struct") || prev.is_ident("interface") {
+                i = close + 1;
+                continue;
+            }
+            body = Some(i + 1..close);
+            i = close + 1;
+            break;
+        } else {
+            i += 1;
+        }
+    }
+
+    Some((
+        FuncDecl {
+            name: name.text.clone(),
+            line: tokens[start].line,
+            receiver,
+            params,
+            body,
+        },
+        i,
+    ))
+}
+
+fn parse_params(tokens: &[Token]) -> Vec<Param> {
+    let mut groups: Vec<&[Token]> = Vec::new();
+    let mut depth = 0usize;
+    let mut group_start = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Punct {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            "," if depth == 0 => {
+                groups.push(&tokens[group_start..i]);
+                group_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    groups.push(&tokens[group_start..]);
+    groups.retain(|group| !group.is_empty());
+
+    let named = groups.iter().any(|group| has_name(group));
+    let mut params: Vec<Param> = groups
+        .iter()
+        .map(|group| {
+            if named && group[0].kind == TokenKind::Ident {
+                Param {
+                    name: Some(group[0].text.clone()),
+                    ty: type_string(&group[1..]),
+                }
+            } else {
+                Param {
+                    name: None,
+                    ty: type_string(group),
+                }
+            }
+        })
+        .collect();
+
+    // In `a, b int` only the last name carries the type.
+    let mut ty = String::new();
+    for param in params.iter_mut().rev() {
+        if param.ty.is_empty() {
+            param.ty = ty.clone();
+        } else {
+            ty = param.ty.clone();
+        }
+    }
+
+    params
+}
+
+fn has_name(group: &[Token]) -> bool {
+    match group {
+        [name, next, rest @ ..] if name.kind == TokenKind::Ident => {
+            if next.is_punct(".") {
+                false
+            } else if next.is_punct("[") {
+                rest.first().is_some_and(|t| t.is_punct("]"))
+            } else {
+                true
+            }
+        }
+        _ => false,
+    }
+}
+
+fn type_string(tokens: &[Token]) -> String {
+    let mut ty = String::new();
+    let mut prev_word = false;
+
+    for token in tokens {
+        let word = matches!(token.kind, TokenKind::Ident | TokenKind::Number);
+        if word && prev_word {
+            ty.push(' ');
+        }
+        ty.push_str(&token.text);
+        prev_word = word;
+    }
+
+    ty
+}
+
+/// Returns the index of the bracket closing the one at `open`, or `None` if
+/// the tokens end first, as in a file being written.
+pub fn matching(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.kind != TokenKind::Punct {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "&^=", "...", "&&", "||", "<-", "++", "--", "==", "!=", "<=", ">=", ":=", "<<",
+    ">>", "&^", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start_line = line;
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident,
+                text: chars[start..i].iter().collect(),
+                line: start_line,
+            });
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                let c = chars[i];
+                let prev = chars[i - 1];
+                let hex = chars[start..i].iter().any(|c| matches!(c, 'x' | 'X'));
+                let exponent_sign = (c == '+' || c == '-')
+                    && (matches!(prev, 'p' | 'P') || (!hex && matches!(prev, 'e' | 'E')));
+                if c.is_alphanumeric() || c == '_' || c == '.' || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token {
+                kind: TokenKind::Number,
+                text: chars[start..i].iter().collect(),
+                line: start_line,
+            });
+        } else if c == '"' {
+            let (text, end) = interpreted_string(&chars, i + 1, '"');
+            i = end;
+            tokens.push(Token {
+                kind: TokenKind::String,
+                text,
+                line: start_line,
+            });
+        } else if c == '\'' {
+            let (text, end) = interpreted_string(&chars, i + 1, '\'');
+            i = end;
+            tokens.push(Token {
+                kind: TokenKind::Char,
+                text,
+                line: start_line,
+            });
+        } else if c == '`' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '`' {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::String,
+                text: chars[start..i.min(chars.len())]
+                    .iter()
+                    .filter(|&&c| c != '\r')
+                    .collect(),
+                line: start_line,
+            });
+            i += 1;
+        } else {
+            let op = OPERATORS.iter().find(|op| {
+                op.chars()
+                    .enumerate()
+                    .all(|(j, o)| chars.get(i + j) == Some(&o))
+            });
+            let text = op.map_or_else(|| c.to_string(), |op| op.to_string());
+            i += text.chars().count();
+            tokens.push(Token {
+                kind: TokenKind::Punct,
+                text,
+                line: start_line,
+            });
+        }
+    }
+
+    tokens
+}
+
+/// Decodes an interpreted string or rune literal starting just after the
+/// opening quote. Returns the value and the index just past the closing
+/// quote. An unterminated literal ends at the line break.
+fn interpreted_string(chars: &[char], start: usize, quote: char) -> (String, usize) {
+    let mut value = String::new();
+    let mut i = start;
+
+    while i < chars.len() && chars[i] != quote && chars[i] != '\n' {
+        if chars[i] != '\\' {
+            value.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let Some(&escape) = chars.get(i + 1) else {
+            i += 1;
+            break;
+        };
+        i += 2;
+        match escape {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            'a' => value.push('\x07'),
+            'b' => value.push('\x08'),
+            'f' => value.push('\x0c'),
+            'v' => value.push('\x0b'),
+            'x' | 'u' | 'U' => {
+                let len = match escape {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let digits: String = chars[i..(i + len).min(chars.len())].iter().collect();
+                i += digits.chars().count();
+                if let Some(c) = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    value.push(c);
+                }
+            }
+            '0'..='7' => {
+                let digits: String = chars[i - 1..(i + 2).min(chars.len())].iter().collect();
+                i += digits.chars().count() - 1;
+                if let Some(c) = u32::from_str_radix(&digits, 8)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    value.push(c);
+                }
+            }
+            other => value.push(other),
+        }
+    }
+
+    if chars.get(i) == Some(&quote) {
+        i += 1;
+    }
+
+    (value, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn func_names(source: &str) -> Vec<String> {
+        parse(source)
+            .funcs
+            .into_iter()
+            .map(|func| func.name)
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_strings_and_skips_comments() {
+        let tokens = tokenize("x := \"a\\tb\" // c\n/* d\n*/ `e\nf`");
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(texts, ["x", ":=", "a\tb", "e\nf"]);
+        assert_eq!(tokens[3].kind, TokenKind::String);
+        assert_eq!(tokens[3].line, 3);
+    }
+
+    #[test]
+    fn parses_package_imports_and_funcs() {
+        let file = parse(
+            "package foo\n\nimport (\n\t\"testing\"\n\tt2 \"example.com/x/v2\"\n)\n\n\
+             func (s *Suite) TestM() {}\n\nfunc TestA(t *testing.T) {\n\tt.Run(\"x\", nil)\n}\n",
+        );
+        assert_eq!(file.package, "foo");
+        assert_eq!(file.imports.len(), 2);
+        assert_eq!(file.imports[1].name.as_deref(), Some("t2"));
+        assert_eq!(file.funcs.len(), 2);
+
+        let method = &file.funcs[0];
+        assert_eq!(method.name, "TestM");
+        assert_eq!(
+            method.receiver.as_ref().map(|r| r.ty.as_str()),
+            Some("*Suite")
+        );
+
+        let test = &file.funcs[1];
+        assert_eq!((test.name.as_str(), test.line), ("TestA", 10));
+        assert_eq!(test.params[0].ty, "*testing.T");
+        assert_eq!(file.body_tokens(test).len(), 8);
+    }
+
+    #[test]
+    fn qualifies_aliased_and_default_imports() {
+        let file =
+            parse("package p\nimport (\n\"testing\"\ns \"github.com/stretchr/testify/suite\"\n)\n");
+        assert!(file.is_qualified("testing.T", "testing", "T"));
+        assert!(file.is_qualified("s.Run", "github.com/stretchr/testify/suite", "Run"));
+        assert!(!file.is_qualified("suite.Run", "github.com/stretchr/testify/suite", "Run"));
+        assert_eq!(default_package_name("gopkg.in/check.v1"), "check");
+        assert_eq!(default_package_name("example.com/mod/v3"), "mod");
+    }
+
+    #[test]
+    fn matching_finds_closing_bracket() {
+        let tokens = tokenize("f(a, [b], {c})");
+        assert_eq!(matching(&tokens, 1), Some(tokens.len() - 1));
+        assert_eq!(matching(&tokenize("f(a, [b"), 1), None);
+    }
+
+    #[test]
+    fn truncated_declarations_are_skipped() {
+        assert!(func_names("package x\n\nfunc TestA(").is_empty());
+        assert!(func_names("package x\n\nfunc (").is_empty());
+        assert!(func_names("package x\n\nfunc (s *S").is_empty());
+        assert!(func_names("package x\n\nfunc TestA[T any").is_empty());
+        assert!(func_names("package x\n\nfunc TestA(t *testing.T) {").is_empty());
+        assert!(func_names("package x\n\nimport (\n\"testing\"").is_empty());
+        assert_eq!(
+            func_names("package x\n\nfunc TestA(t *testing.T) {}\n\nfunc TestB(t *testing.T) {\n"),
+            ["TestA"]
+        );
+    }
+
+    #[test]
+    fn every_prefix_of_a_file_parses() {
+        let source = "package x\n\nimport \"testing\"\n\n\
+                      func (s *S) TestM() { s.Run(\"a\", func() {}) }\n\n\
+                      func TestA[T any](t *testing.T) (ok bool) {\n\
+                      \tfor _, tt := range []struct{ name string }{{\"b\"}} {\n\
+                      \t\tt.Run(tt.name, func(t *testing.T) {})\n\t}\n\treturn\n}\n";
+        for end in (0..=source.len()).filter(|&end| source.is_char_boundary(end)) {
+            let file = parse(&source[..end]);
+            for func in &file.funcs {
+                file.body_tokens(func);
+            }
+        }
+    }
+}
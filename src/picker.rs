@@ -0,0 +1,1193 @@
+//! The built-in selector, for `--ui builtin`.
+//!
+//! A small fuzzy finder drawn directly on the terminal, for terminals where
+//! skim misbehaves. The query's space-separated terms each have to match an
+//! entry as a subsequence, by default case-insensitively unless they
+//! contain capitals, and entries are listed best match first. It takes
+//! over the terminal's alternate screen while open and restores the
+//! terminal when done. Inline, it draws on as many lines of the screen
+//! itself as its height makes, beneath the cursor, and clears them when
+//! done; asked to keep what it drew, it draws on the screen itself, too,
+//! and leaves the lines as they are.
+//!
+//! Entries may be split into tab-separated fields, lined up at tab stops
+//! every 8 columns, and the query matches only the fields the picker names.
+//!
+//! Entries with a parent, such as subtests, make a tree: without a query,
+//! only the top-level entries are listed, and each one can be expanded to
+//! list its children beneath it.
+//!
+//! Entries can keep coming while the picker is open, such as tests still
+//! being discovered; they are listed as they arrive.
+
+use crate::{Case, Layout};
+use anyhow::Result;
+use std::borrow::Cow;
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+/// Actions of bindings that select every listed entry, deselect them all
+/// or toggle each, which pickers perform themselves rather than accept the
+/// selection with.
+pub const SELECT_ALL_ACTION: &str = "select-all";
+pub const DESELECT_ALL_ACTION: &str = "deselect-all";
+pub const TOGGLE_ALL_ACTION: &str = "toggle-all";
+pub const SELECTION_ACTIONS: &[&str] = &[SELECT_ALL_ACTION, DESELECT_ALL_ACTION, TOGGLE_ALL_ACTION];
+
+/// What comes in while the picker is open.
+pub enum Incoming {
+    /// Entries to list after the others.
+    Entries(Vec<Entry>),
+    /// A header to show in place of the picker's, with as many lines.
+    Header(String),
+}
+
+/// An entry to pick, with what the preview pane shows for it.
+#[derive(Clone)]
+pub struct Entry {
+    pub text: String,
+    pub preview: Option<String>,
+    /// Index of the entry this one is listed under in the tree.
+    pub parent: Option<usize>,
+}
+
+pub struct Picker<'a> {
+    pub prompt: &'a str,
+    /// Shown dimmed, a row for each line.
+    pub header: &'a str,
+    pub multi: bool,
+    /// Initial query.
+    pub query: &'a str,
+    /// Entries selected from the start.
+    pub selected: &'a [usize],
+    /// The entry the cursor starts on, when it is listed.
+    pub cursor: Option<usize>,
+    /// Match the query's terms as substrings rather than fuzzily.
+    pub exact: bool,
+    pub case: Case,
+    /// The tab-separated fields of the entries the query matches, by number
+    /// from 1, or from -1 for the last; all of an entry when empty.
+    pub nth: &'a [isize],
+    /// Keys, such as `alt-r`, that accept the selection with an action.
+    pub bindings: &'a [(String, String)],
+    pub layout: Layout,
+    /// Leave the picker on the terminal as it was last drawn when done,
+    /// rather than restoring the screen it covered.
+    pub keep: bool,
+    /// Draw beneath the cursor, `height` rows tall, rather than on the
+    /// alternate screen.
+    pub inline: bool,
+    /// Rows, or a percentage of the screen's, the picker takes inline and
+    /// in fzf; otherwise the built-in selector takes the whole screen.
+    pub height: &'a str,
+    pub min_height: usize,
+    /// The theme's color option; only `bw`, no colors, changes anything.
+    pub color: Option<String>,
+}
+
+/// What a picker was left with.
+pub struct Picked {
+    /// Indices of the picked entries, in entry order; empty when the picker
+    /// was cancelled.
+    pub indices: Vec<usize>,
+    /// The action of the binding that accepted them.
+    pub action: Option<String>,
+    /// The query when the picker was closed.
+    pub query: String,
+}
+
+/// How long the picker waits for a key before listing the entries that
+/// came in meanwhile.
+#[cfg(unix)]
+const INCOMING_POLL_MS: i32 = 50;
+
+impl Picker<'_> {
+    /// Shows the entries and returns the picked ones.
+    pub fn pick(&self, entries: &[Entry]) -> Result<Picked> {
+        self.pick_from(Cow::Borrowed(entries), None)
+    }
+
+    /// [`Picker::pick`] while more entries are on their way: those
+    /// `incoming` hands over are listed as they arrive, until it is closed,
+    /// and the header is updated as it says. Their parents are indices
+    /// among all entries, the earlier ones included.
+    pub fn pick_streamed(
+        &self,
+        entries: Vec<Entry>,
+        incoming: Receiver<Incoming>,
+    ) -> Result<Picked> {
+        self.pick_from(Cow::Owned(entries), Some(incoming))
+    }
+
+    fn pick_from(
+        &self,
+        mut entries: Cow<[Entry]>,
+        mut incoming: Option<Receiver<Incoming>>,
+    ) -> Result<Picked> {
+        #[cfg(unix)]
+        {
+            let mut terminal = if self.inline {
+                terminal::RawTerminal::open_inline(|rows| self.inline_rows(rows), self.keep)?
+            } else if self.keep {
+                terminal::RawTerminal::open_inline(|rows| rows, true)?
+            } else {
+                terminal::RawTerminal::open()?
+            };
+            let mut state = State::new(&entries, self);
+            state.scanning = incoming.is_some();
+            loop {
+                if let Some(receiver) = &incoming {
+                    loop {
+                        match receiver.try_recv() {
+                            Ok(Incoming::Entries(batch)) => {
+                                state.append(&batch, self);
+                                entries.to_mut().extend(batch);
+                            }
+                            Ok(Incoming::Header(header)) => state.header = Some(header),
+                            Err(TryRecvError::Empty) => break,
+                            Err(TryRecvError::Disconnected) => {
+                                state.scanning = false;
+                                break;
+                            }
+                        }
+                    }
+                    if !state.scanning {
+                        incoming = None;
+                    }
+                }
+                let (top, rows, cols) = terminal.region();
+                terminal.write(&self.render(&state, &entries, top, rows, cols))?;
+
+                let key = if incoming.is_some() {
+                    match terminal.read_key_timeout(INCOMING_POLL_MS)? {
+                        Some(key) => key,
+                        None => continue,
+                    }
+                } else {
+                    terminal.read_key()?
+                };
+                if let Some((_, action)) = key
+                    .name()
+                    .and_then(|name| self.bindings.iter().find(|(key, _)| *key == name))
+                {
+                    match action.as_str() {
+                        SELECT_ALL_ACTION if self.multi => state.select_all(),
+                        DESELECT_ALL_ACTION => state.selected.clear(),
+                        TOGGLE_ALL_ACTION if self.multi => state.toggle_all(),
+                        _ if SELECTION_ACTIONS.contains(&action.as_str()) => {}
+                        _ => return Ok(state.picked(Some(action.clone()))),
+                    }
+                    continue;
+                }
+                match key {
+                    Key::Enter => return Ok(state.picked(None)),
+                    Key::Esc | Key::Ctrl('c' | 'g' | 'q') => {
+                        return Ok(Picked {
+                            indices: Vec::new(),
+                            action: None,
+                            query: state.query,
+                        });
+                    }
+                    // Up moves towards the end of the list when it grows upwards.
+                    Key::Up | Key::Ctrl('k' | 'p') => state.move_cursor(-self.direction()),
+                    Key::Down | Key::Ctrl('j' | 'n') => state.move_cursor(self.direction()),
+                    Key::PageUp => state
+                        .move_cursor(-self.direction() * self.list_height(rows, &state) as isize),
+                    Key::PageDown => state
+                        .move_cursor(self.direction() * self.list_height(rows, &state) as isize),
+                    Key::Tab if self.multi => {
+                        state.toggle();
+                        state.move_cursor(1);
+                    }
+                    Key::BackTab if self.multi => {
+                        state.toggle();
+                        state.move_cursor(-1);
+                    }
+                    Key::Right => state.expand(),
+                    Key::Left => state.collapse(),
+                    Key::Ctrl('t') if self.multi => state.toggle_subtree(),
+                    Key::Backspace => {
+                        state.query.pop();
+                        state.update();
+                    }
+                    Key::Ctrl('u') => {
+                        state.query.clear();
+                        state.update();
+                    }
+                    Key::Ctrl('w') => {
+                        let trimmed = state.query.trim_end().len();
+                        let word = state.query[..trimmed]
+                            .rfind(' ')
+                            .map_or(0, |space| space + 1);
+                        state.query.truncate(word);
+                        state.update();
+                    }
+                    Key::Char(c) => {
+                        state.query.push(c);
+                        state.update();
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = (entries, incoming);
+            anyhow::bail!("The built-in selector needs a Unix terminal; use --ui skim")
+        }
+    }
+
+    /// Rows drawn inline on a screen of `rows`: `height`, at least
+    /// `min_height` when it is a percentage, and room for one entry.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn inline_rows(&self, rows: usize) -> usize {
+        let height = match self.height.strip_suffix('%') {
+            Some(percent) => {
+                (rows * percent.parse::<usize>().unwrap_or(100) / 100).max(self.min_height)
+            }
+            None => self.height.parse().unwrap_or(rows),
+        };
+        height.max(3 + header_lines(self.header)).min(rows)
+    }
+
+    /// The header shown: the last one that came in, or the picker's.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn header<'s>(&'s self, state: &'s State) -> &'s str {
+        state.header.as_deref().unwrap_or(self.header)
+    }
+
+    /// Rows left for entries beside the prompt, counter and header.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn list_height(&self, rows: usize, state: &State) -> usize {
+        rows.saturating_sub(2 + header_lines(self.header(state)))
+            .max(1)
+    }
+
+    /// How far down the screen moving down the list goes.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn direction(&self) -> isize {
+        if self.layout == Layout::Default {
+            -1
+        } else {
+            1
+        }
+    }
+
+    /// Draws the prompt, a counter, the header and the visible entries, with
+    /// the highlighted entry's preview beside them when entries have one,
+    /// on `rows` lines from the screen's line `top`. The prompt is at the
+    /// top in the reverse layout and at the bottom otherwise, where the
+    /// default layout lists the best match last.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn render(
+        &self,
+        state: &State,
+        entries: &[Entry],
+        top: usize,
+        rows: usize,
+        cols: usize,
+    ) -> String {
+        let preview = entries.iter().any(|entry| entry.preview.is_some());
+        let list_width = if preview { cols / 2 } else { cols };
+        let header = self.header(state);
+        let height = self.list_height(rows, state);
+        let header_lines = header_lines(header);
+        let rows = height + 2 + header_lines;
+        let (prompt_row, counter_row, header_top, list_top) = match self.layout {
+            Layout::Reverse => (1, 2, 3, 3 + header_lines),
+            Layout::Default | Layout::ReverseList => (rows, rows - 1, rows - 1 - header_lines, 1),
+        };
+        let list_row = |row: usize| match self.layout {
+            Layout::Default => list_top + height - 1 - row,
+            Layout::Reverse | Layout::ReverseList => list_top + row,
+        };
+
+        // Rows from here on count from 1 at `top`.
+        let at = |row: usize| top + row - 1;
+        let mut screen = String::from("\x1b[?25l");
+        let mut line = |row: usize, text: &str| {
+            screen.push_str(&format!("\x1b[{};1H{}\x1b[K", at(row), text));
+        };
+        line(prompt_row, &format!("{}{}", self.prompt, state.query));
+        let mut notes = Vec::new();
+        if !state.selected.is_empty() {
+            notes.push(format!("{} selected", state.selected.len()));
+        }
+        if state.scanning {
+            notes.push("scanning".to_string());
+        }
+        let mut counter = format!("  {}/{}", state.matches.len(), entries.len());
+        if !notes.is_empty() {
+            counter.push_str(&format!(" ({})", notes.join(", ")));
+        }
+        line(counter_row, &format!("\x1b[2m{}\x1b[0m", counter));
+        for (row, header) in header.lines().enumerate() {
+            line(
+                header_top + row,
+                &format!("\x1b[2m{}\x1b[0m", truncate(header, cols)),
+            );
+        }
+
+        let offset = state.offset(height);
+        for row in 0..height {
+            let text = match state.matches.get(offset + row) {
+                Some((index, positions)) => {
+                    let cursor = if offset + row == state.cursor {
+                        '>'
+                    } else {
+                        ' '
+                    };
+                    let mark = if state.selected.contains(index) {
+                        '+'
+                    } else {
+                        ' '
+                    };
+                    let tree = if state.is_tree() {
+                        let node = match state.has_children(*index) {
+                            true if state.expanded.contains(index) => "▾ ",
+                            true => "▸ ",
+                            false => "  ",
+                        };
+                        format!("{}{}", "  ".repeat(state.depth(*index)), node)
+                    } else {
+                        String::new()
+                    };
+                    let text = highlight(
+                        &format!("{}{}", tree, expand_tabs(&entries[*index].text)),
+                        positions,
+                        list_width.saturating_sub(2),
+                        crate::theme::is_plain(self.color.as_deref()),
+                    );
+                    if offset + row == state.cursor {
+                        format!("\x1b[1m{}{}{}\x1b[0m", cursor, mark, text)
+                    } else {
+                        format!("{}{}{}", cursor, mark, text)
+                    }
+                }
+                None => String::new(),
+            };
+            line(list_row(row), &text);
+        }
+
+        if preview {
+            let text = state
+                .matches
+                .get(state.cursor)
+                .and_then(|(index, _)| entries[*index].preview.as_deref())
+                .unwrap_or_default();
+            let mut lines = text.lines();
+            for row in 0..height {
+                let text = highlight(
+                    lines.next().unwrap_or_default(),
+                    &[],
+                    cols.saturating_sub(list_width + 2),
+                    false,
+                );
+                screen.push_str(&format!(
+                    "\x1b[{};{}H\x1b[2m│\x1b[0m {}",
+                    at(list_top + row),
+                    list_width + 1,
+                    text
+                ));
+            }
+        }
+
+        let column = self.prompt.chars().count() + state.query.chars().count() + 1;
+        screen.push_str(&format!("\x1b[{};{}H\x1b[?25h", at(prompt_row), column));
+        screen
+    }
+}
+
+/// The query and what it matched.
+struct State {
+    /// The entries' text without colors and with tabs expanded, which the
+    /// query matches, with NUL in place of the characters of fields it does
+    /// not.
+    texts: Vec<String>,
+    parents: Vec<Option<usize>>,
+    /// Entries listing their children.
+    expanded: Vec<usize>,
+    query: String,
+    /// Matching entries, best first, with the positions of the matched
+    /// characters.
+    matches: Vec<(usize, Vec<usize>)>,
+    cursor: usize,
+    /// Selected entries, by index.
+    selected: Vec<usize>,
+    /// Terms only match consecutive characters.
+    exact: bool,
+    case: Case,
+    /// More entries are on their way.
+    scanning: bool,
+    /// The header that came in last, shown in place of the picker's.
+    header: Option<String>,
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+impl State {
+    fn new(entries: &[Entry], picker: &Picker) -> State {
+        let mut state = State {
+            texts: entries
+                .iter()
+                .map(|entry| searched_text(&crate::logfile::strip_ansi(&entry.text), picker.nth))
+                .collect(),
+            parents: entries.iter().map(|entry| entry.parent).collect(),
+            expanded: Vec::new(),
+            query: picker.query.to_string(),
+            matches: Vec::new(),
+            cursor: 0,
+            selected: picker.selected.to_vec(),
+            exact: picker.exact,
+            case: picker.case,
+            scanning: false,
+            header: None,
+        };
+        // Selected entries deep in the tree are listed, too, and so is the
+        // one under the cursor.
+        for &index in picker.selected.iter().chain(&picker.cursor) {
+            let ancestors: Vec<usize> = state.ancestors(index).collect();
+            for ancestor in ancestors {
+                if !state.expanded.contains(&ancestor) {
+                    state.expanded.push(ancestor);
+                }
+            }
+        }
+        state.update();
+        if let Some(cursor) = picker.cursor
+            && let Some(position) = state.matches.iter().position(|(index, _)| *index == cursor)
+        {
+            state.cursor = position;
+        }
+        state
+    }
+
+    /// Whether entries are shown as a tree, which they are until there is
+    /// a query to rank them by.
+    fn is_tree(&self) -> bool {
+        self.query.trim().is_empty() && self.parents.iter().any(Option::is_some)
+    }
+
+    fn update(&mut self) {
+        if self.is_tree() {
+            self.matches = (0..self.texts.len())
+                .filter(|&index| self.ancestors(index).all(|a| self.expanded.contains(&a)))
+                .map(|index| (index, Vec::new()))
+                .collect();
+            self.cursor = 0;
+            return;
+        }
+
+        let terms: Vec<&str> = self.query.split_whitespace().collect();
+        let mut scored: Vec<_> = self
+            .texts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, text)| {
+                let (score, positions) = score(text, &terms, self.exact, self.case)?;
+                Some((score, index, positions))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored
+            .into_iter()
+            .map(|(_, index, positions)| (index, positions))
+            .collect();
+        self.cursor = 0;
+    }
+
+    /// The entry's parent, its parent's parent and so on.
+    fn ancestors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.parents[index], |&parent| self.parents[parent])
+            .take(self.parents.len())
+    }
+
+    fn depth(&self, index: usize) -> usize {
+        self.ancestors(index).count()
+    }
+
+    fn has_children(&self, index: usize) -> bool {
+        self.parents.contains(&Some(index))
+    }
+
+    /// The entry and everything below it.
+    fn subtree(&self, index: usize) -> Vec<usize> {
+        (0..self.parents.len())
+            .filter(|&other| other == index || self.ancestors(other).any(|a| a == index))
+            .collect()
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.matches.get(self.cursor).map(|(index, _)| *index)
+    }
+
+    /// Lists the children of the highlighted entry.
+    fn expand(&mut self) {
+        let Some(index) = self.current() else {
+            return;
+        };
+        if self.is_tree() && self.has_children(index) && !self.expanded.contains(&index) {
+            self.expanded.push(index);
+            self.refresh(index);
+        }
+    }
+
+    /// Folds the highlighted entry, or the one it is listed under.
+    fn collapse(&mut self) {
+        let Some(index) = self.current() else {
+            return;
+        };
+        if !self.is_tree() {
+            return;
+        }
+        let folded = if self.expanded.contains(&index) {
+            index
+        } else if let Some(parent) = self.parents[index] {
+            parent
+        } else {
+            return;
+        };
+        self.expanded.retain(|&expanded| expanded != folded);
+        self.refresh(folded);
+    }
+
+    /// Adds entries that came in, keeping the highlighted one highlighted.
+    fn append(&mut self, entries: &[Entry], picker: &Picker) {
+        let current = self.current();
+        self.texts.extend(
+            entries
+                .iter()
+                .map(|entry| searched_text(&crate::logfile::strip_ansi(&entry.text), picker.nth)),
+        );
+        self.parents
+            .extend(entries.iter().map(|entry| entry.parent));
+        match current {
+            Some(index) => self.refresh(index),
+            None => self.update(),
+        }
+    }
+
+    /// Lists the entries again, keeping `index` highlighted.
+    fn refresh(&mut self, index: usize) {
+        self.update();
+        self.cursor = self
+            .matches
+            .iter()
+            .position(|(other, _)| *other == index)
+            .unwrap_or(0);
+    }
+
+    /// Selects the highlighted entry and everything below it, or deselects
+    /// them when they all are selected.
+    fn toggle_subtree(&mut self) {
+        let Some(index) = self.current() else {
+            return;
+        };
+        let subtree = self.subtree(index);
+        if subtree.iter().all(|index| self.selected.contains(index)) {
+            self.selected.retain(|selected| !subtree.contains(selected));
+        } else {
+            for index in subtree {
+                if !self.selected.contains(&index) {
+                    self.selected.push(index);
+                }
+            }
+        }
+    }
+
+    fn move_cursor(&mut self, by: isize) {
+        let last = self.matches.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(by).min(last);
+    }
+
+    /// The first entry shown, so that the cursor stays in view.
+    fn offset(&self, height: usize) -> usize {
+        (self.cursor + 1).saturating_sub(height)
+    }
+
+    fn toggle(&mut self) {
+        let Some((index, _)) = self.matches.get(self.cursor) else {
+            return;
+        };
+        match self.selected.iter().position(|selected| selected == index) {
+            Some(position) => {
+                self.selected.remove(position);
+            }
+            None => self.selected.push(*index),
+        }
+    }
+
+    /// Selects the listed entries that are not selected and deselects the
+    /// others.
+    fn toggle_all(&mut self) {
+        for (index, _) in &self.matches {
+            match self.selected.iter().position(|selected| selected == index) {
+                Some(position) => {
+                    self.selected.remove(position);
+                }
+                None => self.selected.push(*index),
+            }
+        }
+    }
+
+    fn select_all(&mut self) {
+        for (index, _) in &self.matches {
+            if !self.selected.contains(index) {
+                self.selected.push(*index);
+            }
+        }
+    }
+
+    /// The selected entries, or the highlighted one when none is.
+    fn picked(self, action: Option<String>) -> Picked {
+        let mut indices = self.selected;
+        if indices.is_empty() {
+            indices.extend(self.matches.get(self.cursor).map(|(index, _)| *index));
+        }
+        indices.sort_unstable();
+        Picked {
+            indices,
+            action,
+            query: self.query,
+        }
+    }
+}
+
+/// Rows `header` takes, one at least.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn header_lines(header: &str) -> usize {
+    header.lines().count().max(1)
+}
+
+/// Whether every term of `query` matches `text`, as in the selector.
+pub fn matches(text: &str, query: &str, exact: bool, case: Case) -> bool {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    score(text, &terms, exact, case).is_some()
+}
+
+/// Scores `text` against every term, or returns `None` when one does not
+/// match. Consecutive matches and matches at word starts score higher.
+/// `exact` terms only match consecutive characters.
+fn score(text: &str, terms: &[&str], exact: bool, case: Case) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut total = 0;
+    let mut positions = Vec::new();
+
+    for term in terms {
+        let case_sensitive = match case {
+            Case::Smart => term.chars().any(char::is_uppercase),
+            Case::Sensitive => true,
+            Case::Insensitive => false,
+        };
+        let term: Vec<char> = term.chars().collect();
+        let eq = |a: char, b: char| {
+            if case_sensitive {
+                a == b
+            } else {
+                a.to_lowercase().eq(b.to_lowercase())
+            }
+        };
+
+        // Try each start and keep the best greedy match from there.
+        let mut best: Option<(i64, Vec<usize>)> = None;
+        for start in (0..chars.len()).filter(|&i| eq(chars[i], term[0])) {
+            let mut matched = vec![start];
+            let mut next = start + 1;
+            for &c in &term[1..] {
+                let found = if exact {
+                    (next < chars.len() && eq(chars[next], c)).then_some(next)
+                } else {
+                    (next..chars.len()).find(|&i| eq(chars[i], c))
+                };
+                let Some(found) = found else {
+                    break;
+                };
+                matched.push(found);
+                next = found + 1;
+            }
+            // A later start only leaves fewer characters to match fuzzily.
+            if matched.len() < term.len() {
+                if exact {
+                    continue;
+                }
+                break;
+            }
+
+            let mut score = 0;
+            for (n, &position) in matched.iter().enumerate() {
+                score += 16;
+                if n > 0 && position == matched[n - 1] + 1 {
+                    score += 12;
+                }
+                if is_word_start(&chars, position) {
+                    score += 8;
+                }
+            }
+            score -= (matched[matched.len() - 1] - start) as i64;
+            if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                best = Some((score, matched));
+            }
+        }
+
+        let (score, matched) = best?;
+        total += score;
+        positions.extend(matched);
+    }
+
+    positions.sort_unstable();
+    positions.dedup();
+    Some((total, positions))
+}
+
+fn is_word_start(chars: &[char], position: usize) -> bool {
+    let Some(previous) = position.checked_sub(1).map(|i| chars[i]) else {
+        return true;
+    };
+    !previous.is_alphanumeric() || (previous.is_lowercase() && chars[position].is_uppercase())
+}
+
+/// Byte ranges of the tab-separated fields of `text` that `nth` names, in
+/// order; all of `text` when `nth` is empty.
+pub fn field_ranges(text: &str, nth: &[isize]) -> Vec<(usize, usize)> {
+    if nth.is_empty() {
+        return vec![(0, text.len())];
+    }
+    let mut fields = Vec::new();
+    let mut start = 0;
+    for (tab, _) in text.match_indices('\t') {
+        fields.push((start, tab));
+        start = tab + 1;
+    }
+    fields.push((start, text.len()));
+
+    let count = fields.len() as isize;
+    let mut ranges: Vec<_> = nth
+        .iter()
+        .filter_map(|&n| {
+            let index = if n < 0 { count + n } else { n - 1 };
+            fields.get(usize::try_from(index).ok()?).copied()
+        })
+        .collect();
+    ranges.sort_unstable();
+    ranges.dedup();
+    ranges
+}
+
+/// Columns between tab stops.
+const TAB_WIDTH: usize = 8;
+
+/// `text` with tabs expanded to the next tab stop, counting only visible
+/// characters.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn expand_tabs(text: &str) -> String {
+    let mut expanded = String::new();
+    let mut visible = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                expanded.push(c);
+                for c in chars.by_ref() {
+                    expanded.push(c);
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            '\t' => {
+                let spaces = TAB_WIDTH - visible % TAB_WIDTH;
+                expanded.push_str(&" ".repeat(spaces));
+                visible += spaces;
+            }
+            _ => {
+                expanded.push(c);
+                visible += 1;
+            }
+        }
+    }
+    expanded
+}
+
+/// `text`, which has no colors, with tabs expanded and NUL, which no query
+/// matches, in place of the characters outside the fields `nth` names.
+fn searched_text(text: &str, nth: &[isize]) -> String {
+    let ranges = field_ranges(text, nth);
+    let mut searched = String::new();
+    let mut visible = 0;
+    for (offset, c) in text.char_indices() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - visible % TAB_WIDTH;
+            searched.push_str(&"\0".repeat(spaces));
+            visible += spaces;
+            continue;
+        }
+        let searched_field = ranges
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&offset));
+        searched.push(if searched_field { c } else { '\0' });
+        visible += 1;
+    }
+    searched
+}
+
+/// Cuts `text` to `width` characters.
+pub(crate) fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Cuts `text` to `width` visible characters, coloring the matched ones,
+/// or underlining them when `plain`. Colors of the text itself are kept.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn highlight(text: &str, positions: &[usize], width: usize, plain: bool) -> String {
+    let (start, end) = if plain {
+        ("\x1b[4m", "\x1b[24m")
+    } else {
+        ("\x1b[32m", "\x1b[39m")
+    };
+    let mut highlighted = String::new();
+    let mut visible = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            highlighted.push(c);
+            for c in chars.by_ref() {
+                highlighted.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        if visible == width {
+            break;
+        }
+        if positions.binary_search(&visible).is_ok() {
+            highlighted.push_str(&format!("{}{}{}", start, c, end));
+        } else {
+            highlighted.push(c);
+        }
+        visible += 1;
+    }
+    highlighted.push_str("\x1b[0m");
+    highlighted
+}
+
+/// A key press.
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Other,
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+impl Key {
+    /// The key's name in bindings, as skim names it.
+    fn name(self) -> Option<String> {
+        Some(match self {
+            Key::Ctrl(c) => format!("ctrl-{}", c),
+            Key::Alt(c) => format!("alt-{}", c),
+            Key::Enter => "enter".to_string(),
+            Key::Tab => "tab".to_string(),
+            Key::BackTab => "btab".to_string(),
+            Key::Esc => "esc".to_string(),
+            Key::Up => "up".to_string(),
+            Key::Down => "down".to_string(),
+            Key::Left => "left".to_string(),
+            Key::Right => "right".to_string(),
+            Key::PageUp => "pgup".to_string(),
+            Key::PageDown => "pgdn".to_string(),
+            Key::Char(_) | Key::Backspace | Key::Other => return None,
+        })
+    }
+}
+
+/// Drawing on the terminal and reading its keys, which the live results
+/// pane shares.
+#[cfg(unix)]
+pub mod terminal {
+    use super::Key;
+    use anyhow::{Context, Result, bail};
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::os::fd::AsRawFd;
+
+    /// How long to wait for the rest of an escape sequence before taking a
+    /// lone Esc.
+    const ESCAPE_TIMEOUT_MS: i32 = 25;
+
+    /// How long to wait for the terminal to report where the cursor is.
+    const REPORT_TIMEOUT_MS: i32 = 500;
+
+    /// The controlling terminal in raw mode, on the alternate screen unless
+    /// opened inline.
+    pub struct RawTerminal {
+        tty: File,
+        keys: Keys,
+        original: libc::termios,
+        inline: Option<Inline>,
+    }
+
+    /// Lines of the screen itself drawn on.
+    struct Inline {
+        top: usize,
+        rows: usize,
+        /// Leave what was drawn once done, rather than clearing it.
+        keep: bool,
+    }
+
+    impl RawTerminal {
+        pub fn open() -> Result<RawTerminal> {
+            let mut terminal = RawTerminal::raw()?;
+            terminal.write("\x1b[?1049h\x1b[2J")?;
+            Ok(terminal)
+        }
+
+        /// Opens the terminal to draw on the screen itself, on as many lines
+        /// as `rows` makes of the screen's from the cursor's line down,
+        /// scrolling the screen up when fewer are left. Once dropped, the
+        /// lines are cleared, or with `keep` left as they were drawn.
+        pub fn open_inline(rows: impl FnOnce(usize) -> usize, keep: bool) -> Result<RawTerminal> {
+            let mut terminal = RawTerminal::raw()?;
+            let (screen_rows, _) = terminal.size();
+            let rows = rows(screen_rows).clamp(1, screen_rows);
+            // Without a report, the cursor is taken to be on the last line.
+            let cursor = terminal.cursor_row().unwrap_or(screen_rows);
+            terminal.write(&"\r\n".repeat(rows - 1))?;
+            terminal.inline = Some(Inline {
+                top: cursor.min(screen_rows + 1 - rows),
+                rows,
+                keep,
+            });
+            Ok(terminal)
+        }
+
+        /// The line the cursor is on, from 1, as the terminal reports it.
+        fn cursor_row(&mut self) -> Option<usize> {
+            self.write("\x1b[6n").ok()?;
+            let mut report = Vec::new();
+            while self.keys.poll(REPORT_TIMEOUT_MS) {
+                let byte = self.keys.read_byte().ok()?;
+                report.push(byte);
+                if byte == b'R' {
+                    break;
+                }
+            }
+            let report = std::str::from_utf8(&report).ok()?;
+            let (row, _) = report
+                .strip_prefix("\x1b[")?
+                .strip_suffix('R')?
+                .split_once(';')?;
+            row.parse().ok()
+        }
+
+        /// The first line, the number of lines and the columns drawn on.
+        pub fn region(&self) -> (usize, usize, usize) {
+            let (rows, cols) = self.size();
+            match &self.inline {
+                Some(inline) => (inline.top, inline.rows.min(rows), cols),
+                None => (1, rows, cols),
+            }
+        }
+
+        fn raw() -> Result<RawTerminal> {
+            let tty = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/tty")
+                .context("No terminal to draw on")?;
+            let fd = tty.as_raw_fd();
+
+            // SAFETY: `termios` is plain data filled in by tcgetattr, and
+            // only a copy of it is modified and applied.
+            let original = unsafe {
+                let mut original: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(fd, &mut original) != 0 {
+                    bail!("Failed to read the terminal settings");
+                }
+                let mut raw = original;
+                libc::cfmakeraw(&mut raw);
+                raw.c_cc[libc::VMIN] = 1;
+                raw.c_cc[libc::VTIME] = 0;
+                if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+                    bail!("Failed to switch the terminal to raw mode");
+                }
+                original
+            };
+
+            let keys = Keys {
+                tty: tty.try_clone()?,
+            };
+            Ok(RawTerminal {
+                tty,
+                keys,
+                original,
+                inline: None,
+            })
+        }
+
+        pub fn write(&mut self, text: &str) -> Result<()> {
+            self.tty.write_all(text.as_bytes())?;
+            self.tty.flush()?;
+            Ok(())
+        }
+
+        /// Rows and columns, or 24x80 when the terminal does not say.
+        pub fn size(&self) -> (usize, usize) {
+            // SAFETY: TIOCGWINSZ fills in the `winsize` it is given.
+            unsafe {
+                let mut size: libc::winsize = std::mem::zeroed();
+                if libc::ioctl(self.tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) == 0
+                    && size.ws_row > 0
+                    && size.ws_col > 0
+                {
+                    return (size.ws_row as usize, size.ws_col as usize);
+                }
+            }
+            (24, 80)
+        }
+
+        pub fn read_key(&mut self) -> Result<Key> {
+            self.keys.read_key()
+        }
+
+        pub fn read_key_timeout(&mut self, timeout_ms: i32) -> Result<Option<Key>> {
+            self.keys.read_key_timeout(timeout_ms)
+        }
+
+        /// Another reader of key presses, for a thread of its own.
+        pub fn keys(&self) -> Result<Keys> {
+            Ok(Keys {
+                tty: self.tty.try_clone()?,
+            })
+        }
+    }
+
+    /// Reads key presses from the terminal.
+    pub struct Keys {
+        tty: File,
+    }
+
+    impl Keys {
+        pub fn read_key(&mut self) -> Result<Key> {
+            let byte = self.read_byte()?;
+            Ok(match byte {
+                b'\r' => Key::Enter,
+                b'\t' => Key::Tab,
+                0x7f | 0x08 => Key::Backspace,
+                0x1b => self.read_escape()?,
+                0x01..=0x1a => Key::Ctrl(char::from(b'a' + byte - 1)),
+                0x00..=0x1f => Key::Other,
+                _ => self.read_char(byte)?.map_or(Key::Other, Key::Char),
+            })
+        }
+
+        /// The next key press, or `None` when none comes within
+        /// `timeout_ms`, so that a reader can check whether to stop.
+        pub fn read_key_timeout(&mut self, timeout_ms: i32) -> Result<Option<Key>> {
+            if !self.poll(timeout_ms) {
+                return Ok(None);
+            }
+            self.read_key().map(Some)
+        }
+
+        fn read_byte(&mut self) -> Result<u8> {
+            let mut byte = [0];
+            self.tty.read_exact(&mut byte)?;
+            Ok(byte[0])
+        }
+
+        /// Whether more input arrives within the escape timeout.
+        fn pending(&self) -> bool {
+            self.poll(ESCAPE_TIMEOUT_MS)
+        }
+
+        /// Whether input arrives within `timeout_ms`.
+        fn poll(&self, timeout_ms: i32) -> bool {
+            let mut poll = libc::pollfd {
+                fd: self.tty.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: polls the single descriptor given.
+            unsafe { libc::poll(&mut poll, 1, timeout_ms) > 0 }
+        }
+
+        fn read_escape(&mut self) -> Result<Key> {
+            if !self.pending() {
+                return Ok(Key::Esc);
+            }
+            let byte = self.read_byte()?;
+            if byte != b'[' && byte != b'O' {
+                return Ok(self.read_char(byte)?.map_or(Key::Other, Key::Alt));
+            }
+
+            let mut sequence = Vec::new();
+            loop {
+                let byte = self.read_byte()?;
+                sequence.push(byte);
+                if (0x40..=0x7e).contains(&byte) {
+                    break;
+                }
+            }
+            Ok(match sequence.as_slice() {
+                b"A" => Key::Up,
+                b"B" => Key::Down,
+                b"C" => Key::Right,
+                b"D" => Key::Left,
+                b"Z" => Key::BackTab,
+                b"5~" => Key::PageUp,
+                b"6~" => Key::PageDown,
+                _ => Key::Other,
+            })
+        }
+
+        /// Reads the rest of a UTF-8 character starting with `first`.
+        fn read_char(&mut self, first: u8) -> Result<Option<char>> {
+            let length = match first {
+                0x00..=0x7f => 1,
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Ok(None),
+            };
+            let mut bytes = vec![first];
+            for _ in 1..length {
+                bytes.push(self.read_byte()?);
+            }
+            Ok(std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|text| text.chars().next()))
+        }
+    }
+
+    impl Drop for RawTerminal {
+        fn drop(&mut self) {
+            let restore = match &self.inline {
+                None => "\x1b[?25h\x1b[?1049l".to_string(),
+                // What comes next goes below what was drawn, or in its place.
+                Some(inline) if inline.keep => {
+                    format!("\x1b[?25h\x1b[{};1H\r\n", inline.top + inline.rows - 1)
+                }
+                Some(inline) => format!("\x1b[?25h\x1b[{};1H\x1b[J", inline.top),
+            };
+            let _ = self.write(&restore);
+            // SAFETY: restores the settings read in `open`.
+            unsafe {
+                libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.original);
+            }
+        }
+    }
+}
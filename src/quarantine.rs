@@ -0,0 +1,64 @@
+//! Known-flaky tests listed in a `.gotestfinder-quarantine` file.
+//!
+//! The nearest such file in the scanned directory or one of its ancestors
+//! is used. Each line names a test, optionally preceded by its package's
+//! import path; a test's entry covers its subtests too:
+//!
+//! ```text
+//! # Times out on slow CI machines.
+//! TestUpload
+//! example.com/shop/cart TestCheckout/expired_card
+//! ```
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+const FILE: &str = ".gotestfinder-quarantine";
+
+#[derive(Debug, Clone, Default)]
+pub struct Quarantine {
+    /// Quarantined tests, with the package they are limited to.
+    entries: Vec<(Option<String>, String)>,
+}
+
+impl Quarantine {
+    /// Loads the quarantine file for `dir`, which is empty without one.
+    pub fn load(dir: &Path) -> Result<Quarantine> {
+        let Ok(dir) = dir.canonicalize() else {
+            return Ok(Quarantine::default());
+        };
+        let Some(file) = dir
+            .ancestors()
+            .map(|dir| dir.join(FILE))
+            .find(|file| file.is_file())
+        else {
+            return Ok(Quarantine::default());
+        };
+
+        let content = std::fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let entries = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.split_once(char::is_whitespace) {
+                Some((package, test)) => (Some(package.to_string()), test.trim().to_string()),
+                None => (None, line.to_string()),
+            })
+            .collect();
+
+        Ok(Quarantine { entries })
+    }
+
+    /// Whether `test` of `package`, with subtests as `TestX/sub`, is
+    /// quarantined itself or through its parent test.
+    pub fn contains(&self, package: &str, test: &str) -> bool {
+        self.entries.iter().any(|(entry_package, entry)| {
+            entry_package.as_deref().is_none_or(|p| p == package)
+                && (test == entry
+                    || test
+                        .strip_prefix(entry.as_str())
+                        .is_some_and(|rest| rest.starts_with('/')))
+        })
+    }
+}
@@ -0,0 +1,781 @@
+//! Subtest name resolution for `t.Run` calls.
+//!
+//! Besides literal names, this follows the common table-driven layout where
+//! a loop ranges over a slice or map literal and passes one of its fields
+//! (or the map key) to `t.Run`, and names held in string constants or
+//! variables. Names built at run time with `fmt.Sprintf` or string
+//! concatenation are kept as dynamic prefixes. Subtests run by helpers the
+//! test hands its `t` to are resolved once the whole package is known.
+
+use crate::parser::{self, FuncDecl, GoFile, Token, TokenKind};
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+pub struct Subtest {
+    /// Slash-separated path below the test function, as `go test` reports
+    /// it.
+    pub name: String,
+    /// Only a literal prefix of the name is known, so `name` holds that
+    /// prefix and the subtest stands for every subtest starting with it.
+    pub dynamic: bool,
+}
+
+impl Subtest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Subtest {
+            name: name.into(),
+            dynamic: false,
+        }
+    }
+
+    fn dynamic(prefix: impl Into<String>) -> Self {
+        Subtest {
+            name: prefix.into(),
+            dynamic: true,
+        }
+    }
+
+    /// The part of a `-run` pattern selecting this subtest below its test,
+    /// with regex metacharacters in each level escaped. A dynamic subtest
+    /// anchors each level so that only the last one matches as a prefix.
+    pub fn run_pattern(&self) -> String {
+        let levels: Vec<String> = self.name.split('/').map(regex::escape).collect();
+        if !self.dynamic {
+            return levels.join("/");
+        }
+
+        let Some((last, parents)) = levels.split_last() else {
+            return String::new();
+        };
+        parents
+            .iter()
+            .map(|level| format!("^{}$/", level))
+            .chain(std::iter::once(format!("^{}", last)))
+            .collect()
+    }
+}
+
+pub fn find_subtests(file: &GoFile, body: &[Token]) -> Vec<Subtest> {
+    let mut subtests = Vec::new();
+    collect_subtests(file, body, 0..body.len(), &[], &mut subtests);
+    subtests
+}
+
+/// Collects the `t.Run` calls in `range` of the body as children of each of
+/// `parents`, descending into their arguments for nested subtests. Nothing
+/// is collected below a dynamic subtest since its full name is unknown.
+fn collect_subtests(
+    file: &GoFile,
+    body: &[Token],
+    range: Range<usize>,
+    parents: &[String],
+    subtests: &mut Vec<Subtest>,
+) {
+    let mut i = range.start;
+
+    while i + 2 < range.end {
+        if !(body[i].is_punct(".") && body[i + 1].is_ident("Run") && body[i + 2].is_punct("(")) {
+            i += 1;
+            continue;
+        }
+
+        let open = i + 2;
+        let Some(close) = parser::matching(body, open) else {
+            break;
+        };
+        let args = &body[open + 1..close];
+        let name = match args.iter().position(|token| token.is_punct(",")) {
+            Some(comma) => &args[..comma],
+            None => args,
+        };
+
+        let found: Vec<Subtest> = match dynamic_prefix(name) {
+            Some(prefix) if !prefix.is_empty() => vec![Subtest::dynamic(rewrite(&prefix))],
+            Some(_) => Vec::new(),
+            None => resolve_names(file, body, i, name)
+                .iter()
+                .map(|name| Subtest::new(rewrite(name)))
+                .collect(),
+        };
+
+        let found: Vec<Subtest> = if parents.is_empty() {
+            found
+        } else {
+            parents
+                .iter()
+                .flat_map(|parent| {
+                    found.iter().map(move |child| Subtest {
+                        name: format!("{}/{}", parent, child.name),
+                        dynamic: child.dynamic,
+                    })
+                })
+                .collect()
+        };
+
+        let nested_parents: Vec<String> = found
+            .iter()
+            .filter(|subtest| !subtest.dynamic)
+            .map(|subtest| subtest.name.clone())
+            .collect();
+        subtests.extend(found);
+
+        if !nested_parents.is_empty() {
+            let name_end = open + 1 + name.len();
+            collect_subtests(file, body, name_end..close, &nested_parents, subtests);
+        }
+
+        i = close + 1;
+    }
+}
+
+/// Recognises names built at run time, `fmt.Sprintf("case-%d", i)` or
+/// `"case-" + suffix`, and returns their literal prefix. A `Sprintf` format
+/// without verbs is a plain name and yields `None`.
+fn dynamic_prefix(expr: &[Token]) -> Option<String> {
+    match expr {
+        [pkg, dot, func, open, format, ..]
+            if pkg.is_ident("fmt")
+                && dot.is_punct(".")
+                && func.is_ident("Sprintf")
+                && open.is_punct("(")
+                && format.is_string() =>
+        {
+            let mut prefix = String::new();
+            let mut chars = format.text.chars().peekable();
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    prefix.push(c);
+                } else if chars.next_if_eq(&'%').is_some() {
+                    prefix.push('%');
+                } else {
+                    return Some(prefix);
+                }
+            }
+            None
+        }
+        [literal, plus, ..] if literal.is_string() && plus.is_punct("+") => {
+            Some(literal.text.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Resolves the expression passed as a subtest name at `at` to the names it
+/// can take.
+fn resolve_names(file: &GoFile, body: &[Token], at: usize, expr: &[Token]) -> Vec<String> {
+    match expr {
+        [name] if name.is_string() => vec![name.text.clone()],
+        [pkg, dot, func, open, format, close]
+            if pkg.is_ident("fmt")
+                && dot.is_punct(".")
+                && func.is_ident("Sprintf")
+                && open.is_punct("(")
+                && format.is_string()
+                && close.is_punct(")") =>
+        {
+            vec![format.text.replace("%%", "%")]
+        }
+        [var, dot, field]
+            if var.kind == TokenKind::Ident
+                && dot.is_punct(".")
+                && field.kind == TokenKind::Ident =>
+        {
+            range_binding(body, at, &var.text)
+                .filter(|binding| !binding.is_key)
+                .and_then(|binding| table(file, body, binding.expr))
+                .map(|table| table.field_values(&field.text))
+                .unwrap_or_default()
+        }
+        [var] if var.kind == TokenKind::Ident => match range_binding(body, at, &var.text) {
+            Some(binding) if binding.is_key => table(file, body, binding.expr)
+                .map(|table| table.keys)
+                .unwrap_or_default(),
+            Some(_) => Vec::new(),
+            None => string_value(file, body, at, &var.text, 0)
+                .into_iter()
+                .collect(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Resolves an identifier to the string literal it was last assigned before
+/// `at` in the function body, or to a package-level constant or variable of
+/// the file. Identifiers initialised from other identifiers are followed a
+/// few levels deep.
+fn string_value(
+    file: &GoFile,
+    body: &[Token],
+    at: usize,
+    name: &str,
+    depth: usize,
+) -> Option<String> {
+    if depth > 4 {
+        return None;
+    }
+
+    let local = (0..at.min(body.len()))
+        .rev()
+        .find_map(|i| assigned_value(body, i, name));
+    if let Some(value) = local {
+        return literal_or_ident(file, body, at, value, depth);
+    }
+
+    let tokens = &file.tokens;
+    let in_func = |i: usize| {
+        file.funcs
+            .iter()
+            .any(|func| func.body.as_ref().is_some_and(|body| body.contains(&i)))
+    };
+    let global = (0..tokens.len())
+        .filter(|&i| !in_func(i))
+        .find_map(|i| assigned_value(tokens, i, name))?;
+    literal_or_ident(file, &[], 0, global, depth)
+}
+
+/// If `tokens[i]` is `name` being assigned a single-token value (`name :=
+/// v`, `name = v`, `var name T = v` or `name T = v` in a const group),
+/// returns that value.
+fn assigned_value<'a>(tokens: &'a [Token], i: usize, name: &str) -> Option<&'a Token> {
+    if !tokens[i].is_ident(name) {
+        return None;
+    }
+
+    let mut j = i + 1;
+    if tokens.get(j).is_some_and(|t| t.kind == TokenKind::Ident) {
+        j += 1;
+    }
+    if !tokens
+        .get(j)
+        .is_some_and(|t| t.is_punct(":=") || t.is_punct("="))
+    {
+        return None;
+    }
+
+    let value = tokens.get(j + 1)?;
+    let single = tokens
+        .get(j + 2)
+        .is_none_or(|next| next.line > value.line || next.is_punct(")") || next.is_punct(";"));
+    single.then_some(value)
+}
+
+fn literal_or_ident(
+    file: &GoFile,
+    body: &[Token],
+    at: usize,
+    value: &Token,
+    depth: usize,
+) -> Option<String> {
+    match value.kind {
+        TokenKind::String => Some(value.text.clone()),
+        TokenKind::Ident => string_value(file, body, at, &value.text, depth + 1),
+        _ => None,
+    }
+}
+
+/// Applies the rewriting `go test` does to subtest names: white space
+/// becomes an underscore and non-printable characters are escaped.
+pub fn rewrite(name: &str) -> String {
+    let mut rewritten = String::new();
+
+    for c in name.chars() {
+        if c.is_whitespace() {
+            rewritten.push('_');
+        } else if c.is_control() {
+            if (c as u32) < 0x80 {
+                rewritten.push_str(&format!("\\x{:02x}", c as u32));
+            } else {
+                rewritten.push_str(&format!("\\u{:04x}", c as u32));
+            }
+        } else {
+            rewritten.push(c);
+        }
+    }
+
+    rewritten
+}
+
+struct RangeBinding {
+    /// Index of the first token of the ranged-over expression.
+    expr: usize,
+    is_key: bool,
+}
+
+/// Finds the closest `for k, v := range expr` before `before` that binds
+/// `var`.
+fn range_binding(body: &[Token], before: usize, var: &str) -> Option<RangeBinding> {
+    let mut binding = None;
+
+    for i in 2..before {
+        if !body[i].is_ident("range") || !(body[i - 1].is_punct(":=") || body[i - 1].is_punct("="))
+        {
+            continue;
+        }
+
+        let names = &body[..i - 1];
+        let is_key = match names {
+            [.., key, comma, value] if comma.is_punct(",") => {
+                if value.is_ident(var) {
+                    Some(false)
+                } else if key.is_ident(var) {
+                    Some(true)
+                } else {
+                    None
+                }
+            }
+            [.., keyword, key] if keyword.is_ident("for") && key.is_ident(var) => Some(true),
+            _ => None,
+        };
+
+        if let Some(is_key) = is_key {
+            binding = Some(RangeBinding {
+                expr: i + 1,
+                is_key,
+            });
+        }
+    }
+
+    binding
+}
+
+/// A slice or map composite literal used as a test table.
+#[derive(Default)]
+struct Table {
+    /// Struct field names in declaration order, for positional elements.
+    fields: Vec<String>,
+    /// Tokens of each element's struct literal, without the braces.
+    elements: Vec<Vec<Token>>,
+    /// String keys of a map literal.
+    keys: Vec<String>,
+}
+
+impl Table {
+    fn field_values(&self, field: &str) -> Vec<String> {
+        let position = self.fields.iter().position(|f| f == field);
+
+        self.elements
+            .iter()
+            .filter_map(|element| {
+                let items = split_top_level(element, ",");
+                let keyed = items.iter().find_map(|item| match item {
+                    [key, colon, value, ..]
+                        if key.is_ident(field) && colon.is_punct(":") && value.is_string() =>
+                    {
+                        Some(value.text.clone())
+                    }
+                    _ => None,
+                });
+                let positional = || match items.get(position?) {
+                    Some([value]) if value.is_string() => Some(value.text.clone()),
+                    _ => None,
+                };
+                keyed.or_else(positional)
+            })
+            .collect()
+    }
+}
+
+/// Resolves the table a range expression refers to: either a literal
+/// written in place or a variable initialised with one, in the function body
+/// or at package level.
+fn table(file: &GoFile, body: &[Token], expr: usize) -> Option<Table> {
+    let first = body.get(expr)?;
+    if first.kind != TokenKind::Ident || first.is_ident("map") {
+        return parse_table(file, &body[expr..]);
+    }
+
+    let local = (0..expr).rev().find(|&i| {
+        body[i].is_ident(&first.text)
+            && body
+                .get(i + 1)
+                .is_some_and(|t| t.is_punct(":=") || t.is_punct("="))
+    });
+    if let Some(i) = local {
+        return parse_table(file, &body[i + 2..]);
+    }
+
+    let tokens = &file.tokens;
+    let global = (1..tokens.len().saturating_sub(2)).find(|&i| {
+        tokens[i - 1].is_ident("var")
+            && tokens[i].is_ident(&first.text)
+            && tokens[i + 1].is_punct("=")
+    })?;
+    parse_table(file, &tokens[global + 2..])
+}
+
+fn parse_table(file: &GoFile, tokens: &[Token]) -> Option<Table> {
+    let mut table = Table::default();
+    let is_map = tokens.first()?.is_ident("map");
+
+    // Skip `[]`, `[N]`, `[...]` or `map[K]` to reach the element type.
+    let mut i = if is_map { 1 } else { 0 };
+    if !tokens.get(i)?.is_punct("[") {
+        return None;
+    }
+    i = parser::matching(tokens, i)? + 1;
+
+    while tokens.get(i)?.is_punct("*") {
+        i += 1;
+    }
+
+    let element_type = tokens.get(i)?;
+    if element_type.is_ident("struct") {
+        let close = parser::matching(tokens, i + 1)?;
+        table.fields = struct_fields(&tokens[i + 2..close]);
+        i = close + 1;
+    } else {
+        table.fields = named_struct_fields(file, &element_type.text);
+        i += 1;
+    }
+
+    if !tokens.get(i)?.is_punct("{") {
+        return None;
+    }
+    let close = parser::matching(tokens, i)?;
+
+    for element in split_top_level(&tokens[i + 1..close], ",") {
+        let value = if is_map {
+            match element {
+                [key, colon, value @ ..] if key.is_string() && colon.is_punct(":") => {
+                    table.keys.push(key.text.clone());
+                    value
+                }
+                _ => continue,
+            }
+        } else {
+            element
+        };
+
+        let open = value.iter().position(|token| token.is_punct("{"));
+        if let Some(open) = open
+            && let Some(end) = parser::matching(value, open)
+        {
+            table.elements.push(value[open + 1..end].to_vec());
+        }
+    }
+
+    Some(table)
+}
+
+/// Looks up `type name struct { ... }` in the file.
+fn named_struct_fields(file: &GoFile, name: &str) -> Vec<String> {
+    let tokens = &file.tokens;
+
+    (1..tokens.len().saturating_sub(2))
+        .find(|&i| {
+            tokens[i - 1].is_ident("type")
+                && tokens[i].is_ident(name)
+                && tokens[i + 1].is_ident("struct")
+                && tokens[i + 2].is_punct("{")
+        })
+        .and_then(|i| {
+            let close = parser::matching(tokens, i + 2)?;
+            Some(struct_fields(&tokens[i + 3..close]))
+        })
+        .unwrap_or_default()
+}
+
+/// Returns field names in declaration order. Fields are separated by line
+/// breaks or semicolons; `a, b int` declares two fields and an embedded type
+/// counts as one field named after the type.
+fn struct_fields(tokens: &[Token]) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for i in 0..=tokens.len() {
+        let end = i == tokens.len()
+            || (depth == 0
+                && (tokens[i].is_punct(";") || (i > start && tokens[i].line > tokens[i - 1].line)));
+
+        if end {
+            let decl = &tokens[start..i];
+            let names: Vec<&Token> = decl
+                .iter()
+                .step_by(2)
+                .take_while(|token| token.kind == TokenKind::Ident)
+                .collect();
+            let multiple = decl.get(1).is_some_and(|t| t.is_punct(","));
+
+            if multiple {
+                fields.extend(names.iter().map(|t| t.text.clone()));
+            } else if let Some(first) = decl.first() {
+                let name = match decl.last() {
+                    Some(last) if decl.len() <= 2 && first.is_punct("*") => &last.text,
+                    _ => &first.text,
+                };
+                fields.push(name.clone());
+            }
+
+            start = if i < tokens.len() && tokens[i].is_punct(";") {
+                i + 1
+            } else {
+                i
+            };
+        }
+
+        if let Some(token) = tokens.get(i)
+            && token.kind == TokenKind::Punct
+        {
+            match token.text.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    fields
+}
+
+/// Splits `tokens` on `separator` where it is not nested in brackets,
+/// dropping empty pieces such as the one after a trailing comma.
+fn split_top_level<'a>(tokens: &'a [Token], separator: &str) -> Vec<&'a [Token]> {
+    let mut pieces = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Punct {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            text if depth == 0 && text == separator => {
+                pieces.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&tokens[start..]);
+    pieces.retain(|piece| !piece.is_empty());
+
+    pieces
+}
+
+/// What a package-level helper taking the test's `*testing.T` runs as
+/// subtests, so that tests delegating to it (`runCases(t, cases)`) can list
+/// them. Names coming from the helper's parameters are only known at a call
+/// site.
+#[derive(Debug, Clone, Default)]
+pub struct Helper {
+    /// Subtests whose names are known inside the helper.
+    subtests: Vec<Subtest>,
+    /// `t.Run` calls naming subtests after one of the helper's parameters.
+    param_names: Vec<ParamName>,
+}
+
+#[derive(Debug, Clone)]
+struct ParamName {
+    /// Position of the parameter in the helper's signature.
+    param: usize,
+    source: NameSource,
+}
+
+#[derive(Debug, Clone)]
+enum NameSource {
+    /// The parameter itself is the name.
+    Value,
+    /// The helper ranges over a table parameter and names subtests after
+    /// this field of its elements.
+    Field(String),
+    /// The helper ranges over a map parameter and names subtests after its
+    /// keys.
+    Key,
+}
+
+impl Helper {
+    pub fn is_empty(&self) -> bool {
+        self.subtests.is_empty() && self.param_names.is_empty()
+    }
+}
+
+/// Analyses a helper function that is handed a `*testing.T`.
+pub fn find_helper(file: &GoFile, func: &FuncDecl) -> Helper {
+    let body = file.body_tokens(func);
+    let mut helper = Helper {
+        subtests: find_subtests(file, body),
+        param_names: Vec::new(),
+    };
+
+    let param = |name: &str| {
+        func.params
+            .iter()
+            .position(|param| param.name.as_deref() == Some(name))
+    };
+    // A range over a parameter, `for _, c := range cases {`.
+    let ranged_param = |binding: &RangeBinding| {
+        let expr = body.get(binding.expr)?;
+        let brace = body.get(binding.expr + 1)?;
+        brace.is_punct("{").then(|| param(&expr.text)).flatten()
+    };
+
+    for i in 0..body.len().saturating_sub(2) {
+        if !(body[i].is_punct(".") && body[i + 1].is_ident("Run") && body[i + 2].is_punct("(")) {
+            continue;
+        }
+        let Some(close) = parser::matching(body, i + 2) else {
+            break;
+        };
+        let args = &body[i + 3..close];
+        let name = match args.iter().position(|token| token.is_punct(",")) {
+            Some(comma) => &args[..comma],
+            None => args,
+        };
+
+        let found = match name {
+            [var] if var.kind == TokenKind::Ident => match range_binding(body, i, &var.text) {
+                Some(binding) if binding.is_key => {
+                    ranged_param(&binding).map(|param| (param, NameSource::Key))
+                }
+                Some(_) => None,
+                None => param(&var.text).map(|param| (param, NameSource::Value)),
+            },
+            [var, dot, field] if var.kind == TokenKind::Ident && dot.is_punct(".") => {
+                range_binding(body, i, &var.text)
+                    .filter(|binding| !binding.is_key)
+                    .and_then(|binding| ranged_param(&binding))
+                    .map(|param| (param, NameSource::Field(field.text.clone())))
+            }
+            _ => None,
+        };
+
+        if let Some((param, source)) = found {
+            helper.param_names.push(ParamName { param, source });
+        }
+    }
+
+    helper
+}
+
+/// A call from a test body to a function that may be a subtest helper,
+/// with whatever its arguments resolve to in the caller.
+pub struct HelperCall {
+    name: String,
+    args: Vec<CallArg>,
+}
+
+struct CallArg {
+    literal: Option<String>,
+    table: Option<Table>,
+}
+
+/// Finds the calls in a test body that pass the test's `t` on to a plain
+/// function, outside of any `t.Run` closure.
+pub fn find_helper_calls(file: &GoFile, body: &[Token], t: &str) -> Vec<HelperCall> {
+    let mut calls = Vec::new();
+    let mut i = 0;
+
+    while i + 2 < body.len() {
+        if body[i].is_punct(".") && body[i + 1].is_ident("Run") && body[i + 2].is_punct("(") {
+            let Some(close) = parser::matching(body, i + 2) else {
+                break;
+            };
+            i = close + 1;
+            continue;
+        }
+
+        let callee = &body[i];
+        let qualified = i > 0 && (body[i - 1].is_punct(".") || body[i - 1].is_ident("func"));
+        if callee.kind != TokenKind::Ident || !body[i + 1].is_punct("(") || qualified {
+            i += 1;
+            continue;
+        }
+
+        let Some(close) = parser::matching(body, i + 1) else {
+            break;
+        };
+        let args = call_args(body, i + 1, close);
+        if args
+            .iter()
+            .any(|arg| matches!(&body[arg.clone()], [arg] if arg.is_ident(t)))
+        {
+            calls.push(HelperCall {
+                name: callee.text.clone(),
+                args: args
+                    .into_iter()
+                    .map(|arg| CallArg {
+                        literal: match &body[arg.clone()] {
+                            [value] if value.is_string() => Some(value.text.clone()),
+                            [value] if value.kind == TokenKind::Ident => {
+                                string_value(file, body, i, &value.text, 0)
+                            }
+                            _ => None,
+                        },
+                        table: table(file, body, arg.start),
+                    })
+                    .collect(),
+            });
+        }
+
+        i += 1;
+    }
+
+    calls
+}
+
+/// Returns the token ranges of the arguments of the call whose parentheses
+/// are at `open` and `close`.
+fn call_args(body: &[Token], open: usize, close: usize) -> Vec<Range<usize>> {
+    let mut args = Vec::new();
+    let mut depth = 0usize;
+    let mut start = open + 1;
+
+    for (i, token) in body.iter().enumerate().take(close).skip(open + 1) {
+        if token.kind != TokenKind::Punct {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            "," if depth == 0 => {
+                args.push(start..i);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(start..close);
+    args.retain(|arg| !arg.is_empty());
+
+    args
+}
+
+/// Expands calls to package helpers into the subtests they run.
+pub fn expand_helper_calls<'a>(
+    calls: &[HelperCall],
+    helper: impl Fn(&str) -> Option<&'a Helper>,
+) -> Vec<Subtest> {
+    let mut subtests = Vec::new();
+
+    for call in calls {
+        let Some(helper) = helper(&call.name) else {
+            continue;
+        };
+        subtests.extend(helper.subtests.iter().cloned());
+
+        for name in &helper.param_names {
+            let Some(arg) = call.args.get(name.param) else {
+                continue;
+            };
+            let names = match &name.source {
+                NameSource::Value => arg.literal.iter().cloned().collect(),
+                NameSource::Field(field) => arg
+                    .table
+                    .as_ref()
+                    .map(|table| table.field_values(field))
+                    .unwrap_or_default(),
+                NameSource::Key => arg
+                    .table
+                    .as_ref()
+                    .map(|table| table.keys.clone())
+                    .unwrap_or_default(),
+            };
+            subtests.extend(names.iter().map(|name| Subtest::new(rewrite(name))));
+        }
+    }
+
+    subtests
+}
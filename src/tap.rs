@@ -0,0 +1,97 @@
+//! Test Anything Protocol output of a run's results, for `--output tap`.
+//!
+//! Each top-level test is one test point, with its subtests folded in: a
+//! test fails when one of its subtests does, and the output of the failures
+//! goes into the point's YAML diagnostics. A package failing without a failed
+//! test, such as one that does not build, is a failed point of its own.
+
+use crate::testjson::{Status, TestResult};
+use std::fmt::Write as _;
+
+pub fn render(results: &[TestResult]) -> String {
+    let mut points = Vec::new();
+    for result in results {
+        match result.test.as_deref() {
+            Some(test) if !test.contains('/') => points.push(result),
+            Some(_) => {}
+            None => {
+                let failed_test = results.iter().any(|other| {
+                    other.package == result.package
+                        && other.test.is_some()
+                        && other.status == Status::Fail
+                });
+                if result.status == Status::Fail && !failed_test {
+                    points.push(result);
+                }
+            }
+        }
+    }
+
+    let mut tap = format!("TAP version 13\n1..{}\n", points.len());
+    for (number, point) in points.iter().enumerate() {
+        let name = match &point.test {
+            Some(test) => format!("{} [{}]", test, point.package),
+            None => format!("{} (package failed)", point.package),
+        };
+        let name = name.replace('#', "\\#");
+
+        match point.status {
+            Status::Pass => {
+                let _ = writeln!(tap, "ok {} - {}", number + 1, name);
+            }
+            Status::Skip => {
+                let reason = point
+                    .output
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    tap,
+                    "ok {} - {} # SKIP{}{}",
+                    number + 1,
+                    name,
+                    if reason.is_empty() { "" } else { " " },
+                    reason
+                );
+            }
+            Status::Fail => {
+                let _ = writeln!(tap, "not ok {} - {}", number + 1, name);
+                let _ = writeln!(tap, "  ---");
+                let _ = writeln!(tap, "  package: {}", point.package);
+                let _ = writeln!(tap, "  duration_ms: {:.0}", point.elapsed * 1000.0);
+                let output = failure_output(results, point);
+                if !output.trim().is_empty() {
+                    let _ = writeln!(tap, "  output: |");
+                    for line in output.lines() {
+                        let _ = writeln!(tap, "    {}", line);
+                    }
+                }
+                let _ = writeln!(tap, "  ...");
+            }
+        }
+    }
+    tap
+}
+
+/// What a failed test and its failed subtests printed, or a failed package
+/// outside its tests.
+fn failure_output(results: &[TestResult], point: &TestResult) -> String {
+    let Some(test) = &point.test else {
+        return point.output.clone();
+    };
+    results
+        .iter()
+        .filter(|result| {
+            result.package == point.package
+                && result.status == Status::Fail
+                && result.test.as_deref().is_some_and(|name| {
+                    name == test
+                        || name
+                            .strip_prefix(test.as_str())
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+        })
+        .map(|result| result.output.as_str())
+        .collect()
+}
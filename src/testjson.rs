@@ -0,0 +1,540 @@
+//! Reading and rendering the event stream of `go test -json`.
+//!
+//! Each line of `go test -json` output is one test2json event. Events are
+//! rendered as they arrive: one colored PASS/FAIL/SKIP line per finished test
+//! with its duration, the output of failing tests, and a result line per
+//! package. The output of passing tests is only shown with `-v`.
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+/// One test2json event.
+#[derive(Debug, Default)]
+pub struct Event {
+    pub action: String,
+    pub package: String,
+    /// Test name, with subtests as `TestX/sub`; `None` for package events.
+    pub test: Option<String>,
+    /// Seconds the test or package took, on pass, fail and skip events.
+    pub elapsed: Option<f64>,
+    pub output: Option<String>,
+}
+
+/// Parses a line of `go test -json` output, or returns `None` for anything
+/// else `go` printed.
+pub fn parse_event(line: &str) -> Option<Event> {
+    let mut event = Event::default();
+
+    for (key, value) in parse_object(line.trim())? {
+        match (key.as_str(), value) {
+            ("Action", Value::String(action)) => event.action = action,
+            // Build output of Go 1.24 and later carries an import path.
+            ("Package" | "ImportPath", Value::String(package)) => event.package = package,
+            ("Test", Value::String(test)) => event.test = Some(test),
+            ("Elapsed", Value::Number(elapsed)) => event.elapsed = Some(elapsed),
+            ("Output", Value::String(output)) => event.output = Some(output),
+            _ => {}
+        }
+    }
+
+    (!event.action.is_empty()).then_some(event)
+}
+
+enum Value {
+    String(String),
+    Number(f64),
+    Other,
+}
+
+/// Parses a JSON object into its members. Nested objects and arrays are
+/// skipped; test2json events do not use them.
+fn parse_object(text: &str) -> Option<Vec<(String, Value)>> {
+    let mut chars = text.chars().peekable();
+    let mut members = Vec::new();
+
+    skip_whitespace(&mut chars);
+    if chars.next()? != '{' {
+        return None;
+    }
+    skip_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+        return Some(members);
+    }
+
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.next()? != '"' {
+            return None;
+        }
+        let key = parse_string(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_whitespace(&mut chars);
+        let value = parse_value(&mut chars)?;
+        members.push((key, value));
+
+        skip_whitespace(&mut chars);
+        match chars.next()? {
+            ',' => continue,
+            '}' => break,
+            _ => return None,
+        }
+    }
+
+    skip_whitespace(&mut chars);
+    chars.next().is_none().then_some(members)
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_whitespace(chars: &mut Chars) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_value(chars: &mut Chars) -> Option<Value> {
+    match *chars.peek()? {
+        '"' => {
+            chars.next();
+            parse_string(chars).map(Value::String)
+        }
+        '{' | '[' => {
+            skip_nested(chars)?;
+            Some(Value::Other)
+        }
+        _ => {
+            let mut literal = String::new();
+            while let Some(c) =
+                chars.next_if(|c| !matches!(c, ',' | '}' | ']') && !c.is_whitespace())
+            {
+                literal.push(c);
+            }
+            match literal.as_str() {
+                "true" | "false" | "null" => Some(Value::Other),
+                number => number.parse().ok().map(Value::Number),
+            }
+        }
+    }
+}
+
+/// Reads the rest of a string whose opening quote was consumed.
+fn parse_string(chars: &mut Chars) -> Option<String> {
+    let mut value = String::new();
+
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'b' => value.push('\u{8}'),
+                'f' => value.push('\u{c}'),
+                'u' => {
+                    let high = parse_hex(chars)?;
+                    let code = if (0xd800..0xdc00).contains(&high) {
+                        // A surrogate pair encodes a character outside the BMP.
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = parse_hex(chars)?;
+                        0x10000 + ((high - 0xd800) << 10) + (low.checked_sub(0xdc00)?)
+                    } else {
+                        high
+                    };
+                    value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+fn parse_hex(chars: &mut Chars) -> Option<u32> {
+    let digits: String = (0..4).map(|_| chars.next()).collect::<Option<_>>()?;
+    u32::from_str_radix(&digits, 16).ok()
+}
+
+/// Skips an object or array, including any strings inside it.
+fn skip_nested(chars: &mut Chars) -> Option<()> {
+    let mut depth = 0;
+
+    loop {
+        match chars.next()? {
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(());
+                }
+            }
+            '"' => {
+                parse_string(chars)?;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// ANSI colors for the rendered results, used when stdout is a terminal and
+/// `NO_COLOR` is not set.
+#[derive(Clone, Copy)]
+pub struct Colors {
+    enabled: bool,
+}
+
+impl Colors {
+    pub fn detect() -> Colors {
+        Colors {
+            enabled: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    pub fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn green(&self, text: &str) -> String {
+        self.paint("32", text)
+    }
+
+    pub fn red(&self, text: &str) -> String {
+        self.paint("31", text)
+    }
+
+    pub fn yellow(&self, text: &str) -> String {
+        self.paint("33", text)
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint("2", text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Fail,
+    Skip,
+}
+
+/// The result of a finished test, or of a whole package.
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub package: String,
+    /// `None` for the package's own result.
+    pub test: Option<String>,
+    pub status: Status,
+    pub elapsed: f64,
+    /// What a failed or skipped test printed, such as its failure message or
+    /// the reason it skipped; empty for passed tests.
+    pub output: String,
+}
+
+/// Prints `text`, starting each of its lines with `prefix`. Lines are
+/// written whole so that those of runs printing at once do not mix.
+pub fn print_prefixed(prefix: Option<&str>, text: &str) {
+    match prefix {
+        Some(prefix) => crate::logfile::print(
+            &text
+                .split_inclusive('\n')
+                .map(|line| format!("{}{}", prefix, line))
+                .collect::<String>(),
+        ),
+        None => crate::logfile::print(text),
+    }
+}
+
+/// Renders events as they arrive and collects the results.
+pub struct Renderer {
+    verbose: bool,
+    colors: Colors,
+    /// Printed before each line, to tell runs printing at once apart.
+    prefix: Option<String>,
+    /// Output of running tests, keyed by package and test name, shown once
+    /// the test fails. Package-level output is kept under an empty name.
+    output: HashMap<(String, String), Vec<String>>,
+    results: Vec<TestResult>,
+}
+
+impl Renderer {
+    pub fn new(verbose: bool, prefix: Option<String>) -> Renderer {
+        Renderer {
+            verbose,
+            colors: Colors::detect(),
+            prefix,
+            output: HashMap::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// The results of the tests and packages that finished, in order.
+    pub fn into_results(self) -> Vec<TestResult> {
+        self.results
+    }
+
+    pub fn handle(&mut self, event: &Event) {
+        crate::live::handle(event);
+        let test = event.test.as_deref().unwrap_or_default();
+        let key = (event.package.clone(), test.to_string());
+
+        let status = match event.action.as_str() {
+            "pass" | "bench" => Some(Status::Pass),
+            "fail" => Some(Status::Fail),
+            "skip" => Some(Status::Skip),
+            _ => None,
+        };
+        if let Some(status) = status {
+            let output = match status {
+                Status::Pass => String::new(),
+                _ => self
+                    .output
+                    .get(&key)
+                    .map(|lines| lines.concat())
+                    .unwrap_or_default(),
+            };
+            self.results.push(TestResult {
+                package: event.package.clone(),
+                test: event.test.clone(),
+                status,
+                elapsed: event.elapsed.unwrap_or_default(),
+                output,
+            });
+        }
+
+        match event.action.as_str() {
+            "output" => {
+                let Some(output) = &event.output else {
+                    return;
+                };
+                if is_framing(output) {
+                    return;
+                }
+                // Benchmark results are printed as they come in.
+                if self.verbose || test.starts_with("Benchmark") {
+                    if !test.is_empty() || !is_package_summary(output) {
+                        self.print(output);
+                    }
+                    if test.starts_with("Benchmark") {
+                        return;
+                    }
+                }
+                // Kept for the results even when already printed.
+                self.output.entry(key).or_default().push(output.clone());
+            }
+            "build-output" => {
+                if let Some(output) = &event.output {
+                    self.print(output);
+                }
+            }
+            "pass" | "fail" | "skip" if test.is_empty() => self.finish_package(event, &key),
+            "pass" | "fail" | "skip" | "bench" => {
+                let output = self.output.remove(&key).unwrap_or_default();
+                let status = match event.action.as_str() {
+                    "pass" | "bench" => self.colors.green("PASS"),
+                    "fail" => self.colors.red("FAIL"),
+                    _ => self.colors.yellow("SKIP"),
+                };
+                let depth = test.matches('/').count();
+                let elapsed = event
+                    .elapsed
+                    .map(|elapsed| self.colors.dim(&format!(" ({:.2}s)", elapsed)))
+                    .unwrap_or_default();
+                let mut text = format!("{}{} {}{}\n", "    ".repeat(depth), status, test, elapsed);
+
+                // go indents test output below the test's own line.
+                if event.action == "fail" && !self.verbose {
+                    text.extend(output);
+                }
+                self.print(&text);
+            }
+            _ => {}
+        }
+    }
+
+    fn print(&self, text: &str) {
+        print_prefixed(self.prefix.as_deref(), text);
+    }
+
+    fn finish_package(&mut self, event: &Event, key: &(String, String)) {
+        let output = self.output.remove(key).unwrap_or_default();
+        let elapsed = event
+            .elapsed
+            .map(|elapsed| format!(" {:.3}s", elapsed))
+            .unwrap_or_default();
+
+        match event.action.as_str() {
+            "pass" => self.print(&format!(
+                "{}  {}{}\n",
+                self.colors.green("ok  "),
+                event.package,
+                elapsed
+            )),
+            "skip" => self.print(&format!(
+                "{}  {} [no test files]\n",
+                self.colors.dim("?   "),
+                event.package
+            )),
+            _ => {
+                // Output outside any test, such as a panic in TestMain.
+                let shown = if self.verbose { &[][..] } else { &output[..] };
+                for line in shown.iter().filter(|line| !is_package_summary(line)) {
+                    self.print(line);
+                }
+                self.print(&format!(
+                    "{}  {}{}\n",
+                    self.colors.red("FAIL"),
+                    event.package,
+                    elapsed
+                ));
+            }
+        }
+
+        // Tests left without a result, such as those running when a test
+        // binary crashed, are never finished; drop what they printed.
+        self.output
+            .retain(|(package, _), _| *package != event.package);
+    }
+}
+
+/// The `=== RUN` and `--- PASS` lines `go test -v` frames tests with, which
+/// the rendered result lines replace.
+fn is_framing(output: &str) -> bool {
+    let line = output.trim_start();
+    ["=== ", "--- PASS", "--- FAIL", "--- SKIP", "--- BENCH"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// The lines a test binary ends with, which the package line replaces.
+fn is_package_summary(output: &str) -> bool {
+    let line = output.trim_end();
+    line == "PASS"
+        || line == "FAIL"
+        || line.starts_with("ok  \t")
+        || line.starts_with("FAIL\t")
+        || line.starts_with("?   \t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `go test -json` lines to a renderer and collects the results.
+    fn results(lines: &[&str]) -> Vec<TestResult> {
+        let mut renderer = Renderer::new(false, None);
+        for event in lines.iter().filter_map(|line| parse_event(line)) {
+            renderer.handle(&event);
+        }
+        renderer.into_results()
+    }
+
+    #[test]
+    fn parses_events() {
+        let event = parse_event(
+            r#"{"Time":"2024-01-02T03:04:05Z","Action":"pass","Package":"example.com/a","Test":"TestX/sub","Elapsed":0.25}"#,
+        )
+        .unwrap();
+        assert_eq!(event.action, "pass");
+        assert_eq!(event.package, "example.com/a");
+        assert_eq!(event.test.as_deref(), Some("TestX/sub"));
+        assert_eq!(event.elapsed, Some(0.25));
+        assert!(parse_event("FAIL\texample.com/a [build failed]").is_none());
+        assert!(parse_event(r#"{"Action":"pass""#).is_none());
+        assert!(parse_event(r#"{"Package":"example.com/a"}"#).is_none());
+    }
+
+    #[test]
+    fn decodes_escapes_and_surrogate_pairs() {
+        let event = parse_event(r#"{"Action":"output","Output":"\"q\" \\ \té 😀\n"}"#).unwrap();
+        assert_eq!(event.output.as_deref(), Some("\"q\" \\ \té 😀\n"));
+        assert!(parse_event(r#"{"Action":"output","Output":"\ud83d"}"#).is_none());
+    }
+
+    #[test]
+    fn skips_nested_values() {
+        let event = parse_event(
+            r#"{"Action":"output","Extra":{"a":[1,{"b":"}]"}],"c":null},"Flags":[true,false],"Output":"x\n"}"#,
+        )
+        .unwrap();
+        assert_eq!(event.output.as_deref(), Some("x\n"));
+    }
+
+    #[test]
+    fn build_output_carries_the_import_path() {
+        let event = parse_event(
+            r#"{"ImportPath":"example.com/a [example.com/a.test]","Action":"build-output","Output":"a_test.go:3:1: syntax error\n"}"#,
+        )
+        .unwrap();
+        assert_eq!(event.action, "build-output");
+        assert_eq!(event.package, "example.com/a [example.com/a.test]");
+        assert!(results(&[
+            r#"{"ImportPath":"example.com/a [example.com/a.test]","Action":"build-output","Output":"a_test.go:3:1: syntax error\n"}"#,
+            r#"{"ImportPath":"example.com/a [example.com/a.test]","Action":"build-fail"}"#,
+        ])
+        .is_empty());
+    }
+
+    #[test]
+    fn collects_test_results() {
+        let results = results(&[
+            r#"{"Action":"start","Package":"example.com/a"}"#,
+            r#"{"Action":"run","Package":"example.com/a","Test":"TestOk"}"#,
+            r#"{"Action":"output","Package":"example.com/a","Test":"TestOk","Output":"=== RUN   TestOk\n"}"#,
+            r#"{"Action":"output","Package":"example.com/a","Test":"TestOk","Output":"    log line\n"}"#,
+            r#"{"Action":"pass","Package":"example.com/a","Test":"TestOk","Elapsed":0.01}"#,
+            r#"{"Action":"run","Package":"example.com/a","Test":"TestBad"}"#,
+            r#"{"Action":"output","Package":"example.com/a","Test":"TestBad/sub","Output":"    a_test.go:9: want 1\n"}"#,
+            r#"{"Action":"output","Package":"example.com/a","Test":"TestBad/sub","Output":"--- FAIL: TestBad/sub (0.00s)\n"}"#,
+            r#"{"Action":"fail","Package":"example.com/a","Test":"TestBad/sub","Elapsed":0}"#,
+            r#"{"Action":"fail","Package":"example.com/a","Test":"TestBad","Elapsed":0.02}"#,
+            r#"{"Action":"output","Package":"example.com/a","Test":"TestSkip","Output":"    a_test.go:12: needs a database\n"}"#,
+            r#"{"Action":"skip","Package":"example.com/a","Test":"TestSkip","Elapsed":0}"#,
+            r#"{"Action":"output","Package":"example.com/a","Output":"FAIL\n"}"#,
+            r#"{"Action":"fail","Package":"example.com/a","Elapsed":0.5}"#,
+        ]);
+        let summary: Vec<(Option<&str>, Status)> = results
+            .iter()
+            .map(|result| (result.test.as_deref(), result.status))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Some("TestOk"), Status::Pass),
+                (Some("TestBad/sub"), Status::Fail),
+                (Some("TestBad"), Status::Fail),
+                (Some("TestSkip"), Status::Skip),
+                (None, Status::Fail),
+            ]
+        );
+        assert_eq!(results[0].output, "");
+        assert_eq!(results[0].elapsed, 0.01);
+        assert_eq!(results[1].output, "    a_test.go:9: want 1\n");
+        assert_eq!(results[3].output, "    a_test.go:12: needs a database\n");
+        assert_eq!(results[4].package, "example.com/a");
+        assert_eq!(results[4].elapsed, 0.5);
+    }
+
+    #[test]
+    fn package_failures_keep_their_output() {
+        let results = results(&[
+            r#"{"Action":"output","Package":"example.com/a","Output":"panic: setup failed\n"}"#,
+            r#"{"Action":"output","Package":"example.com/a","Output":"FAIL\texample.com/a\t0.01s\n"}"#,
+            r#"{"Action":"fail","Package":"example.com/a","Elapsed":0.01}"#,
+            r#"{"Action":"pass","Package":"example.com/b","Elapsed":0.02}"#,
+        ]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].test, None);
+        assert_eq!(results[0].status, Status::Fail);
+        assert!(results[0].output.starts_with("panic: setup failed\n"));
+        assert_eq!(results[1].package, "example.com/b");
+        assert_eq!(results[1].status, Status::Pass);
+    }
+}
@@ -0,0 +1,111 @@
+//! Selector colors, for `--theme`.
+//!
+//! A theme is `dark`, `light`, `none` or a color string in the format skim
+//! and fzf share, such as `dark,matched:108`. Without one, the terminal's
+//! background decides between the dark and light schemes: `COLORFGBG` names
+//! its color when the terminal sets it, and otherwise the terminal is asked
+//! with an OSC 11 query.
+
+/// The color option for the selector, or `None` to leave its default.
+pub fn resolve(theme: Option<&str>) -> Option<String> {
+    match theme {
+        Some("none") => Some("bw".to_string()),
+        Some(theme) => Some(theme.to_string()),
+        None => {
+            detect_light_background().map(|light| if light { "light" } else { "dark" }.to_string())
+        }
+    }
+}
+
+/// Whether colors are off.
+pub fn is_plain(color: Option<&str>) -> bool {
+    color == Some("bw")
+}
+
+fn detect_light_background() -> Option<bool> {
+    colorfgbg().or_else(query_background)
+}
+
+/// `COLORFGBG` is `FG;BG`, sometimes with a middle field, in the 16 ANSI
+/// colors, of which 7 and 9 to 15 are light.
+fn colorfgbg() -> Option<bool> {
+    let value = std::env::var("COLORFGBG").ok()?;
+    let background: u8 = value.rsplit(';').next()?.parse().ok()?;
+    Some(matches!(background, 7 | 9..=15))
+}
+
+/// Asks the terminal for its background color, which it answers as
+/// `ESC ] 11 ; rgb:RRRR/GGGG/BBBB` ended by BEL or ST.
+#[cfg(unix)]
+fn query_background() -> Option<bool> {
+    use std::io::{Read, Write};
+    use std::os::fd::AsRawFd;
+
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let fd = tty.as_raw_fd();
+
+    // SAFETY: `termios` is plain data filled in by tcgetattr, and only a
+    // copy of it is modified and applied.
+    let original = unsafe {
+        let mut original: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut original) != 0 {
+            return None;
+        }
+        let mut raw = original;
+        libc::cfmakeraw(&mut raw);
+        if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+            return None;
+        }
+        original
+    };
+
+    let mut reply = Vec::new();
+    if tty.write_all(b"\x1b]11;?\x07").is_ok() {
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let mut byte = [0];
+        // Terminals that do not support the query stay silent.
+        // SAFETY: polls the single descriptor given.
+        while unsafe { libc::poll(&mut poll, 1, 100) } > 0 && tty.read_exact(&mut byte).is_ok() {
+            reply.push(byte[0]);
+            if byte[0] == 0x07 || reply.ends_with(b"\x1b\\") {
+                break;
+            }
+        }
+    }
+
+    // SAFETY: restores the settings read above.
+    unsafe {
+        libc::tcsetattr(fd, libc::TCSANOW, &original);
+    }
+
+    let reply = String::from_utf8_lossy(&reply);
+    let rgb = reply.split("rgb:").nth(1)?;
+    let rgb = rgb.trim_end_matches(['\x07', '\x1b', '\\']);
+    let channels: Vec<f64> = rgb
+        .split('/')
+        .map(|channel| {
+            if channel.is_empty() || channel.len() > 4 {
+                return None;
+            }
+            let value = u32::from_str_radix(channel, 16).ok()?;
+            Some(f64::from(value) / f64::from((1u32 << (4 * channel.len())) - 1))
+        })
+        .collect::<Option<_>>()?;
+    let [red, green, blue] = channels[..] else {
+        return None;
+    };
+    Some(0.299 * red + 0.587 * green + 0.114 * blue > 0.5)
+}
+
+#[cfg(not(unix))]
+fn query_background() -> Option<bool> {
+    None
+}
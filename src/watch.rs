@@ -0,0 +1,96 @@
+//! Waiting for changes to the files tests are built from, for `watch`.
+//!
+//! The directory is polled twice a second. A change is reported once the
+//! files have stopped changing for a moment, so that saving several files at
+//! once or a formatter rewriting them leads to a single rerun.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the files must stay unchanged before a change is reported.
+const QUIET_PERIOD: Duration = Duration::from_millis(300);
+
+/// Files whose changes can affect a test run, besides those in `testdata`.
+const WATCHED_EXTENSIONS: &[&str] = &["go", "s", "c", "h", "syso"];
+const WATCHED_FILES: &[&str] = &["go.mod", "go.sum", "go.work", "go.work.sum"];
+
+pub struct Watcher {
+    dir: PathBuf,
+    snapshot: HashMap<PathBuf, SystemTime>,
+}
+
+impl Watcher {
+    /// Starts watching `dir`; only changes made from now on are reported.
+    pub fn new(dir: &Path) -> Watcher {
+        Watcher {
+            dir: dir.to_path_buf(),
+            snapshot: scan(dir),
+        }
+    }
+
+    /// Blocks until watched files were added, removed or modified and have
+    /// settled.
+    pub fn wait(&mut self) {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let snapshot = scan(&self.dir);
+            if snapshot != self.snapshot {
+                self.snapshot = snapshot;
+                break;
+            }
+        }
+
+        loop {
+            std::thread::sleep(QUIET_PERIOD);
+            let snapshot = scan(&self.dir);
+            if snapshot == self.snapshot {
+                return;
+            }
+            self.snapshot = snapshot;
+        }
+    }
+}
+
+/// The modification times of the watched files below `dir`. Hidden
+/// directories, such as `.git`, are left out.
+fn scan(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    let walker = WalkDir::new(dir).into_iter().filter_entry(|entry| {
+        entry.depth() == 0
+            || !entry.file_type().is_dir()
+            || !entry.file_name().to_string_lossy().starts_with('.')
+    });
+
+    walker
+        .flatten()
+        .filter(|entry| entry.file_type().is_file() && is_watched(dir, entry.path()))
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((entry.path().to_path_buf(), modified))
+        })
+        .collect()
+}
+
+/// Go sources and module files, and test data read by the tests. The fuzz
+/// corpus in `testdata/fuzz` is not watched: fuzzing writes new entries to
+/// it, which would trigger another run.
+fn is_watched(dir: &Path, path: &Path) -> bool {
+    let relative: Vec<_> = path
+        .strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+    if let Some(index) = relative.iter().position(|name| name == "testdata") {
+        return relative.get(index + 1).is_some_and(|name| name != "fuzz");
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    WATCHED_FILES.contains(&name.as_ref())
+        || path
+            .extension()
+            .is_some_and(|ext| WATCHED_EXTENSIONS.iter().any(|e| ext == *e))
+}
@@ -0,0 +1,86 @@
+//! Temporary git worktrees, for running tests on another revision with
+//! `--base-ref`.
+//!
+//! The worktree is checked out detached in the temporary directory and
+//! removed again, along with git's record of it, when dropped.
+
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub struct Worktree {
+    /// Root of the repository the current directory is in.
+    repo: PathBuf,
+    path: PathBuf,
+}
+
+impl Worktree {
+    /// Checks out `git_ref` of the repository containing the current
+    /// directory.
+    pub fn add(git_ref: &str) -> Result<Worktree> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .context("Failed to run git")?;
+        if !output.status.success() {
+            bail!("--base-ref needs to be run inside a git repository");
+        }
+        let repo = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+        let repo = repo.canonicalize().unwrap_or(repo);
+
+        let path = std::env::temp_dir().join(format!("gotestfinder-base-{}", std::process::id()));
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["worktree", "add", "--detach", "--quiet"])
+            .arg(&path)
+            .arg(git_ref)
+            .stdout(Stdio::null())
+            .status()
+            .context("Failed to run git")?;
+        if !status.success() {
+            bail!("Failed to check out {} into a worktree", git_ref);
+        }
+
+        Ok(Worktree { repo, path })
+    }
+
+    /// Where `dir` of the repository, the current directory by default, is
+    /// in the worktree.
+    pub fn map(&self, dir: Option<&Path>) -> Result<PathBuf> {
+        let dir = match dir {
+            Some(dir) => dir.to_path_buf(),
+            None => std::env::current_dir()?,
+        };
+        let dir = dir.canonicalize().unwrap_or(dir);
+        let relative = dir
+            .strip_prefix(&self.repo)
+            .with_context(|| format!("{} is outside of the repository", dir.display()))?;
+        if relative.as_os_str().is_empty() {
+            return Ok(self.path.clone());
+        }
+        Ok(self.path.join(relative))
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let removed = Command::new("git")
+            .arg("-C")
+            .arg(&self.repo)
+            .args(["worktree", "remove", "--force"])
+            .arg(&self.path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if !removed {
+            let _ = std::fs::remove_dir_all(&self.path);
+            let _ = Command::new("git")
+                .arg("-C")
+                .arg(&self.repo)
+                .args(["worktree", "prune"])
+                .status();
+        }
+    }
+}